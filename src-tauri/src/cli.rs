@@ -0,0 +1,582 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::ascii_art::{get_available_ascii_char_sets, render_ascii_art};
+use crate::commands::image_analyze::AiImageDetector;
+use crate::models::cli::{CliFileResult, ImageInfo, WrittenFile};
+use crate::models::config::Config;
+use crate::models::image::{AiDetectionResult, ExportOptions};
+use crate::utils::archive::{read_image_bytes, storage_path};
+use crate::utils::decode_limits::DecodeLimits;
+use crate::utils::image_processing::{
+    get_supported_image_formats, read_image_metadata, save_image_to_format,
+};
+
+/// Every file was processed.
+const EXIT_SUCCESS: i32 = 0;
+/// At least one file could not be processed.
+const EXIT_FAILURE: i32 = 1;
+/// The command line could not be understood.
+const EXIT_USAGE: i32 = 2;
+/// `detect-ai` found signs of AI generation in at least one file.
+const EXIT_AI_DETECTED: i32 = 3;
+
+const USAGE: &str = "\
+Usage: blickfang <command> [options] <files...>
+       blickfang [viewer options] [files, folders or archives...]
+
+Commands:
+  info        Print the metadata of images
+  convert     Save images in another format
+                --format=<format>    The format to save in (required)
+                --quality=<0-100>    The quality of lossy formats
+                --output=<dir>       The folder to write to, next to each image by default
+                --force              Overwrite existing files
+  ascii       Render images as ASCII art PNG files
+                --chars=<set>        The character set, as configured by default
+                --background=<color> A color like #000000, or 'auto'
+                --output=<dir>       The folder to write to, next to each image by default
+                --force              Overwrite existing files
+  detect-ai   Check images for signs of AI generation
+
+Options:
+  --json      Print the results as JSON
+  -h, --help  Print this help
+
+Viewer options:
+  --fullscreen           Start in fullscreen
+  --zen                  Start in zen mode
+  --slideshow=<secs>     Move on to the next image every <secs> seconds
+  --start-at=<file>      The image to show first
+
+Exit codes:
+  0  All files were processed
+  1  At least one file could not be processed
+  2  The command line is invalid
+  3  detect-ai found signs of AI generation in at least one file
+";
+
+/// A subcommand run without creating a window.
+#[derive(Clone, Copy)]
+enum Subcommand {
+    Info,
+    Convert,
+    Ascii,
+    DetectAi,
+}
+
+impl Subcommand {
+    /// Returns the subcommand with the given name.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "info" => Some(Self::Info),
+            "convert" => Some(Self::Convert),
+            "ascii" => Some(Self::Ascii),
+            "detect-ai" => Some(Self::DetectAi),
+            _ => None,
+        }
+    }
+
+    /// Returns the options the subcommand accepts, besides `--json` and `--help`.
+    fn options(self) -> &'static [&'static str] {
+        match self {
+            Self::Info | Self::DetectAi => &[],
+            Self::Convert => &["format", "quality", "output"],
+            Self::Ascii => &["chars", "background", "output"],
+        }
+    }
+
+    /// Returns `true` if the subcommand writes files, and so accepts `--force`.
+    fn writes_files(self) -> bool {
+        matches!(self, Self::Convert | Self::Ascii)
+    }
+}
+
+/// The options and files given to a subcommand.
+struct Invocation {
+    options: HashMap<String, String>,
+    json: bool,
+    force: bool,
+    files: Vec<String>,
+}
+
+/// Runs a command-line subcommand, if the arguments start with one, without creating a window.
+///
+/// Results are printed to standard output, either human-readable or as a JSON array with
+/// `--json`, and errors about single files to standard error. An existing file named like a
+/// subcommand is opened in the viewer instead. `--help` or `-h` among the viewer arguments
+/// prints the usage instead of starting the viewer.
+///
+/// # Arguments
+/// * `args` - The command-line arguments, without the program name.
+///
+/// # Returns
+/// `Option<i32>` - The exit code of the subcommand, or `None` if the arguments name no subcommand
+/// and the viewer should start.
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    let name = args.first()?;
+    let subcommand = Subcommand::from_name(name).filter(|_| !Path::new(name).exists());
+    let Some(subcommand) = subcommand else {
+        let wants_help = (name == "help" && !Path::new(name).exists())
+            || args
+                .iter()
+                .take_while(|arg| *arg != "--")
+                .any(|arg| arg == "--help" || arg == "-h");
+        if !wants_help {
+            return None;
+        }
+        attach_parent_console();
+        print!("{}", USAGE);
+        return Some(EXIT_SUCCESS);
+    };
+    attach_parent_console();
+
+    let invocation = match parse_arguments(&args[1..], subcommand) {
+        Ok(Some(invocation)) => invocation,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return Some(EXIT_SUCCESS);
+        }
+        Err(e) => {
+            eprintln!("blickfang {}: {}\n\n{}", name, e, USAGE);
+            return Some(EXIT_USAGE);
+        }
+    };
+
+    let config = load_cli_config();
+    let result = match subcommand {
+        Subcommand::Info => Ok(run_info(&invocation, &config)),
+        Subcommand::Convert => run_convert(&invocation, &config),
+        Subcommand::Ascii => run_ascii(&invocation, config),
+        Subcommand::DetectAi => Ok(run_detect_ai(&invocation)),
+    };
+    Some(result.unwrap_or_else(|e| {
+        eprintln!("blickfang {}: {}", name, e);
+        EXIT_USAGE
+    }))
+}
+
+/// Attaches to the console of the shell the application was started from. Release builds on
+/// Windows are GUI applications that get no console of their own, so without this the output of
+/// a subcommand would be lost. Output that is already redirected to a file or pipe is kept.
+#[cfg(windows)]
+fn attach_parent_console() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+        fn GetStdHandle(std_handle: u32) -> *mut std::ffi::c_void;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    // `(DWORD)-11`
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+
+    // SAFETY: both functions take plain integers and have no other preconditions.
+    unsafe {
+        if GetStdHandle(STD_OUTPUT_HANDLE).is_null() {
+            AttachConsole(ATTACH_PARENT_PROCESS);
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
+
+/// Splits the arguments of a subcommand into `--name=value` options and files.
+/// Arguments after `--` are always files.
+///
+/// # Returns
+/// `Result<Option<Invocation>, String>` - The parsed arguments, `None` if help was requested,
+/// or an error string for unknown options and missing files.
+fn parse_arguments(args: &[String], subcommand: Subcommand) -> Result<Option<Invocation>, String> {
+    let allowed = subcommand.options();
+    let mut invocation = Invocation {
+        options: HashMap::new(),
+        json: false,
+        force: false,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            invocation.files.extend(args.by_ref().cloned());
+            break;
+        }
+        if arg == "-h" {
+            return Ok(None);
+        }
+        let Some(option) = arg.strip_prefix("--") else {
+            invocation.files.push(arg.clone());
+            continue;
+        };
+        match option.split_once('=') {
+            None if option == "json" => invocation.json = true,
+            None if option == "help" => return Ok(None),
+            None if option == "force" && subcommand.writes_files() => invocation.force = true,
+            Some((name, value)) if allowed.contains(&name) => {
+                invocation
+                    .options
+                    .insert(name.to_string(), value.to_string());
+            }
+            None if allowed.contains(&option) => {
+                return Err(format!("Option '--{}' needs a value", option));
+            }
+            _ => return Err(format!("Unknown option '{}'", arg)),
+        }
+    }
+
+    if invocation.files.is_empty() {
+        return Err("No files given".to_string());
+    }
+    Ok(Some(invocation))
+}
+
+/// Reads the configuration the viewer uses, so decoding limits and ASCII art settings match,
+/// falling back to the defaults if there is none. A missing configuration is not created.
+fn load_cli_config() -> Config {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".blickfang").join("config.json"))
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|config_str| serde_json::from_str::<Config>(&config_str).ok())
+        .unwrap_or_default()
+}
+
+/// Prints the metadata of every file.
+fn run_info(invocation: &Invocation, config: &Config) -> i32 {
+    let limits = DecodeLimits::from_config(config);
+    let results = invocation
+        .files
+        .iter()
+        .map(|file| {
+            let info = read_image_metadata(Path::new(file), &limits).map(ImageInfo::from);
+            (file.clone(), info)
+        })
+        .collect();
+    report(results, invocation.json, describe_info)
+}
+
+/// Saves every file in the requested format.
+fn run_convert(invocation: &Invocation, config: &Config) -> Result<i32, String> {
+    let format = invocation
+        .options
+        .get("format")
+        .map(|format| format.to_lowercase())
+        .ok_or_else(|| "Missing '--format=<format>'".to_string())?;
+    let formats = get_supported_image_formats()?;
+    if !formats.contains(&format) {
+        return Err(format!(
+            "Unsupported format '{}', expected one of: {}",
+            format,
+            formats.join(", ")
+        ));
+    }
+    let quality = invocation
+        .options
+        .get("quality")
+        .map(|quality| {
+            quality
+                .parse::<f32>()
+                .ok()
+                .filter(|quality| (0.0..=100.0).contains(quality))
+                .ok_or_else(|| format!("Invalid quality '{}', expected 0-100", quality))
+        })
+        .transpose()?;
+    let output_dir = output_directory(invocation)?;
+    let limits = DecodeLimits::from_config(config);
+    let options = ExportOptions {
+        quality,
+        ..ExportOptions::default()
+    };
+
+    let results = output_paths(invocation, output_dir.as_deref(), "", &format)
+        .into_iter()
+        .map(|(file, output)| {
+            let result = output.and_then(|output| {
+                let source = Path::new(&file);
+                let bytes = read_image_bytes(source, &limits)?;
+                save_image_to_format(&bytes, source, &output, &format, &options, &limits)
+                    .map(|output| WrittenFile { output })
+            });
+            (file, result)
+        })
+        .collect();
+    Ok(report(results, invocation.json, describe_written_file))
+}
+
+/// Renders every file as ASCII art and saves it as a PNG file.
+fn run_ascii(invocation: &Invocation, mut config: Config) -> Result<i32, String> {
+    if let Some(chars) = invocation.options.get("chars") {
+        let char_sets = get_available_ascii_char_sets()?;
+        if !char_sets.iter().any(|char_set| &char_set.id == chars) {
+            let ids: Vec<_> = char_sets
+                .iter()
+                .map(|char_set| char_set.id.as_str())
+                .collect();
+            return Err(format!(
+                "Unknown character set '{}', expected one of: {}",
+                chars,
+                ids.join(", ")
+            ));
+        }
+        config.ascii_chars = chars.clone();
+    }
+    match invocation.options.get("background").map(String::as_str) {
+        Some("auto") => config.ascii_auto_background = true,
+        Some(color) if is_hex_color(color) => {
+            config.ascii_auto_background = false;
+            config.ascii_background_color = color.to_string();
+        }
+        Some(color) => {
+            return Err(format!(
+                "Invalid background '{}', expected a color like #000000 or 'auto'",
+                color
+            ))
+        }
+        None => {}
+    }
+    let output_dir = output_directory(invocation)?;
+
+    let results = output_paths(invocation, output_dir.as_deref(), "_ascii", "png")
+        .into_iter()
+        .map(|(file, output)| {
+            let result = output.and_then(|output| {
+                render_ascii_art(Path::new(&file), &config)?
+                    .save_with_format(&output, image::ImageFormat::Png)
+                    .map_err(|e| format!("Failed to save '{}': {}", output.display(), e))?;
+                Ok(WrittenFile {
+                    output: output.to_string_lossy().to_string(),
+                })
+            });
+            (file, result)
+        })
+        .collect();
+    Ok(report(results, invocation.json, describe_written_file))
+}
+
+/// Checks every file for signs of AI generation.
+fn run_detect_ai(invocation: &Invocation) -> i32 {
+    let results: Vec<_> = invocation
+        .files
+        .iter()
+        .map(|file| (file.clone(), AiImageDetector::new(file.clone()).detect()))
+        .collect();
+    let ai_detected = results
+        .iter()
+        .any(|(_, result)| result.as_ref().is_ok_and(|r| r.is_ai_generated));
+
+    match report(results, invocation.json, describe_ai_detection) {
+        EXIT_SUCCESS if ai_detected => EXIT_AI_DETECTED,
+        code => code,
+    }
+}
+
+/// Prints the results of a subcommand and returns its exit code.
+///
+/// With `json`, all results are printed as a single JSON array. Otherwise, each file is printed
+/// on its own line, successful ones to standard output and failed ones to standard error.
+fn report<T: Serialize>(
+    results: Vec<(String, Result<T, String>)>,
+    json: bool,
+    describe: fn(&T) -> String,
+) -> i32 {
+    let failed = results.iter().any(|(_, result)| result.is_err());
+
+    if json {
+        let entries: Vec<_> = results
+            .into_iter()
+            .map(|(path, result)| match result {
+                Ok(result) => CliFileResult {
+                    path,
+                    ok: true,
+                    result: Some(result),
+                    error: None,
+                },
+                Err(error) => CliFileResult {
+                    path,
+                    ok: false,
+                    result: None,
+                    error: Some(error),
+                },
+            })
+            .collect();
+        match serde_json::to_string_pretty(&entries) {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                eprintln!("Failed to serialize results: {}", e);
+                return EXIT_FAILURE;
+            }
+        }
+    } else {
+        for (path, result) in results {
+            match result {
+                Ok(result) => println!("{}: {}", path, describe(&result)),
+                Err(error) => eprintln!("{}: error: {}", path, error),
+            }
+        }
+    }
+
+    if failed {
+        EXIT_FAILURE
+    } else {
+        EXIT_SUCCESS
+    }
+}
+
+/// Returns the folder given with `--output`, which has to exist.
+fn output_directory(invocation: &Invocation) -> Result<Option<PathBuf>, String> {
+    match invocation.options.get("output") {
+        Some(dir) if Path::new(dir).is_dir() => Ok(Some(PathBuf::from(dir))),
+        Some(dir) => Err(format!("Output folder '{}' does not exist", dir)),
+        None => Ok(None),
+    }
+}
+
+/// Returns the path a file derived from `file` is written to: in `output_dir` if given, next to
+/// the file (or its archive) otherwise, named after the file with `suffix` and `extension`.
+fn output_path(
+    file: &str,
+    output_dir: Option<&Path>,
+    suffix: &str,
+    extension: &str,
+) -> Result<PathBuf, String> {
+    let source = Path::new(file);
+    let stem = source
+        .file_stem()
+        .ok_or_else(|| format!("Not a file: {}", file))?
+        .to_string_lossy();
+    let directory = match output_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => storage_path(source)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+    };
+    Ok(directory.join(format!("{}{}.{}", stem, suffix, extension)))
+}
+
+/// Returns the path every file of the invocation is written to, see `output_path`.
+///
+/// Outputs that would overwrite the file itself, or the output of an earlier file, are refused,
+/// as are existing files unless `--force` is given. Paths are compared after resolving symbolic
+/// links and `..`, so different spellings of the same file are caught.
+///
+/// # Returns
+/// `Vec<(String, Result<PathBuf, String>)>` - Each file with its output path, or an error string
+/// if nothing is written for it.
+fn output_paths(
+    invocation: &Invocation,
+    output_dir: Option<&Path>,
+    suffix: &str,
+    extension: &str,
+) -> Vec<(String, Result<PathBuf, String>)> {
+    let mut planned = HashSet::new();
+    invocation
+        .files
+        .iter()
+        .map(|file| {
+            let result = output_path(file, output_dir, suffix, extension).and_then(|output| {
+                let resolved = resolve_path(&output);
+                if resolved == resolve_path(Path::new(file)) {
+                    return Err("The output would overwrite the file itself".to_string());
+                }
+                if !planned.insert(resolved) {
+                    return Err(format!(
+                        "'{}' is already written for an earlier file",
+                        output.display()
+                    ));
+                }
+                if output.exists() && !invocation.force {
+                    return Err(format!(
+                        "'{}' already exists, use --force to overwrite it",
+                        output.display()
+                    ));
+                }
+                Ok(output)
+            });
+            (file.clone(), result)
+        })
+        .collect()
+}
+
+/// Resolves symbolic links and `..` in a path that may not exist yet, through its folder.
+fn resolve_path(path: &Path) -> PathBuf {
+    if let Ok(resolved) = fs::canonicalize(path) {
+        return resolved;
+    }
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return path.to_path_buf();
+    };
+    let parent = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent
+    };
+    fs::canonicalize(parent)
+        .map(|parent| parent.join(name))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Returns `true` if the given string is a color like `#1a2b3c`.
+fn is_hex_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Formats a file size in bytes for display.
+fn format_file_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Describes the metadata of an image on a single line.
+fn describe_info(info: &ImageInfo) -> String {
+    let mut parts = vec![
+        info.format.clone(),
+        format!("{}x{}", info.width, info.height),
+    ];
+    if !info.aspect_ratio.is_empty() {
+        parts.push(info.aspect_ratio.clone());
+    }
+    if let Some(depth) = info.color_depth {
+        parts.push(format!("{}-bit", depth));
+    }
+    parts.push(format_file_size(info.file_size));
+    if let Some(pages) = info.page_count.filter(|&pages| pages > 1) {
+        parts.push(format!("{} pages", pages));
+    }
+    if let Some(frames) = info.frame_count.filter(|&frames| frames > 1) {
+        parts.push(format!("{} frames", frames));
+    }
+    if let Some(profile) = &info.color_profile {
+        parts.push(profile.clone());
+    }
+    parts.join(", ")
+}
+
+/// Describes a written file.
+fn describe_written_file(file: &WrittenFile) -> String {
+    format!("wrote {}", file.output)
+}
+
+/// Describes the outcome of AI detection.
+fn describe_ai_detection(result: &AiDetectionResult) -> String {
+    if result.is_ai_generated {
+        format!("AI-generated ({})", result.format)
+    } else {
+        format!("no signs of AI generation ({})", result.format)
+    }
+}
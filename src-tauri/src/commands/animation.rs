@@ -0,0 +1,138 @@
+use base64::Engine;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Window};
+
+use crate::utils::animation::decode_animation_frames;
+use crate::utils::decode_limits::load_decode_limits;
+use crate::utils::dialog_utils::{pick_directory_dialog, show_save_dialog};
+use crate::utils::image_processing::encode_png;
+
+/// Decodes a single frame of an animated image.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `path` - The path to the image file.
+/// * `index` - The index of the frame to decode, starting at 0.
+///
+/// # Returns
+/// `Result<String, String>` - The frame as a base64-encoded PNG data URL, or an error string
+/// if the frame cannot be decoded.
+#[tauri::command]
+pub async fn decode_animation_frame(
+    app: AppHandle,
+    path: String,
+    index: u32,
+) -> Result<String, String> {
+    let limits = load_decode_limits(&app);
+    tokio::task::spawn_blocking(move || {
+        let mut png = Vec::new();
+        decode_animation_frames(Path::new(&path), index, index, &limits, |_, frame| {
+            png = encode_png(&frame)?;
+            Ok(())
+        })?;
+        let base64 = base64::engine::general_purpose::STANDARD.encode(png);
+        Ok(format!("data:image/png;base64,{base64}"))
+    })
+    .await
+    .map_err(|e| format!("Task spawn error: {}", e))?
+}
+
+/// Exports a frame or a range of frames of an animated image as PNG files.
+///
+/// A single frame is saved to a file chosen in a save dialog. A range of frames is saved
+/// to a directory chosen in a dialog, as `<name>_frame_<index>.png` files. Files in the
+/// directory are never overwritten, the export stops with an error at the first frame whose
+/// file already exists.
+///
+/// # Arguments
+/// * `window` - The Tauri window handle.
+/// * `path` - The path to the image file.
+/// * `start_index` - The index of the first frame to export.
+/// * `end_index` - The index of the last frame to export, inclusive.
+///
+/// # Returns
+/// `Result<Option<Vec<String>>, String>` - A result containing an `Option` with the paths of
+/// the saved files, or `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_animation_frames(
+    window: Window,
+    path: String,
+    start_index: u32,
+    end_index: u32,
+) -> Result<Option<Vec<String>>, String> {
+    let limits = load_decode_limits(window.app_handle());
+    let file_stem = Path::new(&path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("image")
+        .to_string();
+    let frame_name = move |index: u32| format!("{}_frame_{:04}", file_stem, index);
+
+    let target = if start_index == end_index {
+        show_save_dialog(window, &frame_name(start_index), "png")
+            .await?
+            .map(ExportTarget::File)
+    } else {
+        pick_directory_dialog(window)
+            .await?
+            .map(ExportTarget::Directory)
+    };
+    let Some(target) = target else {
+        return Ok(None);
+    };
+
+    let saved = tokio::task::spawn_blocking(move || {
+        let mut saved = Vec::new();
+        decode_animation_frames(
+            Path::new(&path),
+            start_index,
+            end_index,
+            &limits,
+            |index, frame| {
+                let png = encode_png(&frame)?;
+                let save_path = match &target {
+                    // The save dialog has confirmed overwriting an existing file.
+                    ExportTarget::File(file) => {
+                        std::fs::write(file, png)
+                            .map_err(|e| format!("Failed to save frame {}: {}", index, e))?;
+                        file.clone()
+                    }
+                    ExportTarget::Directory(dir) => {
+                        let save_path = dir.join(format!("{}.png", frame_name(index)));
+                        write_new_file(&save_path, &png)
+                            .map_err(|e| format!("Failed to save frame {}: {}", index, e))?;
+                        save_path
+                    }
+                };
+                saved.push(save_path.to_string_lossy().to_string());
+                Ok(())
+            },
+        )?;
+        Ok::<_, String>(saved)
+    })
+    .await
+    .map_err(|e| format!("Task spawn error: {}", e))??;
+
+    Ok(Some(saved))
+}
+
+/// Writes a file that must not exist yet.
+fn write_new_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| match e.kind() {
+            ErrorKind::AlreadyExists => format!("'{}' already exists", path.display()),
+            _ => e.to_string(),
+        })?;
+    file.write_all(contents).map_err(|e| e.to_string())
+}
+
+/// Where exported frames are written to.
+enum ExportTarget {
+    File(PathBuf),
+    Directory(PathBuf),
+}
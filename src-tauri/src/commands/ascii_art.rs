@@ -4,9 +4,13 @@ use rusttype::{Font, Scale};
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Value};
 use std::io::Cursor;
+use std::path::Path;
 
 use crate::models::config::Config;
+use crate::utils::archive::read_image_bytes;
 use crate::utils::config_utils::read_config;
+use crate::utils::decode_limits::DecodeLimits;
+use crate::utils::image_formats::{decode_image, decoder_applies_orientation};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AsciiCharSetInfo {
@@ -109,15 +113,30 @@ pub fn get_available_ascii_char_sets() -> Result<Vec<AsciiCharSetInfo>, String>
 /// A `Result` containing the base64-encoded ASCII art image string, or an error message if the conversion fails.
 #[tauri::command]
 pub fn convert_image_to_ascii_art(path: String, app: tauri::AppHandle) -> Result<String, String> {
-    let file_bytes = std::fs::read(&path).map_err(|e| format!("Failed to read file: {e}"))?;
-    let mut img = image::open(&path).map_err(|e| format!("Failed to open image: {e}"))?;
-    img = correct_image_orientation(img, &file_bytes);
-
     let config_str = read_config(&app)?;
     let config: Config = serde_json::from_str(&config_str)
         .map_err(|e| format!("Failed to deserialize config: {}", e))?;
 
-    let ascii_chars = get_ascii_chars_from_config(&config);
+    let ascii_img = render_ascii_art(Path::new(&path), &config)?;
+    encode_image_to_base64(&ascii_img)
+}
+
+/// Renders an image file as ASCII art, using the character set, background color and
+/// decoding limits of the given configuration.
+///
+/// # Arguments
+/// * `path` - The path of the image file or archive entry to convert.
+/// * `config` - The application configuration.
+///
+/// # Returns
+/// `Result<DynamicImage, String>` - The ASCII art image, or an error string if the image cannot be read.
+pub fn render_ascii_art(path: &Path, config: &Config) -> Result<DynamicImage, String> {
+    let limits = DecodeLimits::from_config(config);
+    let file_bytes = read_image_bytes(path, &limits)?;
+    let mut img = decode_image(&file_bytes, path, &limits)?;
+    img = correct_image_orientation(img, &file_bytes);
+
+    let ascii_chars = get_ascii_chars_from_config(config);
 
     let bg_color = if config.ascii_auto_background {
         detect_dominant_color(&img)
@@ -125,8 +144,11 @@ pub fn convert_image_to_ascii_art(path: String, app: tauri::AppHandle) -> Result
         parse_hex_color(&config.ascii_background_color)
     };
 
-    let ascii_img = create_ascii_image_with_chars_and_bg(&img, &ascii_chars, bg_color);
-    encode_image_to_base64(&ascii_img)
+    Ok(create_ascii_image_with_chars_and_bg(
+        &img,
+        &ascii_chars,
+        bg_color,
+    ))
 }
 
 /// Corrects the orientation of an image according to its EXIF data.
@@ -146,6 +168,9 @@ pub fn convert_image_to_ascii_art(path: String, app: tauri::AppHandle) -> Result
 ///
 /// The corrected image.
 fn correct_image_orientation(img: DynamicImage, file_bytes: &[u8]) -> DynamicImage {
+    if decoder_applies_orientation(file_bytes) {
+        return img;
+    }
     if let Ok(exif_data) = exif::Reader::new().read_from_container(&mut Cursor::new(file_bytes)) {
         if let Some(orientation_field) =
             exif_data.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
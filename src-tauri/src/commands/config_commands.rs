@@ -1,5 +1,10 @@
 use crate::models::config::{default_shortcuts, Config, Shortcuts};
 use crate::utils::config_utils::{read_config, write_config};
+use crate::utils::decode_limits::{MAX_DECODE_MEMORY_LIMIT_MB, MAX_IMAGE_DIMENSION_LIMIT};
+use crate::utils::directory_index::invalidate_directory_index;
+use crate::utils::file_sorting::SortKey;
+use crate::utils::image_cache::ImageCache;
+use crate::utils::tone_mapping::ToneMapOperator;
 use serde_json;
 use tauri::{AppHandle, Emitter, Manager};
 
@@ -395,3 +400,184 @@ pub fn update_grid_color_command(app: AppHandle, color: String) -> Result<(), St
 pub fn update_grid_line_strength_command(app: AppHandle, strength: u32) -> Result<(), String> {
     update_config(&app, |config| config.grid_line_strength = strength)
 }
+
+/// Updates the image cache settings and applies them to the running cache.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `budget_mb` - The memory budget of the image cache in megabytes.
+/// * `prefetch_count` - The number of images to prefetch in each direction.
+///
+/// # Returns
+/// `Result<(), String>` - `Ok(())` if the settings are successfully updated, an error string otherwise.
+#[tauri::command]
+pub fn update_image_cache_settings_command(
+    app: AppHandle,
+    budget_mb: u32,
+    prefetch_count: u32,
+) -> Result<(), String> {
+    update_config(&app, |config| {
+        config.image_cache_budget_mb = budget_mb;
+        config.prefetch_count = prefetch_count;
+    })?;
+    app.state::<ImageCache>()
+        .configure(budget_mb, prefetch_count);
+    Ok(())
+}
+
+/// Updates the order in which the images of a directory are listed and navigated.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `sort_order` - The property to sort by ("natural", "modified", "size", "exif-date" or "dimensions").
+/// * `descending` - Whether to sort in descending direction.
+///
+/// # Returns
+/// `Result<(), String>` - `Ok(())` if the sort order is successfully updated, an error string
+/// otherwise, also for unknown sort keys.
+#[tauri::command]
+pub fn update_sort_order_command(
+    app: AppHandle,
+    sort_order: String,
+    descending: bool,
+) -> Result<(), String> {
+    if SortKey::from_name(&sort_order).is_none() {
+        return Err(format!("Unknown sort order: {}", sort_order));
+    }
+    update_config(&app, |config| {
+        config.sort_order = sort_order;
+        config.sort_descending = descending;
+    })?;
+    invalidate_directory_index(&app);
+    Ok(())
+}
+
+/// Updates how high dynamic range images are rendered for display.
+/// Cached renditions are dropped so the next view uses the new settings.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `tone_mapping` - The tone mapping operator ("aces" or "reinhard").
+/// * `exposure` - The exposure adjustment in stops applied before tone mapping.
+///
+/// # Returns
+/// `Result<(), String>` - `Ok(())` if the settings are successfully updated, an error string
+/// otherwise, also for unknown operators and exposures that are not finite.
+#[tauri::command]
+pub fn update_tone_mapping_command(
+    app: AppHandle,
+    tone_mapping: String,
+    exposure: f32,
+) -> Result<(), String> {
+    if ToneMapOperator::from_name(&tone_mapping).is_none() {
+        return Err(format!("Unknown tone mapping operator: {}", tone_mapping));
+    }
+    if !exposure.is_finite() {
+        return Err(format!("Invalid exposure: {}", exposure));
+    }
+    update_config(&app, |config| {
+        config.tone_mapping = tone_mapping;
+        config.exposure = exposure;
+    })?;
+    app.state::<ImageCache>().clear();
+    Ok(())
+}
+
+/// Updates the limits images are decoded under.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `max_image_dimension` - The largest width or height of an image in pixels.
+/// * `max_decode_memory_mb` - The largest amount of memory a decoder may allocate, in megabytes.
+///
+/// Values above `MAX_IMAGE_DIMENSION_LIMIT` and `MAX_DECODE_MEMORY_LIMIT_MB` are clamped to them.
+///
+/// # Returns
+/// `Result<(), String>` - `Ok(())` if the limits are successfully updated, an error string
+/// otherwise, also if either limit is 0.
+#[tauri::command]
+pub fn update_decode_limits_command(
+    app: AppHandle,
+    max_image_dimension: u32,
+    max_decode_memory_mb: u32,
+) -> Result<(), String> {
+    if max_image_dimension == 0 || max_decode_memory_mb == 0 {
+        return Err("Decoding limits must be greater than 0".to_string());
+    }
+    update_config(&app, |config| {
+        config.max_image_dimension = max_image_dimension.min(MAX_IMAGE_DIMENSION_LIMIT);
+        config.max_decode_memory_mb = max_decode_memory_mb.min(MAX_DECODE_MEMORY_LIMIT_MB);
+    })
+}
+
+/// Updates whether images with an embedded color profile are converted to sRGB for display.
+/// Cached renditions are dropped so the next view uses the new setting.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `convert_to_srgb` - Whether tagged images are converted to sRGB.
+///
+/// # Returns
+/// `Result<(), String>` - `Ok(())` if the setting is successfully updated, an error string otherwise.
+#[tauri::command]
+pub fn update_convert_to_srgb_command(app: AppHandle, convert_to_srgb: bool) -> Result<(), String> {
+    update_config(&app, |config| config.convert_to_srgb = convert_to_srgb)?;
+    app.state::<ImageCache>().clear();
+    Ok(())
+}
+
+/// Updates how folders are browsed: whether subfolders are included in the listing and how deep,
+/// and whether navigating past the last image continues in the next folder.
+/// The directory index is dropped so the next lookup lists the folder with the new settings.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `recursive_browsing` - Whether images in subfolders are listed along with the folder's own.
+/// * `recursive_max_depth` - How many levels of subfolders are included.
+/// * `continue_into_next_folder` - Whether navigation moves on to the neighbouring folder at either end.
+///
+/// # Returns
+/// `Result<(), String>` - `Ok(())` if the settings are successfully updated, an error string otherwise.
+#[tauri::command]
+pub fn update_folder_browsing_command(
+    app: AppHandle,
+    recursive_browsing: bool,
+    recursive_max_depth: u32,
+    continue_into_next_folder: bool,
+) -> Result<(), String> {
+    update_config(&app, |config| {
+        config.recursive_browsing = recursive_browsing;
+        config.recursive_max_depth = recursive_max_depth;
+        config.continue_into_next_folder = continue_into_next_folder;
+    })?;
+    invalidate_directory_index(&app);
+    Ok(())
+}
+
+/// Updates whether only a single instance of the application runs at a time, with later launches
+/// handing their paths to the running instance. Takes effect on the next start.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `single_instance` - Whether single-instance mode is enabled.
+///
+/// # Returns
+/// `Result<(), String>` - `Ok(())` if the setting is successfully updated, an error string otherwise.
+#[tauri::command]
+pub fn update_single_instance_command(app: AppHandle, single_instance: bool) -> Result<(), String> {
+    update_config(&app, |config| config.single_instance = single_instance)
+}
+
+/// Updates whether scripts can drive the application through its local control socket.
+/// Takes effect on the next start.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `control_socket` - Whether the control socket is enabled.
+///
+/// # Returns
+/// `Result<(), String>` - `Ok(())` if the setting is successfully updated, an error string otherwise.
+#[tauri::command]
+pub fn update_control_socket_command(app: AppHandle, control_socket: bool) -> Result<(), String> {
+    update_config(&app, |config| config.control_socket = control_socket)
+}
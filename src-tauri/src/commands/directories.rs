@@ -0,0 +1,42 @@
+use std::path::{Path, PathBuf};
+
+use crate::models::directory::RelatedDirectories;
+use crate::utils::file_system::{get_parent_directory, list_subdirectories};
+
+/// Lists the sibling and child folders of a folder, for jumping between folders.
+/// Hidden folders are left out, and symbolic links to folders are listed as folders.
+///
+/// # Arguments
+/// * `path` - The path to a folder, or to a file within it.
+///
+/// # Returns
+/// `Result<RelatedDirectories, String>` - The folder, its parent, siblings and children,
+/// or an error string if the folder cannot be read.
+#[tauri::command]
+pub async fn get_related_directories(path: String) -> Result<RelatedDirectories, String> {
+    let directory = if tokio::fs::metadata(&path).await.is_ok_and(|m| m.is_dir()) {
+        PathBuf::from(&path)
+    } else {
+        get_parent_directory(&path)?.to_path_buf()
+    };
+
+    let parent = directory.parent().map(Path::to_path_buf);
+    let siblings = match &parent {
+        Some(parent) => list_subdirectories(parent, true).await.unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let children = list_subdirectories(&directory, true).await?;
+
+    let to_strings = |paths: Vec<PathBuf>| {
+        paths
+            .into_iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect::<Vec<String>>()
+    };
+    Ok(RelatedDirectories {
+        directory: directory.to_string_lossy().to_string(),
+        parent: parent.map(|p| p.to_string_lossy().to_string()),
+        siblings: to_strings(siblings),
+        children: to_strings(children),
+    })
+}
@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+use crate::models::duplicates::{DuplicateGroup, DuplicateScanProgress};
+use crate::utils::decode_limits::load_decode_limits;
+use crate::utils::duplicate_finder::find_duplicates;
+use crate::utils::file_sorting::load_sort_order;
+use crate::utils::file_system::{
+    get_filtered_directory_files, get_filtered_files_in_directory, get_parent_directory,
+};
+use crate::utils::folder_browsing::{get_filtered_files_in_tree, load_folder_browsing};
+use crate::utils::perceptual_hash::HashAlgorithm;
+
+/// The Hamming distance up to which images count as near-duplicates if none is given.
+const DEFAULT_HASH_THRESHOLD: u32 = 8;
+
+/// Finds exact duplicates and near-duplicates among the images of a directory.
+///
+/// The scan runs off the main thread and emits a `duplicate-scan-progress` event after every file.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `path` - The directory to scan, or a file within it.
+/// * `recursive` - Whether images in subdirectories are included, down to the configured folder depth.
+/// * `algorithm` - The perceptual hash algorithm ("ahash", "dhash" or "phash"). Defaults to "phash".
+/// * `threshold` - The largest Hamming distance between the hashes of near-duplicates, out of 64 bits.
+///
+/// # Returns
+/// `Result<Vec<DuplicateGroup>, String>` - The groups of duplicates with each file's size and dimensions,
+/// or an error string if the directory cannot be read.
+#[tauri::command]
+pub async fn find_duplicate_images(
+    app: AppHandle,
+    path: String,
+    recursive: bool,
+    algorithm: Option<String>,
+    threshold: Option<u32>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    let files = collect_image_files(&app, &path, recursive).await?;
+    let algorithm = HashAlgorithm::from_name(algorithm.as_deref().unwrap_or("phash"));
+    let threshold = threshold.unwrap_or(DEFAULT_HASH_THRESHOLD).min(64);
+    let limits = load_decode_limits(&app);
+
+    tokio::task::spawn_blocking(move || {
+        let total = files.len();
+        find_duplicates(&files, algorithm, threshold, &limits, |processed, path| {
+            let progress = DuplicateScanProgress {
+                processed,
+                total,
+                path: path.to_string(),
+            };
+            if let Err(e) = app.emit("duplicate-scan-progress", progress) {
+                eprintln!("Failed to emit 'duplicate-scan-progress' event: {}", e);
+            }
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))
+}
+
+/// Lists the images of the given directory, or of the directory containing the given file,
+/// optionally including its subdirectories down to the configured depth. Subdirectories that
+/// cannot be read are skipped.
+async fn collect_image_files(
+    app: &AppHandle,
+    path: &str,
+    recursive: bool,
+) -> Result<Vec<String>, String> {
+    let order = load_sort_order(app);
+    let root = if Path::new(path).is_dir() {
+        PathBuf::from(path)
+    } else if recursive {
+        get_parent_directory(path)?.to_path_buf()
+    } else {
+        return get_filtered_directory_files(path, order).await;
+    };
+
+    if recursive {
+        let max_depth = load_folder_browsing(app).max_depth;
+        get_filtered_files_in_tree(&root, order, max_depth).await
+    } else {
+        get_filtered_files_in_directory(&root, order).await
+    }
+}
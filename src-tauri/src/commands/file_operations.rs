@@ -1,10 +1,25 @@
-use tauri::Window;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State, Window};
 
-use crate::models::image::ImageMetadata;
+use crate::models::directory::DirectoryChange;
+use crate::models::image::{ExportOptions, ImageMetadata};
 use crate::utils::{
+    archive::{archive_kind, read_image_bytes},
+    color_profile::load_convert_to_srgb,
+    decode_limits::load_decode_limits,
     dialog_utils::{open_image_dialog, show_save_dialog},
-    file_system::get_filtered_directory_files,
+    directory_index::{
+        get_indexed_directory, get_indexed_directory_files, get_indexed_files_in_directory,
+        is_playlist_active, set_current_image,
+    },
+    file_sorting::load_sort_order,
+    file_system::get_parent_directory,
+    folder_browsing::{find_adjacent_folder_image, load_folder_browsing},
+    image_cache::{prefetch_neighbours, ImageCache},
     image_processing::{self, get_supported_image_formats as get_formats, read_image_file},
+    image_protocol::{register_image_url, ImageRegistry},
+    tiff_utils::count_tiff_pages,
+    tone_mapping::load_tone_map_settings,
 };
 use base64::Engine;
 
@@ -12,7 +27,10 @@ use base64::Engine;
 /// path, and a list of other image files in the same directory.
 ///
 /// # Arguments
+/// * `app` - The Tauri application handle.
 /// * `window` - The Tauri window handle.
+/// * `registry` - The image registry used by the image protocol.
+/// * `cache` - The image cache.
 ///
 /// # Returns
 /// `Result<Option<(ImageMetadata, String, Vec<String>)>, String>` - A result containing
@@ -20,14 +38,31 @@ use base64::Engine;
 /// directory if a file was selected, or `None` if the dialog was cancelled.
 #[tauri::command]
 pub async fn open_and_read_file(
+    app: AppHandle,
     window: Window,
+    registry: State<'_, ImageRegistry>,
+    cache: State<'_, ImageCache>,
 ) -> Result<Option<(ImageMetadata, String, Vec<String>)>, String> {
     if let Some(path_buf) = open_image_dialog(window).await? {
         let path_str = path_buf.to_string_lossy().to_string();
-        let metadata = read_image_file(&path_str)
-            .await
-            .map_err(|e| format!("Failed to read image file '{}': {}", path_str, e))?;
-        let directory_files = get_filtered_directory_files(&path_str).await?;
+        if archive_kind(&path_buf).is_some() {
+            return open_first_image(&app, &path_str, &registry, &cache)
+                .await
+                .map(Some);
+        }
+        let metadata = read_image_file(
+            &path_str,
+            &registry,
+            &cache,
+            load_tone_map_settings(&app),
+            load_convert_to_srgb(&app),
+            load_decode_limits(&app),
+        )
+        .await
+        .map_err(|e| format!("Failed to read image file '{}': {}", path_str, e))?;
+        let directory_files = get_indexed_directory_files(&app, &path_str).await?;
+        set_current_image(&app, &path_str);
+        prefetch_around(&app, &directory_files, &path_str);
         Ok(Some((metadata, path_str, directory_files)))
     } else {
         Ok(None)
@@ -35,34 +70,126 @@ pub async fn open_and_read_file(
 }
 
 /// Reads image metadata and lists other files in the same directory given a specific path.
+/// Archives are opened like directories, showing their first image, and the entries of an
+/// archive are addressed as `<archive>/<entry>`.
 ///
 /// # Arguments
+/// * `app` - The Tauri application handle.
 /// * `path` - The path to the image file as a `String`.
+/// * `registry` - The image registry used by the image protocol.
+/// * `cache` - The image cache.
 ///
 /// # Returns
 /// `Result<(ImageMetadata, String, Vec<String>)>, String>` - A result containing
 /// the image metadata, its path, and a list of other files in the directory.
 #[tauri::command]
 pub async fn read_image_from_path(
+    app: AppHandle,
     path: String,
+    registry: State<'_, ImageRegistry>,
+    cache: State<'_, ImageCache>,
 ) -> Result<(ImageMetadata, String, Vec<String>), String> {
-    let metadata = read_image_file(&path)
-        .await
-        .map_err(|e| format!("Failed to read image file '{}': {}", path, e))?;
-    let directory_files = get_filtered_directory_files(&path).await?;
+    if archive_kind(Path::new(&path)).is_some() {
+        return open_first_image(&app, &path, &registry, &cache).await;
+    }
+    let metadata = read_image_file(
+        &path,
+        &registry,
+        &cache,
+        load_tone_map_settings(&app),
+        load_convert_to_srgb(&app),
+        load_decode_limits(&app),
+    )
+    .await
+    .map_err(|e| format!("Failed to read image file '{}': {}", path, e))?;
+    let directory_files = get_indexed_directory_files(&app, &path).await?;
+    set_current_image(&app, &path);
+    prefetch_around(&app, &directory_files, &path);
     Ok((metadata, path, directory_files))
 }
 
+/// Opens the first image of a directory, in the configured sort order, and returns its metadata,
+/// path, and the list of image files in the directory.
+/// With recursive browsing enabled, images in subfolders are included in the list.
+/// Archives are opened like directories.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `path` - The path to the directory or archive as a `String`.
+/// * `registry` - The image registry used by the image protocol.
+/// * `cache` - The image cache.
+///
+/// # Returns
+/// `Result<(ImageMetadata, String, Vec<String>), String>` - A result containing the metadata
+/// and path of the first image and the list of image files, or an error string if the directory has no images.
+#[tauri::command]
+pub async fn open_directory(
+    app: AppHandle,
+    path: String,
+    registry: State<'_, ImageRegistry>,
+    cache: State<'_, ImageCache>,
+) -> Result<(ImageMetadata, String, Vec<String>), String> {
+    open_first_image(&app, &path, &registry, &cache).await
+}
+
+/// Opens the first image listed for a directory or archive and indexes the listing.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `directory` - The path to the directory or archive.
+/// * `registry` - The image registry used by the image protocol.
+/// * `cache` - The image cache.
+///
+/// # Returns
+/// `Result<(ImageMetadata, String, Vec<String>), String>` - The metadata and path of the first image
+/// and the list of image files, or an error string if there are no images.
+async fn open_first_image(
+    app: &AppHandle,
+    directory: &str,
+    registry: &ImageRegistry,
+    cache: &ImageCache,
+) -> Result<(ImageMetadata, String, Vec<String>), String> {
+    let directory_files = get_indexed_files_in_directory(app, Path::new(directory)).await?;
+    let first_image = directory_files
+        .first()
+        .cloned()
+        .ok_or_else(|| format!("No images found in '{}'", directory))?;
+    let metadata = read_image_file(
+        &first_image,
+        registry,
+        cache,
+        load_tone_map_settings(app),
+        load_convert_to_srgb(app),
+        load_decode_limits(app),
+    )
+    .await
+    .map_err(|e| format!("Failed to read image file '{}': {}", first_image, e))?;
+    set_current_image(app, &first_image);
+    prefetch_around(app, &directory_files, &first_image);
+    Ok((metadata, first_image, directory_files))
+}
+
+/// Starts prefetching the neighbours of the given image, if it is part of the file list.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `files` - The list of image files in the directory.
+/// * `path` - The path of the currently displayed image.
+fn prefetch_around(app: &AppHandle, files: &[String], path: &str) {
+    if let Some(index) = files.iter().position(|f| f == path) {
+        prefetch_neighbours(app, files.to_vec(), index);
+    }
+}
+
 /// Saves an image from base64 encoded data to a specified path and format, with optional quality.
-/// This unified command works for both normal and ASCII-converted images.
+/// This is used for images that only exist in the frontend, such as ASCII-converted images.
 ///
 /// # Arguments
 /// * `window` - The Tauri window handle.
 /// * `base64data` - The base64 encoded image data.
 /// * `source_name` - The source name for filename suggestion (file path or "ascii_art").
 /// * `format` - The desired output format (e.g., "png", "jpeg").
-/// * `quality` - Optional quality setting for formats like JPEG (0.0-1.0).
-/// * `rotation` - The rotation angle in degrees (0, 90, 180, 270).
+/// * `options` - The quality and rotation of the saved image.
 ///
 /// # Returns
 /// `Result<Option<String>, String>` - A result containing an `Option` with the
@@ -73,16 +200,63 @@ pub async fn save_base64_image_as(
     base64data: String,
     source_name: String,
     format: String,
-    quality: Option<f32>,
-    rotation: i32,
+    options: ExportOptions,
 ) -> Result<Option<String>, String> {
+    let limits = load_decode_limits(window.app_handle());
     if let Some(save_path) = show_save_dialog(window, &source_name, &format).await? {
         let bytes = base64::engine::general_purpose::STANDARD
             .decode(base64data)
             .map_err(|e| format!("Failed to decode base64: {}", e))?;
 
         let result = tokio::task::spawn_blocking(move || {
-            image_processing::save_image_to_format(&bytes, &save_path, &format, quality, rotation)
+            image_processing::save_image_to_format(
+                &bytes,
+                Path::new(&source_name),
+                &save_path,
+                &format,
+                &options,
+                &limits,
+            )
+        })
+        .await
+        .map_err(|e| format!("Task spawn error: {}", e))??;
+        Ok(Some(result))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Saves the image file at the given path to a specified path and format, with optional quality.
+/// Animated images saved as GIF, PNG or WebP stay animated, and SVG images are rasterized at the given scale.
+///
+/// # Arguments
+/// * `window` - The Tauri window handle.
+/// * `path` - The path to the source image file.
+/// * `format` - The desired output format (e.g., "png", "jpeg").
+/// * `options` - The quality, rotation, animation, SVG rasterization and color profile settings of the saved image.
+///
+/// # Returns
+/// `Result<Option<String>, String>` - A result containing an `Option` with the
+/// path to the saved file if successful, or `None` if the save operation was cancelled.
+#[tauri::command]
+pub async fn save_image_as(
+    window: Window,
+    path: String,
+    format: String,
+    options: ExportOptions,
+) -> Result<Option<String>, String> {
+    let limits = load_decode_limits(window.app_handle());
+    if let Some(save_path) = show_save_dialog(window, &path, &format).await? {
+        let result = tokio::task::spawn_blocking(move || {
+            let bytes = read_image_bytes(Path::new(&path), &limits)?;
+            image_processing::save_image_to_format(
+                &bytes,
+                Path::new(&path),
+                &save_path,
+                &format,
+                &options,
+                &limits,
+            )
         })
         .await
         .map_err(|e| format!("Task spawn error: {}", e))??;
@@ -104,9 +278,15 @@ pub fn get_supported_image_formats() -> Result<Vec<String>, String> {
 
 /// Navigates to the next or previous image in the current directory.
 ///
+/// Past either end of the directory, navigation wraps around, or continues in the neighbouring
+/// folder if that is enabled. Moving to another folder emits `directory-changed` with its listing.
+///
 /// # Arguments
 /// * `current_path` - The path of the currently displayed image.
 /// * `direction` - The navigation direction, either "next" or "previous".
+/// * `app` - The Tauri application handle.
+/// * `registry` - The image registry used by the image protocol.
+/// * `cache` - The image cache.
 ///
 /// # Returns
 /// `Result<(ImageMetadata, String), String>` - A result containing the metadata
@@ -115,26 +295,155 @@ pub fn get_supported_image_formats() -> Result<Vec<String>, String> {
 pub async fn change_image(
     current_path: String,
     direction: String,
+    app: AppHandle,
+    registry: State<'_, ImageRegistry>,
+    cache: State<'_, ImageCache>,
 ) -> Result<(ImageMetadata, String), String> {
-    let files = get_filtered_directory_files(&current_path).await?;
-
-    if files.len() <= 1 {
-        return Err("No other images in directory".to_string());
-    }
+    let files = get_indexed_directory_files(&app, &current_path).await?;
 
     let current_index = files
         .iter()
         .position(|f| f == &current_path)
         .ok_or_else(|| "Current image not found in directory".to_string())?;
 
-    let next_index = match direction.as_str() {
-        "next" => (current_index + 1) % files.len(),
-        "previous" | "prev" => (current_index + files.len() - 1) % files.len(),
+    let forward = match direction.as_str() {
+        "next" => true,
+        "previous" | "prev" => false,
         _ => return Err("Invalid direction. Must be 'next' or 'previous'.".to_string()),
     };
 
+    let at_end = if forward {
+        current_index + 1 == files.len()
+    } else {
+        current_index == 0
+    };
+    if at_end {
+        if let Some((next_image_path, files)) =
+            find_next_folder_image(&app, &current_path, forward).await?
+        {
+            let metadata = read_image_file(
+                &next_image_path,
+                &registry,
+                &cache,
+                load_tone_map_settings(&app),
+                load_convert_to_srgb(&app),
+                load_decode_limits(&app),
+            )
+            .await?;
+            set_current_image(&app, &next_image_path);
+            prefetch_around(&app, &files, &next_image_path);
+            return Ok((metadata, next_image_path));
+        }
+    }
+
+    if files.len() <= 1 {
+        return Err("No other images in directory".to_string());
+    }
+
+    let next_index = if forward {
+        (current_index + 1) % files.len()
+    } else {
+        (current_index + files.len() - 1) % files.len()
+    };
+
     let next_image_path = files[next_index].clone();
-    let metadata = read_image_file(&next_image_path).await?;
+    let metadata = read_image_file(
+        &next_image_path,
+        &registry,
+        &cache,
+        load_tone_map_settings(&app),
+        load_convert_to_srgb(&app),
+        load_decode_limits(&app),
+    )
+    .await?;
+    set_current_image(&app, &next_image_path);
+    prefetch_neighbours(&app, files, next_index);
 
     Ok((metadata, next_image_path))
 }
+
+/// Finds the image in the neighbouring folder to continue with at either end of the current one,
+/// if continuing into the next folder is enabled, and indexes its folder.
+/// Playlists always wrap around.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `current_path` - The path of the currently displayed image.
+/// * `forward` - Whether navigation moves forward.
+///
+/// # Returns
+/// `Result<Option<(String, Vec<String>)>, String>` - The path of the image to continue with and the
+/// listing of its folder, or `None` if navigation should wrap around instead.
+async fn find_next_folder_image(
+    app: &AppHandle,
+    current_path: &str,
+    forward: bool,
+) -> Result<Option<(String, Vec<String>)>, String> {
+    let browsing = load_folder_browsing(app);
+    if !browsing.continue_into_next_folder || is_playlist_active(app) {
+        return Ok(None);
+    }
+
+    // A recursive listing spans the subfolders, so it continues beside its root.
+    let directory: PathBuf = match get_indexed_directory(app) {
+        Some(directory) if browsing.recursive => directory,
+        _ => get_parent_directory(current_path)?.to_path_buf(),
+    };
+    let Some((next_directory, next_image_path)) =
+        find_adjacent_folder_image(&directory, forward, browsing, load_sort_order(app)).await?
+    else {
+        return Ok(None);
+    };
+
+    let files = get_indexed_files_in_directory(app, &next_directory).await?;
+    let payload = DirectoryChange {
+        directory: next_directory.to_string_lossy().to_string(),
+        files: files.clone(),
+    };
+    if let Err(e) = app.emit("directory-changed", payload) {
+        eprintln!("Failed to emit 'directory-changed' event: {}", e);
+    }
+    Ok(Some((next_image_path, files)))
+}
+
+/// Returns the URL a page of a multi-page TIFF file is served from through the image protocol.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `registry` - The image registry used by the image protocol.
+/// * `path` - The path to the TIFF file.
+/// * `page` - The index of the page, starting at 0.
+///
+/// # Returns
+/// `Result<String, String>` - The URL of the page, or an error string if the file has no such page.
+#[tauri::command]
+pub async fn get_image_page_url(
+    app: AppHandle,
+    registry: State<'_, ImageRegistry>,
+    path: String,
+    page: u32,
+) -> Result<String, String> {
+    let path_buf = PathBuf::from(&path);
+    let reader_path = path_buf.clone();
+    let page_count = tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&reader_path)
+            .map_err(|e| format!("Failed to open file '{}': {}", reader_path.display(), e))?;
+        count_tiff_pages(std::io::BufReader::new(file))
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))??;
+    if page >= page_count {
+        return Err(format!("'{}' has no page {}", path, page + 1));
+    }
+
+    // Pages are rendered with the display settings, so the URL has to change along with them.
+    let tone_map = load_tone_map_settings(&app);
+    let convert_to_srgb = load_convert_to_srgb(&app);
+    Ok(format!(
+        "{}&r={}&cms={}&p={}",
+        register_image_url(&registry, &path_buf),
+        tone_map.cache_key(),
+        convert_to_srgb,
+        page
+    ))
+}
@@ -0,0 +1,60 @@
+use base64::Engine;
+use image::DynamicImage;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::models::image::ImageComparison;
+use crate::utils::archive::read_image_bytes;
+use crate::utils::decode_limits::{load_decode_limits, DecodeLimits};
+use crate::utils::image_compare::{align_images, compare_images};
+use crate::utils::image_formats::decode_image;
+use crate::utils::image_processing::{apply_exif_orientation, encode_png};
+
+/// Compares two images and renders a heatmap of their differences.
+///
+/// Images of different sizes are compared after scaling the larger one to the size of the smaller one.
+/// The heatmap is returned as a PNG data URL, which can be saved with `save_base64_image_as`.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `first_path` - The path to the first image file.
+/// * `second_path` - The path to the second image file.
+///
+/// # Returns
+/// `Result<ImageComparison, String>` - The PSNR, SSIM, maximum error and heatmap of the two images,
+/// or an error string if one of them cannot be decoded.
+#[tauri::command]
+pub async fn compare_image_files(
+    app: AppHandle,
+    first_path: String,
+    second_path: String,
+) -> Result<ImageComparison, String> {
+    let limits = load_decode_limits(&app);
+    tokio::task::spawn_blocking(move || {
+        let first = open_upright(&first_path, &limits)?;
+        let second = open_upright(&second_path, &limits)?;
+        let (first, second, scaled) = align_images(&first, &second);
+        let comparison = compare_images(&first, &second);
+
+        let png = encode_png(&DynamicImage::ImageRgb8(comparison.heatmap))?;
+        let base64 = base64::engine::general_purpose::STANDARD.encode(png);
+        Ok(ImageComparison {
+            width: first.width(),
+            height: first.height(),
+            scaled,
+            psnr: comparison.psnr,
+            ssim: comparison.ssim,
+            max_error: comparison.max_error,
+            heatmap: format!("data:image/png;base64,{base64}"),
+        })
+    })
+    .await
+    .map_err(|e| format!("Task spawn error: {}", e))?
+}
+
+/// Decodes the image file at the given path and turns it upright according to its EXIF orientation.
+fn open_upright(path: &str, limits: &DecodeLimits) -> Result<DynamicImage, String> {
+    let bytes = read_image_bytes(Path::new(path), limits)?;
+    let img = decode_image(&bytes, Path::new(path), limits)?;
+    Ok(apply_exif_orientation(&bytes, img))
+}
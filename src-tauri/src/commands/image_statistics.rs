@@ -0,0 +1,32 @@
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::models::image::ImageStatistics;
+use crate::utils::decode_limits::load_decode_limits;
+use crate::utils::image_formats::open_image;
+use crate::utils::image_statistics::compute_image_statistics;
+use crate::utils::tone_mapping::{load_tone_map_settings, to_display_image};
+
+/// Computes the histograms and channel statistics of an image.
+///
+/// The statistics describe the image as it is displayed, so high dynamic range images
+/// are tone mapped with the configured settings first.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `path` - The path to the image file.
+///
+/// # Returns
+/// `Result<ImageStatistics, String>` - The per-channel histograms and statistics of the image,
+/// or an error string if the image cannot be decoded.
+#[tauri::command]
+pub async fn get_image_statistics(app: AppHandle, path: String) -> Result<ImageStatistics, String> {
+    let tone_map = load_tone_map_settings(&app);
+    let limits = load_decode_limits(&app);
+    tokio::task::spawn_blocking(move || {
+        let img = open_image(Path::new(&path), &limits)?;
+        Ok(compute_image_statistics(&to_display_image(img, &tone_map)))
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+}
@@ -1,6 +1,13 @@
+pub mod animation;
 pub mod app_info;
 pub mod ascii_art;
 pub mod config_commands;
+pub mod directories;
+pub mod duplicates;
 pub mod file_operations;
 pub mod image_analyze;
+pub mod image_compare;
+pub mod image_statistics;
 pub mod linux_integration;
+pub mod pixel_inspector;
+pub mod thumbnails;
@@ -0,0 +1,55 @@
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+use crate::models::image::PixelInfo;
+use crate::utils::archive::read_image_bytes;
+use crate::utils::decode_limits::load_decode_limits;
+use crate::utils::image_formats::decode_image;
+use crate::utils::image_processing::{apply_exif_orientation, get_modified_time};
+use crate::utils::pixel_inspector::{inspect_pixel, unrotate_coordinates, PixelInspectorCache};
+use crate::utils::tone_mapping::load_tone_map_settings;
+
+/// Reads the value of a pixel of an image at the full bit depth of the source.
+///
+/// The coordinates refer to the image as it is displayed, after its EXIF orientation
+/// and the user rotation have been applied. The image last inspected is kept decoded,
+/// so moving over it only reads the pixels.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `path` - The path to the image file.
+/// * `x` - The column of the pixel.
+/// * `y` - The row of the pixel.
+/// * `rotation` - The rotation angle in degrees (0, 90, 180, 270) the image is displayed with.
+/// * `area` - The side length of a square area centred on the pixel to average over, if any.
+///
+/// # Returns
+/// `Result<PixelInfo, String>` - The pixel value as hex, RGB, HSL, HSV, Lab and alpha,
+/// or an error string if the image cannot be decoded or the pixel lies outside it.
+#[tauri::command]
+pub async fn inspect_image_pixel(
+    app: AppHandle,
+    path: String,
+    x: u32,
+    y: u32,
+    rotation: i32,
+    area: Option<u32>,
+) -> Result<PixelInfo, String> {
+    let limits = load_decode_limits(&app);
+    let tone_map = load_tone_map_settings(&app);
+    tokio::task::spawn_blocking(move || {
+        let path = Path::new(&path);
+        let modified = get_modified_time(path)?;
+        let img = app
+            .state::<PixelInspectorCache>()
+            .get_or_decode(path, modified, || {
+                let bytes = read_image_bytes(path, &limits)?;
+                let img = decode_image(&bytes, path, &limits)?;
+                Ok(apply_exif_orientation(&bytes, img))
+            })?;
+        let (x, y) = unrotate_coordinates(x, y, rotation, img.width(), img.height())?;
+        inspect_pixel(&img, x, y, area.unwrap_or(1), &tone_map)
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+}
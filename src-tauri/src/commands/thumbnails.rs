@@ -0,0 +1,80 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use tauri::AppHandle;
+
+use crate::models::image::ThumbnailEntry;
+use crate::utils::decode_limits::load_decode_limits;
+use crate::utils::image_protocol::thumbnail_url;
+use crate::utils::thumbnails::{get_or_create_thumbnail, thumbnail_cache_dir};
+use crate::utils::tone_mapping::load_tone_map_settings;
+
+/// Returns the thumbnails of a batch of image files, creating the missing ones.
+///
+/// Thumbnails are stored in the shared freedesktop thumbnail cache, so thumbnails made by other
+/// applications are reused and the ones made here are available to them. They are generated on
+/// background threads and served through the image protocol.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `paths` - The paths of the image files, typically the visible part of a directory listing.
+/// * `size` - The requested thumbnail size: 128, 256 or 512 pixels.
+///
+/// # Returns
+/// `Result<Vec<ThumbnailEntry>, String>` - The thumbnail URL of each file, in the order of `paths`,
+/// or an error string if the thumbnail cache cannot be located.
+#[tauri::command]
+pub async fn get_thumbnails(
+    app: AppHandle,
+    paths: Vec<String>,
+    size: u32,
+) -> Result<Vec<ThumbnailEntry>, String> {
+    let cache_dir = thumbnail_cache_dir(&app)?;
+    let tone_map = load_tone_map_settings(&app);
+    let limits = load_decode_limits(&app);
+
+    let thumbnails = tokio::task::spawn_blocking(move || {
+        let workers = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = paths.len().div_ceil(workers).max(1);
+        let (cache_dir, tone_map, limits) = (&cache_dir, &tone_map, &limits);
+        thread::scope(|scope| {
+            let handles: Vec<_> = paths
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|path| {
+                                let thumbnail = get_or_create_thumbnail(
+                                    cache_dir,
+                                    Path::new(path),
+                                    size,
+                                    tone_map,
+                                    limits,
+                                )
+                                .unwrap_or_else(|e| {
+                                    eprintln!("{}", e);
+                                    None
+                                });
+                                (path.clone(), thumbnail)
+                            })
+                            .collect::<Vec<(String, Option<PathBuf>)>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))?;
+
+    Ok(thumbnails
+        .into_iter()
+        .map(|(path, thumbnail)| ThumbnailEntry {
+            path,
+            url: thumbnail.and_then(|thumbnail| thumbnail_url(&thumbnail)),
+        })
+        .collect())
+}
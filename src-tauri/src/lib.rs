@@ -1,5 +1,11 @@
 use crate::utils::{
+    control_socket::{setup_control_socket, ControlSocket},
+    directory_index::DirectoryIndex,
+    image_cache::{apply_image_cache_config, ImageCache},
+    image_protocol::{handle_image_protocol_request, ImageRegistry, IMAGE_PROTOCOL_SCHEME},
     os_specific_setup::perform_os_specific_setup,
+    pixel_inspector::PixelInspectorCache,
+    single_instance::{setup_single_instance, SingleInstance},
     startup_handler::{AppReady, OpenedPathsState},
     window_utils::setup_main_window,
 };
@@ -8,10 +14,21 @@ use tauri_plugin_dialog;
 use tauri_plugin_fs;
 use tauri_plugin_opener;
 
+mod cli;
 mod commands;
 mod models;
 mod utils;
 
+/// Runs a command-line subcommand such as `blickfang info <files...>` without creating a window,
+/// if the launch arguments start with one.
+///
+/// # Returns
+/// `Option<i32>` - The exit code of the subcommand, or `None` if the viewer should start.
+pub fn run_headless() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    cli::run_cli(&args)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let context = tauri::generate_context!();
@@ -19,9 +36,25 @@ pub fn run() {
     tauri::Builder::default()
         .manage(OpenedPathsState::default())
         .manage(AppReady::default())
+        .manage(ImageRegistry::default())
+        .manage(ImageCache::default())
+        .manage(DirectoryIndex::default())
+        .manage(SingleInstance::default())
+        .manage(ControlSocket::default())
+        .manage(PixelInspectorCache::default())
+        .register_asynchronous_uri_scheme_protocol(
+            IMAGE_PROTOCOL_SCHEME,
+            handle_image_protocol_request,
+        )
         .setup(|app| {
+            if setup_single_instance(app.handle()) {
+                // The paths were handed to the running instance, which shows them instead.
+                std::process::exit(0);
+            }
             perform_os_specific_setup(&app.handle())?;
             setup_main_window(&app.handle())?;
+            apply_image_cache_config(app.handle())?;
+            setup_control_socket(app.handle());
             Ok(())
         })
         .plugin(tauri_plugin_opener::init())
@@ -30,12 +63,17 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             utils::window_utils::show_window,
             utils::startup_handler::frontend_is_ready,
+            utils::control_socket::set_displayed_rotation,
             commands::app_info::get_app_version,
             commands::file_operations::open_and_read_file,
             commands::file_operations::read_image_from_path,
+            commands::file_operations::open_directory,
+            commands::directories::get_related_directories,
             commands::file_operations::change_image,
             commands::file_operations::save_base64_image_as,
+            commands::file_operations::save_image_as,
             commands::file_operations::get_supported_image_formats,
+            commands::file_operations::get_image_page_url,
             commands::config_commands::read_config_command,
             commands::config_commands::write_config_command,
             commands::config_commands::update_language_command,
@@ -59,9 +97,24 @@ pub fn run() {
             commands::config_commands::update_grid_overlay_mode_command,
             commands::config_commands::update_grid_color_command,
             commands::config_commands::update_grid_line_strength_command,
+            commands::config_commands::update_image_cache_settings_command,
+            commands::config_commands::update_sort_order_command,
+            commands::config_commands::update_tone_mapping_command,
+            commands::config_commands::update_decode_limits_command,
+            commands::config_commands::update_convert_to_srgb_command,
+            commands::config_commands::update_folder_browsing_command,
+            commands::config_commands::update_single_instance_command,
+            commands::config_commands::update_control_socket_command,
             commands::linux_integration::install_linux_desktop_file_command,
             commands::linux_integration::is_running_as_appimage_command,
             commands::image_analyze::detect_ai_image,
+            commands::image_statistics::get_image_statistics,
+            commands::pixel_inspector::inspect_image_pixel,
+            commands::duplicates::find_duplicate_images,
+            commands::image_compare::compare_image_files,
+            commands::thumbnails::get_thumbnails,
+            commands::animation::decode_animation_frame,
+            commands::animation::export_animation_frames,
             commands::ascii_art::convert_image_to_ascii_art,
             commands::ascii_art::get_available_ascii_char_sets
         ])
@@ -70,5 +123,7 @@ pub fn run() {
         .run(|app_handle, event| {
             utils::startup_handler::handle_run_event(app_handle, &event);
             utils::window_utils::handle_window_event(app_handle, &event);
+            utils::single_instance::handle_exit_event(app_handle, &event);
+            utils::control_socket::handle_exit_event(app_handle, &event);
         });
 }
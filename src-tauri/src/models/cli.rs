@@ -0,0 +1,65 @@
+use serde::Serialize;
+
+use crate::models::image::ImageMetadata;
+
+/// The outcome of a command-line subcommand for a single file, as printed with `--json`.
+#[derive(Serialize)]
+pub struct CliFileResult<T: Serialize> {
+    pub path: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The metadata of an image, as reported by the `info` subcommand.
+#[derive(Serialize)]
+pub struct ImageInfo {
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    pub aspect_ratio: String,
+    pub color_depth: Option<u8>,
+    pub file_size: u64,
+    pub page_count: Option<u32>,
+    pub sensor_width: Option<u32>,
+    pub sensor_height: Option<u32>,
+    pub frame_count: Option<u32>,
+    pub loop_count: Option<u32>,
+    pub total_duration: Option<u64>,
+    pub color_profile: Option<String>,
+    pub archive_path: Option<String>,
+    pub archive_entry: Option<String>,
+    /// The EXIF fields of the image by tag name, or `null` if it has none.
+    pub exif: serde_json::Value,
+}
+
+impl From<ImageMetadata> for ImageInfo {
+    fn from(metadata: ImageMetadata) -> Self {
+        Self {
+            exif: serde_json::from_str(&metadata.exif_data).unwrap_or(serde_json::Value::Null),
+            format: metadata.format,
+            width: metadata.width,
+            height: metadata.height,
+            aspect_ratio: metadata.aspect_ratio,
+            color_depth: metadata.color_depth,
+            file_size: metadata.file_size,
+            page_count: metadata.page_count,
+            sensor_width: metadata.sensor_width,
+            sensor_height: metadata.sensor_height,
+            frame_count: metadata.frame_count,
+            loop_count: metadata.loop_count,
+            total_duration: metadata.total_duration,
+            color_profile: metadata.color_profile,
+            archive_path: metadata.archive_path,
+            archive_entry: metadata.archive_entry,
+        }
+    }
+}
+
+/// The file written by the `convert` and `ascii` subcommands.
+#[derive(Serialize)]
+pub struct WrittenFile {
+    pub output: String,
+}
@@ -74,6 +74,34 @@ pub struct Config {
     pub grid_color: String,
     #[serde(default = "default_grid_line_strength")]
     pub grid_line_strength: u32,
+    #[serde(default = "default_image_cache_budget_mb")]
+    pub image_cache_budget_mb: u32,
+    #[serde(default = "default_prefetch_count")]
+    pub prefetch_count: u32,
+    #[serde(default = "default_sort_order")]
+    pub sort_order: String,
+    #[serde(default = "default_sort_descending")]
+    pub sort_descending: bool,
+    #[serde(default = "default_tone_mapping")]
+    pub tone_mapping: String,
+    #[serde(default = "default_exposure")]
+    pub exposure: f32,
+    #[serde(default = "default_max_image_dimension")]
+    pub max_image_dimension: u32,
+    #[serde(default = "default_max_decode_memory_mb")]
+    pub max_decode_memory_mb: u32,
+    #[serde(default = "default_convert_to_srgb")]
+    pub convert_to_srgb: bool,
+    #[serde(default = "default_recursive_browsing")]
+    pub recursive_browsing: bool,
+    #[serde(default = "default_recursive_max_depth")]
+    pub recursive_max_depth: u32,
+    #[serde(default = "default_continue_into_next_folder")]
+    pub continue_into_next_folder: bool,
+    #[serde(default = "default_single_instance")]
+    pub single_instance: bool,
+    #[serde(default = "default_control_socket")]
+    pub control_socket: bool,
 }
 
 fn default_linux_desktop_install_choice() -> String {
@@ -104,6 +132,62 @@ fn default_grid_line_strength() -> u32 {
     2
 }
 
+fn default_image_cache_budget_mb() -> u32 {
+    256
+}
+
+fn default_prefetch_count() -> u32 {
+    2
+}
+
+fn default_sort_order() -> String {
+    "natural".into()
+}
+
+fn default_sort_descending() -> bool {
+    false
+}
+
+fn default_tone_mapping() -> String {
+    "aces".into()
+}
+
+fn default_exposure() -> f32 {
+    0.0
+}
+
+fn default_max_image_dimension() -> u32 {
+    32768
+}
+
+fn default_max_decode_memory_mb() -> u32 {
+    1024
+}
+
+fn default_convert_to_srgb() -> bool {
+    true
+}
+
+fn default_recursive_browsing() -> bool {
+    false
+}
+
+fn default_recursive_max_depth() -> u32 {
+    4
+}
+
+fn default_continue_into_next_folder() -> bool {
+    false
+}
+
+fn default_single_instance() -> bool {
+    false
+}
+
+fn default_control_socket() -> bool {
+    false
+}
+
 fn default_has_configured_initial_settings() -> bool {
     false
 }
@@ -224,6 +308,20 @@ impl Default for Config {
             grid_overlay_mode: default_grid_overlay_mode(),
             grid_color: default_grid_color(),
             grid_line_strength: default_grid_line_strength(),
+            image_cache_budget_mb: default_image_cache_budget_mb(),
+            prefetch_count: default_prefetch_count(),
+            sort_order: default_sort_order(),
+            sort_descending: default_sort_descending(),
+            tone_mapping: default_tone_mapping(),
+            exposure: default_exposure(),
+            max_image_dimension: default_max_image_dimension(),
+            max_decode_memory_mb: default_max_decode_memory_mb(),
+            convert_to_srgb: default_convert_to_srgb(),
+            recursive_browsing: default_recursive_browsing(),
+            recursive_max_depth: default_recursive_max_depth(),
+            continue_into_next_folder: default_continue_into_next_folder(),
+            single_instance: default_single_instance(),
+            control_socket: default_control_socket(),
         }
     }
 }
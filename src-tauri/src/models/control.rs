@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::image::{AiDetectionResult, ExportOptions, ImageMetadata};
+
+/// A command sent to the control socket, as a single line of JSON such as
+/// `{"command": "open", "path": "/photos/cat.jpg"}`.
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlCommand {
+    /// Opens an image, or the first image of a directory or archive.
+    Open { path: String },
+    /// Moves to the next image of the directory.
+    Next,
+    /// Moves to the previous image of the directory.
+    Previous,
+    /// Rotates the displayed image by 90 degrees.
+    Rotate {
+        #[serde(default)]
+        direction: RotationDirection,
+    },
+    /// Saves the displayed image, as it is rotated, to the given path without showing a dialog.
+    Export {
+        path: String,
+        /// The format to save in, taken from the extension of `path` if omitted.
+        format: Option<String>,
+        #[serde(default)]
+        options: ExportOptions,
+    },
+    /// Reports the displayed image.
+    Current,
+    /// Checks the displayed image, or the given one, for signs of AI generation.
+    DetectAi { path: Option<String> },
+    /// Keeps the connection open and pushes an event line whenever the displayed image changes.
+    Subscribe,
+}
+
+/// The direction of a rotation requested through the control socket.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationDirection {
+    #[default]
+    Clockwise,
+    Counterclockwise,
+}
+
+/// The result of a successful control command.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum ControlResult {
+    /// The image displayed after opening or navigating.
+    Image {
+        path: String,
+        metadata: ImageMetadata,
+    },
+    /// The rotation of the displayed image in degrees.
+    Rotation { rotation: i32 },
+    /// The path of an exported file.
+    Exported { path: String },
+    /// The displayed image and its place in the directory listing.
+    Current {
+        path: Option<String>,
+        rotation: i32,
+        position: Option<usize>,
+        total: usize,
+    },
+    /// The outcome of AI detection.
+    AiDetection(AiDetectionResult),
+    /// Commands without a result, such as `subscribe`.
+    Empty {},
+}
+
+/// The reply to a control command, written back as a single line of JSON.
+#[derive(Serialize)]
+pub struct ControlReply {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<ControlResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// An event pushed to the subscribers of the control socket.
+#[derive(Serialize, Clone)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum ControlEvent {
+    /// Another image is displayed.
+    ImageChanged { path: String },
+    /// The displayed image was rotated.
+    RotationChanged { rotation: i32 },
+}
@@ -0,0 +1,20 @@
+use serde::Serialize;
+
+#[derive(Serialize, Clone)]
+pub struct DirectoryChange {
+    pub directory: String,
+    pub files: Vec<String>,
+}
+
+/// The folders around the one being viewed, for jumping between folders.
+#[derive(Serialize)]
+pub struct RelatedDirectories {
+    /// The folder being viewed.
+    pub directory: String,
+    /// The folder containing it, or `None` at the root of the filesystem.
+    pub parent: Option<String>,
+    /// The folders next to it, including itself, sorted by name.
+    pub siblings: Vec<String>,
+    /// The subfolders of the folder, sorted by name.
+    pub children: Vec<String>,
+}
@@ -0,0 +1,27 @@
+use serde::Serialize;
+
+/// An image file that is part of a group of duplicates.
+#[derive(Serialize, Clone)]
+pub struct DuplicateFile {
+    pub path: String,
+    pub file_size: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A group of images that are exact copies of each other or look alike.
+/// The files are ordered best copy first: largest dimensions, then largest file size.
+#[derive(Serialize, Clone)]
+pub struct DuplicateGroup {
+    /// "exact" for files with identical contents, "similar" for near-duplicates.
+    pub kind: String,
+    pub files: Vec<DuplicateFile>,
+}
+
+/// The progress of a duplicate scan, emitted as a `duplicate-scan-progress` event after every file.
+#[derive(Serialize, Clone)]
+pub struct DuplicateScanProgress {
+    pub processed: usize,
+    pub total: usize,
+    pub path: String,
+}
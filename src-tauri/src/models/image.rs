@@ -1,8 +1,8 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Clone)]
 pub struct ImageMetadata {
-    pub image_data: String,
+    pub image_url: String,
     pub exif_data: String,
     pub width: u32,
     pub height: u32,
@@ -10,6 +10,48 @@ pub struct ImageMetadata {
     pub format: String,
     pub color_depth: Option<u8>,
     pub file_size: u64,
+    pub page_count: Option<u32>,
+    /// The dynamic range of images with more than 8 bits per channel, in stops.
+    pub dynamic_range: Option<f32>,
+    /// The dimensions of the sensor data of camera RAW files, which are shown through a smaller preview.
+    pub sensor_width: Option<u32>,
+    pub sensor_height: Option<u32>,
+    /// The number of frames of animated images.
+    pub frame_count: Option<u32>,
+    /// The display duration of every frame of animated images, in milliseconds.
+    pub frame_delays: Option<Vec<u32>>,
+    /// How many times an animation plays, where 0 means it repeats forever.
+    pub loop_count: Option<u32>,
+    /// The duration of a single play of an animation, in milliseconds.
+    pub total_duration: Option<u64>,
+    /// The `viewBox` of SVG images as `[min-x, min-y, width, height]`.
+    pub view_box: Option<[f32; 4]>,
+    /// The description of the embedded ICC color profile, such as "Display P3".
+    pub color_profile: Option<String>,
+    /// The path of the archive images opened from an archive are stored in.
+    pub archive_path: Option<String>,
+    /// The name of the archive entry images opened from an archive are stored as.
+    pub archive_entry: Option<String>,
+}
+
+/// Options for saving an image in another format.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    /// Optional quality setting for lossy formats (0.0-100.0).
+    pub quality: Option<f32>,
+    /// The rotation angle in degrees (0, 90, 180, 270).
+    #[serde(default)]
+    pub rotation: i32,
+    /// The highest frame rate of an exported animation. Faster animations drop frames.
+    pub max_fps: Option<f32>,
+    /// The largest width or height of an exported animation. Larger animations are scaled down.
+    pub max_dimension: Option<u32>,
+    /// The factor the intrinsic size of SVG images is multiplied with when they are rasterized.
+    pub scale: Option<f32>,
+    /// How the color profile of the source image is handled: "embed" keeps it in the saved file,
+    /// "srgb" converts the pixels to sRGB. Formats that cannot carry a profile are always converted.
+    pub color_profile: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -17,3 +59,82 @@ pub struct AiDetectionResult {
     pub is_ai_generated: bool,
     pub format: String,
 }
+
+/// The distribution of the values of a single channel of an image.
+#[derive(Serialize)]
+pub struct ChannelStatistics {
+    /// The number of pixels with each of the 256 possible values.
+    pub histogram: Vec<u64>,
+    pub min: u8,
+    pub max: u8,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// The percentage of pixels at the lowest value, 0.
+    pub clipped_shadows: f64,
+    /// The percentage of pixels at the highest value, 255.
+    pub clipped_highlights: f64,
+}
+
+/// The per-channel statistics of an image, as computed on its 8-bit display rendition.
+#[derive(Serialize)]
+pub struct ImageStatistics {
+    pub red: ChannelStatistics,
+    pub green: ChannelStatistics,
+    pub blue: ChannelStatistics,
+    /// The Rec. 709 luma of the pixels.
+    pub luma: ChannelStatistics,
+    pub pixel_count: u64,
+    /// Whether the image has an alpha channel.
+    pub has_alpha: bool,
+    /// Whether any pixel is not fully opaque.
+    pub alpha_used: bool,
+}
+
+/// The value of a pixel of an image, read from the decoded source at full bit depth.
+#[derive(Serialize)]
+pub struct PixelInfo {
+    /// The bits per channel of the source image: 8, 16 or 32 (floating point).
+    pub bit_depth: u8,
+    /// The red, green, blue and alpha values at the source bit depth.
+    pub raw: [f64; 4],
+    /// The color as an `#rrggbb` hex string.
+    pub hex: String,
+    pub rgb: [u8; 3],
+    /// The opacity, from 0 to 1.
+    pub alpha: f64,
+    /// Hue in degrees, saturation and lightness in percent.
+    pub hsl: [f64; 3],
+    /// Hue in degrees, saturation and value in percent.
+    pub hsv: [f64; 3],
+    /// CIE L*a*b* under the D65 white point.
+    pub lab: [f64; 3],
+    /// The side length of the square area the values are averaged over, 1 for a single pixel.
+    pub area: u32,
+}
+
+/// The result of comparing two images.
+#[derive(Serialize)]
+pub struct ImageComparison {
+    /// The size both images were compared at.
+    pub width: u32,
+    pub height: u32,
+    /// Whether one of the images was scaled to the size of the other.
+    pub scaled: bool,
+    /// The peak signal-to-noise ratio in dB, or `None` if the images are identical.
+    pub psnr: Option<f64>,
+    /// The mean structural similarity index, from -1 to 1, where 1 means identical.
+    pub ssim: f64,
+    /// The largest difference of any channel of any pixel, from 0 to 255.
+    pub max_error: u8,
+    /// A heatmap of the differences as a base64-encoded PNG data URL.
+    pub heatmap: String,
+}
+
+/// The thumbnail of an image file in a directory listing.
+#[derive(Serialize)]
+pub struct ThumbnailEntry {
+    /// The path of the image file.
+    pub path: String,
+    /// The URL the thumbnail is served at, or `None` if no thumbnail can be made for the file.
+    pub url: Option<String>,
+}
@@ -0,0 +1,15 @@
+use serde::Serialize;
+
+/// What to show after the application was launched with arguments, emitted to the frontend
+/// as the `image-source` event.
+#[derive(Serialize, Clone, Default, Debug)]
+pub struct LaunchRequest {
+    /// The image to open first.
+    pub path: String,
+    /// Whether the window switches to fullscreen.
+    pub fullscreen: bool,
+    /// Whether zen mode is entered.
+    pub zen: bool,
+    /// The number of seconds each image of a slideshow is shown, if one is started.
+    pub slideshow: Option<f64>,
+}
@@ -1,2 +1,7 @@
+pub mod cli;
 pub mod config;
+pub mod control;
+pub mod directory;
+pub mod duplicates;
 pub mod image;
+pub mod launch;
@@ -0,0 +1,304 @@
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::imageops::FilterType;
+use image::{Delay, DynamicImage, Frame, ImageFormat, RgbaImage};
+use std::fs::{self, File};
+use std::io::{BufWriter, Cursor};
+use std::path::Path;
+
+use crate::models::image::ExportOptions;
+use crate::utils::animation::{open_frames, read_animation_info, AnimationInfo};
+use crate::utils::decode_limits::{DecodeError, DecodeLimits};
+use crate::utils::image_formats::{identify_reader_format, FormatDecoder};
+use crate::utils::image_processing::apply_user_rotation;
+
+/// An animated image that can be saved as another animated format.
+pub struct AnimatedSource {
+    format: ImageFormat,
+    info: AnimationInfo,
+}
+
+/// A decoded frame together with its display duration in milliseconds.
+type TimedFrame = (RgbaImage, u32);
+
+/// Checks whether the given image is an animation that stays animated when saved in the target format.
+///
+/// # Arguments
+/// * `bytes` - The image bytes.
+/// * `source_path` - The path or name the image bytes were read from, used to identify their format.
+/// * `target` - The format the image is saved in.
+///
+/// # Returns
+/// `Option<AnimatedSource>` - The animation, or `None` for still images and targets without animation support.
+pub fn probe_animated_source(
+    bytes: &[u8],
+    source_path: &Path,
+    target: ImageFormat,
+) -> Option<AnimatedSource> {
+    if !matches!(
+        target,
+        ImageFormat::Gif | ImageFormat::Png | ImageFormat::WebP
+    ) {
+        return None;
+    }
+    let mut reader = Cursor::new(bytes);
+    let FormatDecoder::Image(format) = identify_reader_format(&mut reader, source_path)
+        .ok()?
+        .decoder
+    else {
+        return None;
+    };
+    let info = read_animation_info(&mut reader, format).ok()??;
+    Some(AnimatedSource { format, info })
+}
+
+/// Saves an animation in the target format, keeping its frame timing and loop count.
+///
+/// Frames are decoded fully composited and written as full-canvas frames, so the saved
+/// animation plays like the source regardless of the disposal and blending modes it used.
+///
+/// # Arguments
+/// * `source` - The animation, as returned by `probe_animated_source`.
+/// * `bytes` - The image bytes of the animation.
+/// * `save_path` - The path to save the animation to.
+/// * `target` - The format to save the animation in: GIF, PNG or WebP.
+/// * `options` - The quality, rotation, frame rate and size of the saved animation.
+/// * `limits` - The limits the frames are decoded under. The decoded frames must fit into its memory limit.
+///
+/// # Returns
+/// `Result<(), String>` - The result of the save operation. Returns an error string if the save operation fails.
+pub fn save_animation(
+    source: &AnimatedSource,
+    bytes: &[u8],
+    save_path: &Path,
+    target: ImageFormat,
+    options: &ExportOptions,
+    limits: &DecodeLimits,
+) -> Result<(), String> {
+    let frames = decode_frames(source, bytes, options, limits)?;
+    if frames.is_empty() {
+        return Err("The animation has no frames".to_string());
+    }
+    let loop_count = source.info.loop_count;
+
+    match target {
+        ImageFormat::Gif => save_gif(frames, loop_count, save_path),
+        ImageFormat::Png => save_apng(&frames, loop_count, save_path),
+        ImageFormat::WebP => save_animated_webp(&frames, loop_count, options.quality, save_path),
+        _ => Err(format!("{:?} images cannot be animated", target)),
+    }
+}
+
+/// Decodes the frames kept at the requested frame rate, rotating and scaling each of them.
+fn decode_frames(
+    source: &AnimatedSource,
+    bytes: &[u8],
+    options: &ExportOptions,
+    limits: &DecodeLimits,
+) -> Result<Vec<TimedFrame>, DecodeError> {
+    let frames = open_frames(Cursor::new(bytes), source.format, limits)?
+        .ok_or_else(|| DecodeError::Failed("The image is not animated".to_string()))?;
+    let mut kept = select_frames(&source.info.frame_delays, options.max_fps).into_iter();
+    let mut next = kept.next();
+
+    let mut decoded = Vec::new();
+    let mut allocated = 0u64;
+    for (index, frame) in frames.enumerate() {
+        let Some((kept_index, duration)) = next else {
+            break;
+        };
+        let frame =
+            frame.map_err(|e| DecodeError::from_image_error(e, "Failed to decode frame"))?;
+        if index != kept_index {
+            continue;
+        }
+
+        let img = apply_user_rotation(
+            DynamicImage::ImageRgba8(frame.into_buffer()),
+            options.rotation,
+        );
+        let buffer = fit_within(img, options.max_dimension).into_rgba8();
+        allocated += buffer.as_raw().len() as u64;
+        if allocated > limits.max_alloc {
+            return Err(DecodeError::LimitsExceeded(format!(
+                "the frames need more than {} MB",
+                limits.max_alloc / (1024 * 1024)
+            )));
+        }
+        decoded.push((buffer, duration));
+        next = kept.next();
+    }
+    Ok(decoded)
+}
+
+/// Picks the frames kept when the animation is limited to the given frame rate.
+///
+/// A frame is dropped if it starts too soon after the previous kept frame, which is then
+/// shown for the dropped frame's duration as well, so the total duration stays the same.
+///
+/// # Arguments
+/// * `delays` - The duration of every frame in milliseconds.
+/// * `max_fps` - The highest frame rate, or `None` to keep every frame.
+///
+/// # Returns
+/// `Vec<(usize, u32)>` - The index and new duration of every kept frame, in order.
+fn select_frames(delays: &[u32], max_fps: Option<f32>) -> Vec<(usize, u32)> {
+    let min_interval = match max_fps {
+        Some(fps) if fps.is_finite() && fps > 0.0 => (1000.0 / fps).ceil() as u64,
+        _ => 0,
+    };
+
+    let mut kept: Vec<(usize, u32)> = Vec::new();
+    let mut elapsed = 0u64;
+    let mut last_start = 0u64;
+    for (index, &delay) in delays.iter().enumerate() {
+        match kept.last_mut() {
+            Some((_, duration)) if elapsed - last_start < min_interval => *duration += delay,
+            _ => {
+                kept.push((index, delay));
+                last_start = elapsed;
+            }
+        }
+        elapsed += delay as u64;
+    }
+    kept
+}
+
+/// Scales an image down so that neither side exceeds the given size, keeping its aspect ratio.
+fn fit_within(img: DynamicImage, max_dimension: Option<u32>) -> DynamicImage {
+    match max_dimension {
+        Some(max) if max > 0 && (img.width() > max || img.height() > max) => {
+            img.resize(max, max, FilterType::Lanczos3)
+        }
+        _ => img,
+    }
+}
+
+/// Saves the frames as an animated GIF.
+///
+/// # Arguments
+/// * `frames` - The frames and their durations.
+/// * `loop_count` - How many times the animation plays, where 0 means it repeats forever.
+/// * `save_path` - The destination path to save the animation.
+fn save_gif(frames: Vec<TimedFrame>, loop_count: u32, save_path: &Path) -> Result<(), String> {
+    let file = File::create(save_path).map_err(|e| format!("Failed to create GIF file: {}", e))?;
+    let mut encoder = GifEncoder::new(BufWriter::new(file));
+    let encode_error = |e| format!("Failed to save animated GIF: {}", e);
+
+    // The NETSCAPE extension counts repetitions after the first play; without it the animation plays once.
+    match loop_count {
+        0 => encoder.set_repeat(Repeat::Infinite).map_err(encode_error)?,
+        1 => {}
+        n => encoder
+            .set_repeat(Repeat::Finite((n - 1).min(u16::MAX as u32) as u16))
+            .map_err(encode_error)?,
+    }
+    for (buffer, duration) in frames {
+        let delay = Delay::from_numer_denom_ms(duration, 1);
+        encoder
+            .encode_frame(Frame::from_parts(buffer, 0, 0, delay))
+            .map_err(encode_error)?;
+    }
+    Ok(())
+}
+
+/// Saves the frames as an animated PNG.
+///
+/// # Arguments
+/// * `frames` - The frames and their durations.
+/// * `loop_count` - How many times the animation plays, where 0 means it repeats forever.
+/// * `save_path` - The destination path to save the animation.
+fn save_apng(frames: &[TimedFrame], loop_count: u32, save_path: &Path) -> Result<(), String> {
+    let (width, height) = frames[0].0.dimensions();
+    let file = File::create(save_path).map_err(|e| format!("Failed to create PNG file: {}", e))?;
+    let encode_error = |e| format!("Failed to save animated PNG: {}", e);
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, loop_count)
+        .map_err(encode_error)?;
+    let mut writer = encoder.write_header().map_err(encode_error)?;
+    for (buffer, duration) in frames {
+        // Durations beyond the 16-bit numerator are stored in hundredths of a second.
+        let (numerator, denominator) = match u16::try_from(*duration) {
+            Ok(ms) => (ms, 1000),
+            Err(_) => ((duration / 10).min(u16::MAX as u32) as u16, 100),
+        };
+        writer
+            .set_frame_delay(numerator, denominator)
+            .map_err(encode_error)?;
+        writer.write_image_data(buffer).map_err(encode_error)?;
+    }
+    writer.finish().map_err(encode_error)
+}
+
+/// Saves the frames as an animated WebP.
+///
+/// # Arguments
+/// * `frames` - The frames and their durations.
+/// * `loop_count` - How many times the animation plays, where 0 means it repeats forever.
+/// * `quality` - Optional quality setting (0.0-100.0). Defaults to 75.0 if not provided.
+/// * `save_path` - The destination path to save the animation.
+fn save_animated_webp(
+    frames: &[TimedFrame],
+    loop_count: u32,
+    quality: Option<f32>,
+    save_path: &Path,
+) -> Result<(), String> {
+    let (width, height) = frames[0].0.dimensions();
+    let mut config =
+        webp::WebPConfig::new().map_err(|_| "Failed to configure the WebP encoder".to_string())?;
+    config.quality = quality.unwrap_or(75.0);
+
+    let mut encoder = webp::AnimEncoder::new(width, height, &config);
+    encoder.set_loop_count(loop_count.min(u16::MAX as u32) as i32);
+    let mut timestamp = 0u32;
+    for (buffer, duration) in frames {
+        encoder.add_frame(webp::AnimFrame::from_rgba(
+            buffer,
+            width,
+            height,
+            timestamp as i32,
+        ));
+        timestamp += duration;
+    }
+    let mut bytes = encoder
+        .try_encode()
+        .map_err(|e| format!("Failed to save animated WebP: {:?}", e))?
+        .to_vec();
+    set_last_webp_frame_duration(&mut bytes, timestamp);
+
+    fs::write(save_path, &bytes).map_err(|e| format!("Failed to save animated WebP: {}", e))
+}
+
+/// Sets the duration of the last `ANMF` chunk of an animated WebP so the animation lasts the given time.
+///
+/// The encoder only learns a frame's duration from the start of the next frame, and guesses
+/// the duration of the last one.
+fn set_last_webp_frame_duration(bytes: &mut [u8], total_duration: u32) {
+    let mut offset = 12;
+    let mut elapsed = 0u32;
+    let mut last_frame = None;
+    while offset + 8 <= bytes.len() {
+        let len = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        if &bytes[offset..offset + 4] == b"ANMF" && offset + 24 <= bytes.len() {
+            let field = offset + 20;
+            if let Some(previous) = last_frame.replace(field) {
+                elapsed += u32::from_le_bytes([
+                    bytes[previous],
+                    bytes[previous + 1],
+                    bytes[previous + 2],
+                    0,
+                ]);
+            }
+        }
+        // Chunks are padded to an even size.
+        offset += 8 + len + (len & 1);
+    }
+
+    if let Some(field) = last_frame {
+        let duration = total_duration.saturating_sub(elapsed).min(0xFF_FFFF);
+        bytes[field..field + 3].copy_from_slice(&duration.to_le_bytes()[..3]);
+    }
+}
@@ -0,0 +1,368 @@
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, Frames, ImageDecoder, ImageFormat};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::utils::decode_limits::{DecodeError, DecodeLimits};
+use crate::utils::image_formats::{open_image, sniff_file_format, FormatDecoder};
+
+/// The timing of an animated image, read without decoding any frames.
+pub struct AnimationInfo {
+    /// The display duration of every frame in milliseconds, as stored in the file.
+    pub frame_delays: Vec<u32>,
+    /// How many times the animation plays, where 0 means it repeats forever.
+    pub loop_count: u32,
+}
+
+impl AnimationInfo {
+    pub fn frame_count(&self) -> u32 {
+        self.frame_delays.len() as u32
+    }
+
+    /// Returns the duration of a single play of the animation in milliseconds.
+    pub fn total_duration(&self) -> u64 {
+        self.frame_delays.iter().map(|&delay| delay as u64).sum()
+    }
+}
+
+/// Reads the frame timing of an animated GIF, APNG or animated WebP image.
+///
+/// # Arguments
+/// * `reader` - A reader over the image data.
+/// * `format` - The format of the image.
+///
+/// # Returns
+/// `Result<Option<AnimationInfo>, String>` - The animation timing, `None` for still images,
+/// or an error string if the image cannot be read.
+pub fn read_animation_info<R: Read + Seek>(
+    reader: &mut R,
+    format: ImageFormat,
+) -> Result<Option<AnimationInfo>, String> {
+    reader
+        .seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to rewind image reader: {}", e))?;
+    let info = match format {
+        ImageFormat::Gif => read_gif_animation(reader),
+        ImageFormat::Png => read_apng_animation(reader),
+        ImageFormat::WebP => read_webp_animation(reader),
+        _ => return Ok(None),
+    }
+    .map_err(|e| format!("Failed to read animation: {}", e))?;
+    Ok(info.filter(|info| info.frame_count() > 1))
+}
+
+/// Walks the blocks of a GIF file, collecting the delay of every frame and the loop count
+/// from the NETSCAPE application extension. Image data is skipped.
+fn read_gif_animation<R: Read>(reader: &mut R) -> io::Result<Option<AnimationInfo>> {
+    let mut header = [0u8; 13];
+    reader.read_exact(&mut header)?;
+    if !header.starts_with(b"GIF8") {
+        return Ok(None);
+    }
+    skip_color_table(reader, header[10])?;
+
+    let mut frame_delays = Vec::new();
+    let mut pending_delay = None;
+    let mut repetitions = None;
+    loop {
+        let mut introducer = [0u8; 1];
+        if reader.read_exact(&mut introducer).is_err() {
+            break;
+        }
+        match introducer[0] {
+            // Extension
+            0x21 => {
+                let mut label = [0u8; 1];
+                reader.read_exact(&mut label)?;
+                let blocks = read_sub_blocks(reader, 2)?;
+                match (label[0], blocks.as_slice()) {
+                    // Graphic control extension, with the delay in hundredths of a second.
+                    (0xF9, [control, ..]) if control.len() >= 3 => {
+                        pending_delay =
+                            Some(u16::from_le_bytes([control[1], control[2]]) as u32 * 10);
+                    }
+                    // Application extension, carrying the loop count in NETSCAPE2.0 and ANIMEXTS1.0.
+                    (0xFF, [identifier, data, ..])
+                        if (identifier.as_slice() == b"NETSCAPE2.0"
+                            || identifier.as_slice() == b"ANIMEXTS1.0")
+                            && data.len() >= 3
+                            && data[0] == 1 =>
+                    {
+                        repetitions = Some(u16::from_le_bytes([data[1], data[2]]) as u32);
+                    }
+                    _ => {}
+                }
+            }
+            // Image descriptor
+            0x2C => {
+                let mut descriptor = [0u8; 9];
+                reader.read_exact(&mut descriptor)?;
+                skip_color_table(reader, descriptor[8])?;
+                let mut lzw_code_size = [0u8; 1];
+                reader.read_exact(&mut lzw_code_size)?;
+                read_sub_blocks(reader, 0)?;
+                frame_delays.push(pending_delay.take().unwrap_or(0));
+            }
+            // Trailer, or anything unexpected in a damaged file
+            _ => break,
+        }
+    }
+
+    // The NETSCAPE extension counts repetitions after the first play.
+    let loop_count = match repetitions {
+        None => 1,
+        Some(0) => 0,
+        Some(n) => n + 1,
+    };
+    Ok(Some(AnimationInfo {
+        frame_delays,
+        loop_count,
+    }))
+}
+
+/// Skips the color table announced by the packed field of a GIF screen or image descriptor.
+fn skip_color_table<R: Read>(reader: &mut R, packed: u8) -> io::Result<()> {
+    if packed & 0x80 != 0 {
+        let len = 3 * (1u64 << ((packed & 0x07) + 1));
+        io::copy(&mut (&mut *reader).take(len), &mut io::sink())?;
+    }
+    Ok(())
+}
+
+/// Reads a chain of GIF data sub-blocks, keeping the contents of the first `keep` blocks
+/// and skipping the rest.
+fn read_sub_blocks<R: Read>(reader: &mut R, keep: usize) -> io::Result<Vec<Vec<u8>>> {
+    let mut blocks = Vec::new();
+    let mut index = 0;
+    loop {
+        let mut len = [0u8; 1];
+        reader.read_exact(&mut len)?;
+        if len[0] == 0 {
+            return Ok(blocks);
+        }
+        if index < keep {
+            let mut block = vec![0u8; len[0] as usize];
+            reader.read_exact(&mut block)?;
+            blocks.push(block);
+        } else {
+            io::copy(&mut (&mut *reader).take(len[0] as u64), &mut io::sink())?;
+        }
+        index += 1;
+    }
+}
+
+/// Walks the chunks of a PNG file, reading the loop count from the `acTL` chunk
+/// and the frame delays from the `fcTL` chunks. Files without `acTL` are still images.
+fn read_apng_animation<R: Read + Seek>(reader: &mut R) -> io::Result<Option<AnimationInfo>> {
+    reader.seek(SeekFrom::Start(8))?;
+    let mut loop_count = None;
+    let mut frame_delays = Vec::new();
+    loop {
+        let mut chunk_header = [0u8; 8];
+        if reader.read_exact(&mut chunk_header).is_err() {
+            break;
+        }
+        let len = u32::from_be_bytes(chunk_header[0..4].try_into().unwrap()) as i64;
+        let mut consumed = 0;
+        match &chunk_header[4..8] {
+            b"acTL" => {
+                let mut data = [0u8; 8];
+                reader.read_exact(&mut data)?;
+                consumed = data.len() as i64;
+                loop_count = Some(u32::from_be_bytes(data[4..8].try_into().unwrap()));
+            }
+            b"fcTL" => {
+                let mut data = [0u8; 24];
+                reader.read_exact(&mut data)?;
+                consumed = data.len() as i64;
+                let numerator = u16::from_be_bytes([data[20], data[21]]) as u32;
+                // A denominator of 0 means hundredths of a second.
+                let denominator = match u16::from_be_bytes([data[22], data[23]]) {
+                    0 => 100,
+                    d => d as u32,
+                };
+                frame_delays.push(numerator * 1000 / denominator);
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+        // Skip the rest of the chunk and its CRC.
+        reader.seek(SeekFrom::Current(len - consumed + 4))?;
+    }
+
+    Ok(loop_count.map(|loop_count| AnimationInfo {
+        frame_delays,
+        loop_count,
+    }))
+}
+
+/// Walks the chunks of a WebP file, reading the loop count from the `ANIM` chunk
+/// and the frame durations from the `ANMF` chunks. Files without `ANIM` are still images.
+fn read_webp_animation<R: Read + Seek>(reader: &mut R) -> io::Result<Option<AnimationInfo>> {
+    let mut riff_header = [0u8; 12];
+    reader.read_exact(&mut riff_header)?;
+    if &riff_header[0..4] != b"RIFF" || &riff_header[8..12] != b"WEBP" {
+        return Ok(None);
+    }
+
+    let mut loop_count = None;
+    let mut frame_delays = Vec::new();
+    loop {
+        let mut chunk_header = [0u8; 8];
+        if reader.read_exact(&mut chunk_header).is_err() {
+            break;
+        }
+        let len = u32::from_le_bytes(chunk_header[4..8].try_into().unwrap()) as i64;
+        let mut consumed = 0;
+        match &chunk_header[0..4] {
+            b"ANIM" => {
+                let mut data = [0u8; 6];
+                reader.read_exact(&mut data)?;
+                consumed = data.len() as i64;
+                loop_count = Some(u16::from_le_bytes([data[4], data[5]]) as u32);
+            }
+            b"ANMF" => {
+                let mut data = [0u8; 16];
+                reader.read_exact(&mut data)?;
+                consumed = data.len() as i64;
+                frame_delays.push(u32::from_le_bytes([data[12], data[13], data[14], 0]));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even size.
+        reader.seek(SeekFrom::Current(len - consumed + (len & 1)))?;
+    }
+
+    Ok(loop_count.map(|loop_count| AnimationInfo {
+        frame_delays,
+        loop_count,
+    }))
+}
+
+/// Decodes a range of frames of the image file at the given path.
+///
+/// Frames are fully composited, so each one looks as it does when the animation is played.
+/// Still images have a single frame with index 0.
+///
+/// # Arguments
+/// * `path` - The path to the image file.
+/// * `start` - The index of the first frame to decode.
+/// * `end` - The index of the last frame to decode, inclusive.
+/// * `limits` - The limits the frames are decoded under.
+/// * `on_frame` - Called with the index and contents of every decoded frame, in order.
+///
+/// # Returns
+/// `Result<(), DecodeError>` - `Ok(())` if all frames of the range were decoded, or an error
+/// if the range is out of bounds or a frame cannot be decoded.
+pub fn decode_animation_frames<F>(
+    path: &Path,
+    start: u32,
+    end: u32,
+    limits: &DecodeLimits,
+    mut on_frame: F,
+) -> Result<(), DecodeError>
+where
+    F: FnMut(u32, DynamicImage) -> Result<(), String>,
+{
+    if start > end {
+        return Err(DecodeError::Failed(format!(
+            "Invalid frame range: {} to {}",
+            start, end
+        )));
+    }
+
+    let Some(frames) = open_animation_frames(path, limits)? else {
+        if start != 0 || end != 0 {
+            return Err(DecodeError::Failed(format!(
+                "Frame {} is out of range for a still image",
+                end
+            )));
+        }
+        return Ok(on_frame(0, open_image(path, limits)?)?);
+    };
+
+    let mut next = start;
+    for (index, frame) in frames.enumerate().skip(start as usize) {
+        if index as u32 > end {
+            break;
+        }
+        let frame =
+            frame.map_err(|e| DecodeError::from_image_error(e, "Failed to decode frame"))?;
+        on_frame(index as u32, DynamicImage::ImageRgba8(frame.into_buffer()))?;
+        next = index as u32 + 1;
+    }
+    if next <= end {
+        return Err(DecodeError::Failed(format!(
+            "Frame {} is out of range, the animation has {} frames",
+            end, next
+        )));
+    }
+    Ok(())
+}
+
+/// Opens the frame decoder for an animated image file.
+///
+/// # Returns
+/// `Result<Option<Frames<'static>>, DecodeError>` - The frames, or `None` for still images.
+fn open_animation_frames(
+    path: &Path,
+    limits: &DecodeLimits,
+) -> Result<Option<Frames<'static>>, DecodeError> {
+    let image_format = match sniff_file_format(path).map(|info| info.decoder) {
+        Some(FormatDecoder::Image(image_format)) => image_format,
+        _ => return Ok(None),
+    };
+    let file =
+        File::open(path).map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+    open_frames(BufReader::new(file), image_format, limits)
+}
+
+/// Opens the frame decoder for animated image data read from the given reader.
+///
+/// # Arguments
+/// * `reader` - A reader over the image data.
+/// * `image_format` - The format of the image.
+/// * `limits` - The limits the frames are decoded under.
+///
+/// # Returns
+/// `Result<Option<Frames<'a>>, DecodeError>` - The fully composited frames, or `None` for still images.
+pub fn open_frames<'a, R: BufRead + Seek + 'a>(
+    reader: R,
+    image_format: ImageFormat,
+    limits: &DecodeLimits,
+) -> Result<Option<Frames<'a>>, DecodeError> {
+    let open_error = |e| DecodeError::from_image_error(e, "Failed to open animation");
+
+    let frames = match image_format {
+        ImageFormat::Gif => {
+            let mut decoder = GifDecoder::new(reader).map_err(open_error)?;
+            decoder
+                .set_limits(limits.to_image_limits())
+                .map_err(open_error)?;
+            decoder.into_frames()
+        }
+        ImageFormat::Png => {
+            let decoder =
+                PngDecoder::with_limits(reader, limits.to_image_limits()).map_err(open_error)?;
+            if !decoder.is_apng().map_err(open_error)? {
+                return Ok(None);
+            }
+            decoder.apng().map_err(open_error)?.into_frames()
+        }
+        ImageFormat::WebP => {
+            let mut decoder = WebPDecoder::new(reader).map_err(open_error)?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder
+                .set_limits(limits.to_image_limits())
+                .map_err(open_error)?;
+            decoder.into_frames()
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(frames))
+}
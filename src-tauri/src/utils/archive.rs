@@ -0,0 +1,261 @@
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::utils::decode_limits::DecodeLimits;
+use crate::utils::file_sorting::natural_cmp;
+use crate::utils::image_formats::{identify_image_format, FORMAT_HEADER_LEN};
+
+/// The extensions of the archive formats that can be browsed, as used for the open dialog.
+pub const ARCHIVE_EXTENSIONS: [&str; 4] = ["zip", "cbz", "tar", "cbt"];
+
+/// The container formats images can be browsed in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArchiveKind {
+    /// ZIP archives, including CBZ comic books.
+    Zip,
+    /// Uncompressed tar archives, including CBT comic books.
+    Tar,
+}
+
+/// Identifies the archive format of a file by its magic bytes.
+///
+/// # Arguments
+/// * `path` - The path to the file.
+///
+/// # Returns
+/// `Option<ArchiveKind>` - The archive format, or `None` if the file is no supported archive.
+pub fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let mut header = Vec::with_capacity(512);
+    File::open(path)
+        .ok()?
+        .take(512)
+        .read_to_end(&mut header)
+        .ok()?;
+    if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        Some(ArchiveKind::Zip)
+    } else if header.get(257..262) == Some(b"ustar") {
+        Some(ArchiveKind::Tar)
+    } else {
+        None
+    }
+}
+
+/// Splits the path of an archive entry into the path of the archive and the name of the entry.
+///
+/// Entries are addressed as if the archive were a directory, e.g. `comic.cbz/pages/001.jpg`.
+/// Paths that exist on disk are never archive entries.
+///
+/// # Arguments
+/// * `path` - The path to check.
+///
+/// # Returns
+/// `Option<(PathBuf, String)>` - The archive and the entry name with `/` separators,
+/// or `None` if the path does not point into an archive.
+pub fn split_archive_path(path: &Path) -> Option<(PathBuf, String)> {
+    if path.exists() {
+        return None;
+    }
+    for archive in path.ancestors().skip(1) {
+        if archive.as_os_str().is_empty() {
+            return None;
+        }
+        match fs::metadata(archive) {
+            Ok(metadata) if metadata.is_file() => {
+                archive_kind(archive)?;
+                let entry = path
+                    .strip_prefix(archive)
+                    .ok()?
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                return Some((archive.to_path_buf(), entry));
+            }
+            // An existing directory means the path simply does not exist.
+            Ok(_) => return None,
+            Err(_) => continue,
+        }
+    }
+    None
+}
+
+/// Returns the file that holds the data of the given path: the archive for archive entries,
+/// the path itself otherwise.
+///
+/// # Arguments
+/// * `path` - A path to a file or an archive entry.
+///
+/// # Returns
+/// `PathBuf` - The path of the file on disk.
+pub fn storage_path(path: &Path) -> PathBuf {
+    split_archive_path(path)
+        .map(|(archive, _)| archive)
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Reads an image file, or an archive entry, into memory.
+///
+/// # Arguments
+/// * `path` - A path to a file or an archive entry.
+/// * `limits` - The decoding limits, whose memory limit caps the size of archive entries.
+///
+/// # Returns
+/// `Result<Vec<u8>, String>` - The contents of the file or entry, or an error string if it cannot be read.
+pub fn read_image_bytes(path: &Path, limits: &DecodeLimits) -> Result<Vec<u8>, String> {
+    match split_archive_path(path) {
+        Some((archive, entry)) => read_archive_entry(&archive, &entry, limits.max_alloc),
+        None => {
+            fs::read(path).map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))
+        }
+    }
+}
+
+/// Lists the images in an archive, in natural order of their entry names.
+///
+/// Entries are identified by their content, which is read from the archive stream without
+/// extracting anything to disk. Hidden entries, such as the `__MACOSX` resource forks of
+/// archives made on macOS, and entries whose names would point outside the archive are skipped.
+///
+/// # Arguments
+/// * `archive` - The path to the archive.
+///
+/// # Returns
+/// `Result<Vec<String>, String>` - The paths of the image entries, or an error string if the archive cannot be read.
+pub fn list_archive_images(archive: &Path) -> Result<Vec<String>, String> {
+    let kind = archive_kind(archive)
+        .ok_or_else(|| format!("Not a supported archive: {}", archive.display()))?;
+    let file = open_archive(archive)?;
+    let mut names = Vec::new();
+
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = zip::ZipArchive::new(file).map_err(archive_error)?;
+            for index in 0..zip.len() {
+                let mut entry = zip.by_index(index).map_err(archive_error)?;
+                if entry.is_dir() || !is_listed_entry(entry.name()) {
+                    continue;
+                }
+                let name = entry.name().to_string();
+                if is_image_entry(&name, &mut entry) {
+                    names.push(name);
+                }
+            }
+        }
+        ArchiveKind::Tar => {
+            let mut tar = tar::Archive::new(file);
+            for entry in tar.entries().map_err(archive_error)? {
+                let mut entry = entry.map_err(archive_error)?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry
+                    .path()
+                    .map_err(archive_error)?
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                if is_listed_entry(&name) && is_image_entry(&name, &mut entry) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+
+    names.sort_by(|a, b| natural_cmp(a, b));
+    Ok(names
+        .into_iter()
+        .map(|name| archive.join(name).to_string_lossy().to_string())
+        .collect())
+}
+
+/// Reads a single entry of an archive into memory.
+///
+/// # Arguments
+/// * `archive` - The path to the archive.
+/// * `entry` - The name of the entry, with `/` separators.
+/// * `max_size` - The largest entry size in bytes that is read.
+///
+/// # Returns
+/// `Result<Vec<u8>, String>` - The contents of the entry, or an error string if it cannot be found or is too large.
+pub fn read_archive_entry(archive: &Path, entry: &str, max_size: u64) -> Result<Vec<u8>, String> {
+    let kind = archive_kind(archive)
+        .ok_or_else(|| format!("Not a supported archive: {}", archive.display()))?;
+    let file = open_archive(archive)?;
+    let not_found = || format!("Entry '{}' not found in '{}'", entry, archive.display());
+
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = zip::ZipArchive::new(file).map_err(archive_error)?;
+            let zip_entry = zip.by_name(entry).map_err(|_| not_found())?;
+            read_entry(zip_entry, entry, max_size)
+        }
+        ArchiveKind::Tar => {
+            let mut tar = tar::Archive::new(file);
+            for tar_entry in tar.entries().map_err(archive_error)? {
+                let tar_entry = tar_entry.map_err(archive_error)?;
+                let matches = tar_entry
+                    .path()
+                    .is_ok_and(|path| path.to_string_lossy().replace('\\', "/") == entry);
+                if matches && tar_entry.header().entry_type().is_file() {
+                    return read_entry(tar_entry, entry, max_size);
+                }
+            }
+            Err(not_found())
+        }
+    }
+}
+
+/// Opens an archive for buffered reading.
+fn open_archive(archive: &Path) -> Result<BufReader<File>, String> {
+    File::open(archive)
+        .map(BufReader::new)
+        .map_err(|e| format!("Failed to open archive '{}': {}", archive.display(), e))
+}
+
+/// Formats an error raised while reading an archive.
+fn archive_error(e: impl std::fmt::Display) -> String {
+    format!("Failed to read archive: {}", e)
+}
+
+/// Returns `true` if an entry is neither hidden nor named to point outside the archive.
+fn is_listed_entry(name: &str) -> bool {
+    let path = Path::new(name);
+    path.components().all(|component| match component {
+        Component::Normal(part) => {
+            let part = part.to_string_lossy();
+            !part.starts_with('.') && part != "__MACOSX"
+        }
+        Component::CurDir => true,
+        _ => false,
+    })
+}
+
+/// Returns `true` if the entry read from the given reader holds an image, judged by its leading bytes.
+fn is_image_entry<R: Read>(name: &str, reader: &mut R) -> bool {
+    let mut header = Vec::with_capacity(FORMAT_HEADER_LEN as usize);
+    if reader
+        .take(FORMAT_HEADER_LEN)
+        .read_to_end(&mut header)
+        .is_err()
+    {
+        return false;
+    }
+    identify_image_format(Path::new(name), &header).is_some()
+}
+
+/// Reads an archive entry into memory, refusing entries larger than `max_size`.
+fn read_entry<R: Read>(reader: R, entry: &str, max_size: u64) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    reader
+        .take(max_size + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read entry '{}': {}", entry, e))?;
+    if bytes.len() as u64 > max_size {
+        return Err(format!(
+            "Entry '{}' exceeds the maximum size of {} MB",
+            entry,
+            max_size / (1024 * 1024)
+        ));
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,269 @@
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformExecutor, TransformOptions};
+use std::io::{BufRead, Seek};
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::models::config::Config;
+use crate::utils::config_utils::read_config;
+use crate::utils::image_formats::{identify_reader_format, FormatDecoder};
+
+/// Reads whether images with an embedded color profile are converted to sRGB for display,
+/// falling back to the default if the configuration cannot be read.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `bool` - Whether tagged images are converted to sRGB for display.
+pub fn load_convert_to_srgb(app: &AppHandle) -> bool {
+    read_config(app)
+        .ok()
+        .and_then(|config_str| serde_json::from_str::<Config>(&config_str).ok())
+        .unwrap_or_default()
+        .convert_to_srgb
+}
+
+/// Reads the ICC profile embedded in the image read from the given reader.
+///
+/// Profiles are found in the APP2 segments of JPEG files, the iCCP chunk of PNG files
+/// and the ICCP chunk of WebP files, as well as in TIFF files. The reader is left at an
+/// unspecified position.
+///
+/// # Arguments
+/// * `reader` - A reader over the image data, positioned at its start.
+/// * `path` - The path the data is read from, used to identify formats without magic bytes.
+///
+/// # Returns
+/// `Option<Vec<u8>>` - The raw ICC profile, or `None` if the image carries none or cannot be read.
+pub fn read_icc_profile<R: BufRead + Seek>(mut reader: R, path: &Path) -> Option<Vec<u8>> {
+    let FormatDecoder::Image(image_format) =
+        identify_reader_format(&mut reader, path).ok()?.decoder
+    else {
+        return None;
+    };
+    let mut decoder = ImageReader::with_format(reader, image_format)
+        .into_decoder()
+        .ok()?;
+    decoder.icc_profile().ok().flatten()
+}
+
+/// Returns the description stored in the `desc` tag of an ICC profile, such as "Display P3".
+///
+/// Both the `textDescriptionType` of version 2 profiles and the `multiLocalizedUnicodeType`
+/// of version 4 profiles are understood. Of localized descriptions, the English one is preferred.
+///
+/// # Arguments
+/// * `icc` - The raw ICC profile.
+///
+/// # Returns
+/// `Option<String>` - The profile description, or `None` if the profile has none or is malformed.
+pub fn profile_description(icc: &[u8]) -> Option<String> {
+    let tag_count = read_u32(icc, 128)? as usize;
+    let tag = (0..tag_count.min(256))
+        .map(|i| 132 + i * 12)
+        .find(|&entry| icc.get(entry..entry + 4) == Some(b"desc"))?;
+    let offset = read_u32(icc, tag + 4)? as usize;
+    let size = read_u32(icc, tag + 8)? as usize;
+    let data = icc.get(offset..offset.checked_add(size)?)?;
+
+    let description = match data.get(0..4)? {
+        b"desc" => {
+            let length = read_u32(data, 8)? as usize;
+            let text = data.get(12..12 + length)?;
+            let text = text.split(|&b| b == 0).next().unwrap_or_default();
+            String::from_utf8_lossy(text).into_owned()
+        }
+        b"mluc" => {
+            let record_count = read_u32(data, 8)? as usize;
+            let record_size = read_u32(data, 12)? as usize;
+            let records = (0..record_count.min(256)).map(|i| 16 + i * record_size.max(12));
+            let record = records
+                .clone()
+                .find(|&record| data.get(record..record + 2) == Some(b"en"))
+                .or_else(|| records.clone().next())?;
+            let length = read_u32(data, record + 4)? as usize;
+            let start = read_u32(data, record + 8)? as usize;
+            let text = data.get(start..start.checked_add(length)?)?;
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .take_while(|&unit| unit != 0)
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => return None,
+    };
+
+    let description = description.trim();
+    (!description.is_empty()).then(|| description.to_string())
+}
+
+/// Reads a big-endian `u32` at the given offset.
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Returns `true` if images tagged with the given ICC profile look different once converted to sRGB.
+///
+/// Only RGB profiles are converted. Profiles describing sRGB itself, and grayscale and CMYK
+/// profiles, whose images the decoders already hand out as plain gray or RGB values, are left alone.
+///
+/// # Arguments
+/// * `icc` - The raw ICC profile.
+///
+/// # Returns
+/// `bool` - Whether the image has to be converted to sRGB to be shown correctly.
+pub fn needs_srgb_conversion(icc: &[u8]) -> bool {
+    let is_srgb = profile_description(icc).is_some_and(|description| description.contains("sRGB"));
+    !is_srgb
+        && ColorProfile::new_from_slice(icc)
+            .is_ok_and(|profile| profile.color_space == DataColorSpace::Rgb)
+}
+
+/// Converts an image tagged with the given ICC profile to sRGB.
+///
+/// 8-bit and 16-bit images keep their precision and alpha channel. Floating point images
+/// hold scene-referred values the profile cannot describe and are returned unchanged,
+/// as are images whose profile does not need a conversion.
+///
+/// # Arguments
+/// * `img` - The image to convert.
+/// * `icc` - The raw ICC profile the image is tagged with.
+///
+/// # Returns
+/// `Result<DynamicImage, String>` - The image in sRGB, or an error string if the profile cannot be used.
+pub fn convert_to_srgb(img: &DynamicImage, icc: &[u8]) -> Result<DynamicImage, String> {
+    if !needs_srgb_conversion(icc) {
+        return Ok(img.clone());
+    }
+    let source = ColorProfile::new_from_slice(icc)
+        .map_err(|e| format!("Failed to read ICC profile: {}", e))?;
+    let srgb = ColorProfile::new_srgb();
+    let options = TransformOptions::default();
+    let has_alpha = img.color().has_alpha();
+    let layout = if has_alpha { Layout::Rgba } else { Layout::Rgb };
+    let transform_error = |e: moxcms::CmsError| format!("Failed to convert to sRGB: {}", e);
+
+    match img {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => Ok(img.clone()),
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_) => {
+            let transform = source
+                .create_transform_16bit(layout, &srgb, layout, options)
+                .map_err(transform_error)?;
+            if has_alpha {
+                let mut buffer = img.to_rgba16();
+                apply_transform(transform.as_ref(), &mut buffer)?;
+                Ok(DynamicImage::ImageRgba16(buffer))
+            } else {
+                let mut buffer = img.to_rgb16();
+                apply_transform(transform.as_ref(), &mut buffer)?;
+                Ok(DynamicImage::ImageRgb16(buffer))
+            }
+        }
+        _ => {
+            let transform = source
+                .create_transform_8bit(layout, &srgb, layout, options)
+                .map_err(transform_error)?;
+            if has_alpha {
+                let mut buffer = img.to_rgba8();
+                apply_transform(transform.as_ref(), &mut buffer)?;
+                Ok(DynamicImage::ImageRgba8(buffer))
+            } else {
+                let mut buffer = img.to_rgb8();
+                apply_transform(transform.as_ref(), &mut buffer)?;
+                Ok(DynamicImage::ImageRgb8(buffer))
+            }
+        }
+    }
+}
+
+/// Runs a color transform over a buffer of samples in place.
+fn apply_transform<T: Copy + Default>(
+    transform: &(dyn TransformExecutor<T> + Send + Sync),
+    samples: &mut [T],
+) -> Result<(), String> {
+    let source = samples.to_vec();
+    transform
+        .transform(&source, samples)
+        .map_err(|e| format!("Failed to convert to sRGB: {}", e))
+}
+
+/// Returns `true` if an ICC profile can be embedded into images saved in the given format.
+pub fn can_embed_icc_profile(image_format: ImageFormat) -> bool {
+    matches!(
+        image_format,
+        ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP
+    )
+}
+
+/// Embeds an ICC profile into an encoded WebP image.
+///
+/// Simple WebP files are turned into the extended format, whose VP8X header announces the
+/// ICCP chunk, which is inserted directly after it as the format requires.
+///
+/// # Arguments
+/// * `webp` - The encoded WebP image.
+/// * `width` - The width of the image in pixels.
+/// * `height` - The height of the image in pixels.
+/// * `icc` - The raw ICC profile to embed.
+///
+/// # Returns
+/// `Result<Vec<u8>, String>` - The WebP image carrying the profile, or an error string if the image is malformed.
+pub fn embed_webp_icc_profile(
+    webp: &[u8],
+    width: u32,
+    height: u32,
+    icc: &[u8],
+) -> Result<Vec<u8>, String> {
+    const ICC_FLAG: u8 = 0x20;
+    const ALPHA_FLAG: u8 = 0x10;
+
+    if webp.len() < 20 || &webp[0..4] != b"RIFF" || &webp[8..12] != b"WEBP" {
+        return Err("Invalid WebP image".to_string());
+    }
+    let chunks = &webp[12..];
+
+    let mut body = Vec::with_capacity(webp.len() + icc.len() + 32);
+    body.extend_from_slice(b"WEBP");
+    let remaining = if &chunks[0..4] == b"VP8X" {
+        let vp8x = chunks
+            .get(..18)
+            .ok_or_else(|| "Invalid WebP image".to_string())?;
+        body.extend_from_slice(&vp8x[..8]);
+        body.push(vp8x[8] | ICC_FLAG);
+        body.extend_from_slice(&vp8x[9..]);
+        &chunks[18..]
+    } else {
+        let alpha = if &chunks[0..4] == b"VP8L" {
+            ALPHA_FLAG
+        } else {
+            0
+        };
+        body.extend_from_slice(b"VP8X");
+        body.extend_from_slice(&10u32.to_le_bytes());
+        body.extend_from_slice(&[ICC_FLAG | alpha, 0, 0, 0]);
+        body.extend_from_slice(&(width.max(1) - 1).to_le_bytes()[..3]);
+        body.extend_from_slice(&(height.max(1) - 1).to_le_bytes()[..3]);
+        chunks
+    };
+
+    body.extend_from_slice(b"ICCP");
+    body.extend_from_slice(&(icc.len() as u32).to_le_bytes());
+    body.extend_from_slice(icc);
+    if icc.len() % 2 == 1 {
+        body.push(0);
+    }
+    body.extend_from_slice(remaining);
+
+    let mut result = Vec::with_capacity(body.len() + 8);
+    result.extend_from_slice(b"RIFF");
+    result.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    result.extend_from_slice(&body);
+    Ok(result)
+}
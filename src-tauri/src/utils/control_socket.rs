@@ -0,0 +1,444 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, RunEvent, State};
+
+use crate::models::config::Config;
+use crate::models::control::ControlEvent;
+use crate::utils::config_utils::read_config;
+
+#[cfg(unix)]
+use tauri::Emitter;
+
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+#[cfg(unix)]
+use std::sync::mpsc::{sync_channel, SyncSender};
+#[cfg(unix)]
+use std::sync::Arc;
+#[cfg(unix)]
+use std::time::Duration;
+
+#[cfg(unix)]
+use crate::commands::file_operations::{change_image, open_directory, read_image_from_path};
+#[cfg(unix)]
+use crate::commands::image_analyze::detect_ai_image;
+#[cfg(unix)]
+use crate::models::control::{ControlCommand, ControlReply, ControlResult, RotationDirection};
+#[cfg(unix)]
+use crate::models::image::ImageMetadata;
+#[cfg(unix)]
+use crate::utils::archive::read_image_bytes;
+#[cfg(unix)]
+use crate::utils::decode_limits::load_decode_limits;
+#[cfg(unix)]
+use crate::utils::directory_index::{get_current_image, get_indexed_directory_files};
+#[cfg(unix)]
+use crate::utils::image_processing::save_image_to_format;
+#[cfg(unix)]
+use crate::utils::single_instance::runtime_socket_path;
+
+/// The name of the socket scripts send their commands to.
+#[cfg(unix)]
+const CONTROL_SOCKET_NAME: &str = "blickfang-control.sock";
+
+/// How long writing a reply or event may block before the client is considered gone.
+#[cfg(unix)]
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How many events may wait for a subscriber before it is considered too slow and dropped.
+#[cfg(unix)]
+const EVENT_QUEUE_SIZE: usize = 64;
+
+/// A connection to the control socket, shared between its reader thread and its event writer thread.
+#[cfg(unix)]
+type Connection = Arc<Mutex<UnixStream>>;
+
+/// A connection subscribed to events, with the queue its event writer thread reads from.
+#[cfg(unix)]
+struct Subscriber {
+    connection: Connection,
+    events: SyncSender<String>,
+}
+
+/// State struct holding the control socket, the rotation of the displayed image and the
+/// connections subscribed to events.
+#[derive(Default)]
+pub struct ControlSocket {
+    socket_path: Mutex<Option<PathBuf>>,
+    rotation: Mutex<i32>,
+    #[cfg(unix)]
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+/// Reads whether the control socket is enabled, falling back to the default if the configuration cannot be read.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `bool` - Whether scripts can drive the application through the control socket.
+pub fn load_control_socket(app: &AppHandle) -> bool {
+    read_config(app)
+        .ok()
+        .and_then(|config_str| serde_json::from_str::<Config>(&config_str).ok())
+        .unwrap_or_default()
+        .control_socket
+}
+
+/// Starts listening on the control socket, if it is enabled.
+///
+/// Scripts connect to a per-user Unix socket and send one JSON command per line, such as
+/// `{"command": "next"}`, and get a JSON reply line `{"ok": true, "result": ...}` or
+/// `{"ok": false, "error": ...}` for each. After a `subscribe` command, the connection also
+/// receives event lines whenever the displayed image changes or is rotated.
+/// The control socket is only available on Unix platforms.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+pub fn setup_control_socket(app: &AppHandle) {
+    #[cfg(unix)]
+    {
+        if !load_control_socket(app) {
+            return;
+        }
+        match runtime_socket_path(app, CONTROL_SOCKET_NAME) {
+            Ok(socket_path) => listen_for_commands(app, socket_path),
+            Err(e) => eprintln!("Failed to set up the control socket: {}", e),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = app;
+    }
+}
+
+/// Removes the control socket when the application exits.
+///
+/// # Arguments
+/// * `app_handle` - The Tauri application handle.
+/// * `event` - The `RunEvent` to handle.
+pub fn handle_exit_event(app_handle: &AppHandle, event: &RunEvent) {
+    if let RunEvent::Exit = event {
+        let state = app_handle.state::<ControlSocket>();
+        if let Some(socket_path) = state.socket_path.lock().unwrap().take() {
+            let _ = std::fs::remove_file(socket_path);
+        }
+    }
+}
+
+/// Records the rotation of the displayed image, as shown by the frontend, so exports through
+/// the control socket match what is on screen.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `state` - The control socket state.
+/// * `rotation` - The rotation angle in degrees (0, 90, 180, 270).
+#[tauri::command]
+pub fn set_displayed_rotation(app: AppHandle, state: State<'_, ControlSocket>, rotation: i32) {
+    let changed = {
+        let mut current = state.rotation.lock().unwrap();
+        let changed = *current != rotation;
+        *current = rotation;
+        changed
+    };
+    if changed {
+        publish_control_event(&app, ControlEvent::RotationChanged { rotation });
+    }
+}
+
+/// Pushes an event to all connections subscribed to the control socket.
+///
+/// The event is only queued, each subscriber has its own thread writing the queue to its
+/// connection. Subscribers whose queue is full, or whose connection cannot be written to,
+/// are dropped.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `event` - The event to push.
+pub fn publish_control_event(app: &AppHandle, event: ControlEvent) {
+    #[cfg(unix)]
+    {
+        let state = app.state::<ControlSocket>();
+        let mut subscribers = state.subscribers.lock().unwrap();
+        if subscribers.is_empty() {
+            return;
+        }
+        let Ok(line) = serde_json::to_string(&event) else {
+            return;
+        };
+        subscribers.retain(|subscriber| subscriber.events.try_send(line.clone()).is_ok());
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (app, event);
+    }
+}
+
+/// Binds the control socket and serves every connection on its own background thread.
+#[cfg(unix)]
+fn listen_for_commands(app: &AppHandle, socket_path: PathBuf) {
+    // A socket nobody listens on was left behind by an instance that crashed.
+    if UnixStream::connect(&socket_path).is_err() {
+        let _ = std::fs::remove_file(&socket_path);
+    }
+    let listener = match UnixListener::bind(&socket_path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to listen on '{}': {}", socket_path.display(), e);
+            return;
+        }
+    };
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600));
+    }
+    *app.state::<ControlSocket>().socket_path.lock().unwrap() = Some(socket_path);
+
+    let app_handle = app.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let app_handle = app_handle.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = handle_control_connection(&app_handle, stream) {
+                            eprintln!("Control connection failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => eprintln!("Failed to accept control connection: {}", e),
+            }
+        }
+    });
+}
+
+/// Reads commands from a connection until it is closed, answering each with a reply line.
+#[cfg(unix)]
+fn handle_control_connection(app: &AppHandle, stream: UnixStream) -> Result<(), String> {
+    stream
+        .set_write_timeout(Some(WRITE_TIMEOUT))
+        .map_err(|e| format!("Failed to configure connection: {}", e))?;
+    let connection: Connection = Arc::new(Mutex::new(
+        stream
+            .try_clone()
+            .map_err(|e| format!("Failed to configure connection: {}", e))?,
+    ));
+
+    let result = serve_commands(app, stream, &connection);
+
+    let state = app.state::<ControlSocket>();
+    state
+        .subscribers
+        .lock()
+        .unwrap()
+        .retain(|subscriber| !Arc::ptr_eq(&subscriber.connection, &connection));
+    result
+}
+
+/// Answers the commands read from a connection, one reply line per command line.
+#[cfg(unix)]
+fn serve_commands(
+    app: &AppHandle,
+    stream: UnixStream,
+    connection: &Connection,
+) -> Result<(), String> {
+    for line in BufReader::new(stream).lines() {
+        let line = line.map_err(|e| format!("Failed to read command: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match serde_json::from_str::<ControlCommand>(&line) {
+            Ok(ControlCommand::Subscribe) => {
+                subscribe(app, connection);
+                Ok(ControlResult::Empty {})
+            }
+            Ok(command) => tauri::async_runtime::block_on(run_command(app, command)),
+            Err(e) => Err(format!("Invalid command: {}", e)),
+        };
+        let reply = match result {
+            Ok(result) => ControlReply {
+                ok: true,
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => ControlReply {
+                ok: false,
+                result: None,
+                error: Some(error),
+            },
+        };
+        let line = serde_json::to_string(&reply)
+            .map_err(|e| format!("Failed to serialize reply: {}", e))?;
+        write_line(connection, &line)?;
+    }
+    Ok(())
+}
+
+/// Subscribes a connection to events, starting the thread that writes them to it.
+/// Connections already subscribed stay subscribed once.
+#[cfg(unix)]
+fn subscribe(app: &AppHandle, connection: &Connection) {
+    let state = app.state::<ControlSocket>();
+    let mut subscribers = state.subscribers.lock().unwrap();
+    if subscribers
+        .iter()
+        .any(|subscriber| Arc::ptr_eq(&subscriber.connection, connection))
+    {
+        return;
+    }
+
+    let (events, queue) = sync_channel::<String>(EVENT_QUEUE_SIZE);
+    let writer_connection = connection.clone();
+    std::thread::spawn(move || {
+        // Ends once the subscriber is dropped, or stops reading.
+        for line in queue {
+            if write_line(&writer_connection, &line).is_err() {
+                break;
+            }
+        }
+    });
+    subscribers.push(Subscriber {
+        connection: connection.clone(),
+        events,
+    });
+}
+
+/// Writes a single line to a connection.
+#[cfg(unix)]
+fn write_line(connection: &Connection, line: &str) -> Result<(), String> {
+    let mut stream = connection.lock().unwrap();
+    writeln!(stream, "{}", line).map_err(|e| format!("Failed to write to connection: {}", e))
+}
+
+/// Runs a control command, mapping it onto the commands the frontend uses.
+#[cfg(unix)]
+async fn run_command(app: &AppHandle, command: ControlCommand) -> Result<ControlResult, String> {
+    match command {
+        ControlCommand::Open { path } => {
+            let (metadata, path, _) = if Path::new(&path).is_dir() {
+                open_directory(app.clone(), path, app.state(), app.state()).await?
+            } else {
+                read_image_from_path(app.clone(), path, app.state(), app.state()).await?
+            };
+            show_image(app, metadata, path)
+        }
+        ControlCommand::Next | ControlCommand::Previous => {
+            let direction = if matches!(command, ControlCommand::Next) {
+                "next"
+            } else {
+                "previous"
+            };
+            let current_path = displayed_image(app)?;
+            let (metadata, path) = change_image(
+                current_path,
+                direction.to_string(),
+                app.clone(),
+                app.state(),
+                app.state(),
+            )
+            .await?;
+            show_image(app, metadata, path)
+        }
+        ControlCommand::Rotate { direction } => {
+            displayed_image(app)?;
+            let state = app.state::<ControlSocket>();
+            let rotation = {
+                let mut rotation = state.rotation.lock().unwrap();
+                *rotation = match direction {
+                    RotationDirection::Clockwise => (*rotation + 90) % 360,
+                    RotationDirection::Counterclockwise => (*rotation + 270) % 360,
+                };
+                *rotation
+            };
+            if let Err(e) = app.emit("control-rotation-changed", rotation) {
+                eprintln!("Failed to emit 'control-rotation-changed' event: {}", e);
+            }
+            publish_control_event(app, ControlEvent::RotationChanged { rotation });
+            Ok(ControlResult::Rotation { rotation })
+        }
+        ControlCommand::Export {
+            path,
+            format,
+            mut options,
+        } => {
+            let source_path = displayed_image(app)?;
+            let format = format
+                .or_else(|| {
+                    Path::new(&path)
+                        .extension()
+                        .map(|extension| extension.to_string_lossy().to_lowercase())
+                })
+                .ok_or_else(|| "No format given and none implied by the path".to_string())?;
+            options.rotation = *app.state::<ControlSocket>().rotation.lock().unwrap();
+            let limits = load_decode_limits(app);
+
+            let path = tokio::task::spawn_blocking(move || {
+                let bytes = read_image_bytes(Path::new(&source_path), &limits)?;
+                save_image_to_format(
+                    &bytes,
+                    Path::new(&source_path),
+                    Path::new(&path),
+                    &format,
+                    &options,
+                    &limits,
+                )
+            })
+            .await
+            .map_err(|e| format!("Task spawn error: {}", e))??;
+            Ok(ControlResult::Exported { path })
+        }
+        ControlCommand::Current => {
+            let rotation = *app.state::<ControlSocket>().rotation.lock().unwrap();
+            let Some(path) = get_current_image(app) else {
+                return Ok(ControlResult::Current {
+                    path: None,
+                    rotation,
+                    position: None,
+                    total: 0,
+                });
+            };
+            let files = get_indexed_directory_files(app, &path).await?;
+            Ok(ControlResult::Current {
+                position: files.iter().position(|f| f == &path),
+                total: files.len(),
+                path: Some(path),
+                rotation,
+            })
+        }
+        ControlCommand::DetectAi { path } => {
+            let path = match path {
+                Some(path) => path,
+                None => displayed_image(app)?,
+            };
+            tokio::task::spawn_blocking(move || detect_ai_image(path))
+                .await
+                .map_err(|e| format!("Task spawn error: {}", e))?
+                .map(ControlResult::AiDetection)
+        }
+        ControlCommand::Subscribe => Ok(ControlResult::Empty {}),
+    }
+}
+
+/// Returns the path of the displayed image, or an error if no image is displayed.
+#[cfg(unix)]
+fn displayed_image(app: &AppHandle) -> Result<String, String> {
+    get_current_image(app).ok_or_else(|| "No image is displayed".to_string())
+}
+
+/// Shows an image opened through the control socket in the frontend, unrotated.
+#[cfg(unix)]
+fn show_image(
+    app: &AppHandle,
+    metadata: ImageMetadata,
+    path: String,
+) -> Result<ControlResult, String> {
+    *app.state::<ControlSocket>().rotation.lock().unwrap() = 0;
+    if let Err(e) = app.emit("control-image-opened", (metadata.clone(), path.clone())) {
+        eprintln!("Failed to emit 'control-image-opened' event: {}", e);
+    }
+    Ok(ControlResult::Image { path, metadata })
+}
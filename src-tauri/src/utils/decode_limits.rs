@@ -0,0 +1,146 @@
+use image::error::ImageError;
+use image::Limits;
+use std::fmt;
+use tauri::AppHandle;
+
+use crate::models::config::Config;
+use crate::utils::config_utils::read_config;
+
+/// The largest maximum image dimension that can be configured, in pixels.
+pub const MAX_IMAGE_DIMENSION_LIMIT: u32 = 1_000_000;
+/// The largest maximum decoding memory that can be configured, in megabytes.
+pub const MAX_DECODE_MEMORY_LIMIT_MB: u32 = 64 * 1024;
+
+/// The limits every image is decoded under, protecting against decompression bombs.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DecodeLimits {
+    /// The largest width or height accepted, in pixels.
+    pub max_dimension: u32,
+    /// The largest amount of memory a decoder may allocate, in bytes.
+    pub max_alloc: u64,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+impl DecodeLimits {
+    /// Builds the decoding limits stored in the application configuration.
+    ///
+    /// # Arguments
+    /// * `config` - The application configuration.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_dimension: config
+                .max_image_dimension
+                .clamp(1, MAX_IMAGE_DIMENSION_LIMIT),
+            max_alloc: config
+                .max_decode_memory_mb
+                .clamp(1, MAX_DECODE_MEMORY_LIMIT_MB) as u64
+                * 1024
+                * 1024,
+        }
+    }
+
+    /// Returns these limits in the form the `image` crate's decoders enforce.
+    pub fn to_image_limits(self) -> Limits {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(self.max_dimension);
+        limits.max_image_height = Some(self.max_dimension);
+        limits.max_alloc = Some(self.max_alloc);
+        limits
+    }
+
+    /// Checks whether an image of the given size may be decoded, before any pixel data is read.
+    ///
+    /// # Arguments
+    /// * `width` - The width of the image in pixels.
+    /// * `height` - The height of the image in pixels.
+    /// * `bytes_per_pixel` - The size of a decoded pixel in bytes.
+    ///
+    /// # Returns
+    /// `Result<(), DecodeError>` - `Ok(())` if the image is within the limits, `DecodeError::LimitsExceeded` otherwise.
+    pub fn check(&self, width: u32, height: u32, bytes_per_pixel: u64) -> Result<(), DecodeError> {
+        if width > self.max_dimension || height > self.max_dimension {
+            return Err(DecodeError::LimitsExceeded(format!(
+                "{}x{} pixels exceeds the maximum of {} pixels per side",
+                width, height, self.max_dimension
+            )));
+        }
+        let required = (width as u64)
+            .checked_mul(height as u64)
+            .and_then(|pixels| pixels.checked_mul(bytes_per_pixel))
+            .unwrap_or(u64::MAX);
+        if required > self.max_alloc {
+            return Err(DecodeError::LimitsExceeded(format!(
+                "decoding needs {} MB, but at most {} MB may be used",
+                required.div_ceil(1024 * 1024),
+                self.max_alloc / (1024 * 1024)
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Reads the configured decoding limits, falling back to the defaults if the configuration cannot be read.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `DecodeLimits` - The configured decoding limits.
+pub fn load_decode_limits(app: &AppHandle) -> DecodeLimits {
+    read_config(app)
+        .ok()
+        .and_then(|config_str| serde_json::from_str::<Config>(&config_str).ok())
+        .map(|config| DecodeLimits::from_config(&config))
+        .unwrap_or_default()
+}
+
+/// An error raised while decoding an image.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The image was rejected because decoding it would exceed the configured limits.
+    LimitsExceeded(String),
+    /// The image could not be read or decoded.
+    Failed(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::LimitsExceeded(reason) => {
+                write!(f, "Image exceeds the decoding limits: {}", reason)
+            }
+            DecodeError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for DecodeError {
+    fn from(message: String) -> Self {
+        DecodeError::Failed(message)
+    }
+}
+
+impl From<DecodeError> for String {
+    fn from(error: DecodeError) -> Self {
+        error.to_string()
+    }
+}
+
+impl DecodeError {
+    /// Converts an error of the `image` crate, keeping limit violations distinct.
+    ///
+    /// # Arguments
+    /// * `error` - The error returned by the `image` crate.
+    /// * `context` - A description of what failed, used for errors other than limit violations.
+    pub fn from_image_error(error: ImageError, context: &str) -> Self {
+        match error {
+            ImageError::Limits(e) => DecodeError::LimitsExceeded(e.to_string()),
+            e => DecodeError::Failed(format!("{}: {}", context, e)),
+        }
+    }
+}
@@ -3,7 +3,10 @@ use tauri::Window;
 use tauri_plugin_dialog::DialogExt;
 use tokio::sync::oneshot;
 
-/// Opens a file dialog for the user to select an image file.
+use crate::utils::archive::ARCHIVE_EXTENSIONS;
+use crate::utils::image_formats::dialog_extensions;
+
+/// Opens a file dialog for the user to select an image file or an archive of images.
 ///
 /// # Arguments
 /// * `window` - The Tauri window handle.
@@ -13,11 +16,19 @@ use tokio::sync::oneshot;
 /// or `None` if the dialog is cancelled by the user.
 pub async fn open_image_dialog(window: Window) -> Result<Option<PathBuf>, String> {
     let (tx, rx) = oneshot::channel();
+    let mut extensions = dialog_extensions();
+    extensions.extend(
+        ARCHIVE_EXTENSIONS
+            .iter()
+            .flat_map(|ext| [ext.to_string(), ext.to_uppercase()]),
+    );
+    let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
 
     window
         .dialog()
         .file()
-        .add_filter("Image files", &["png", "jpg", "jpeg", "webp", "bmp", "gif"])
+        .add_filter("Image files", &extensions)
+        .add_filter("All files", &["*"])
         .pick_file(move |result| {
             let path_to_send = result.and_then(|fp| match fp {
                 tauri_plugin_dialog::FilePath::Path(p) => Some(p),
@@ -75,3 +86,26 @@ pub async fn show_save_dialog(
     rx.await
         .map_err(|e| format!("Failed to receive save path from dialog: {}", e))
 }
+
+/// Opens a dialog for the user to select a directory.
+///
+/// # Arguments
+/// * `window` - The Tauri window handle.
+///
+/// # Returns
+/// `Result<Option<PathBuf>, String>` - An `Option` containing the path to the selected directory,
+/// or `None` if the dialog is cancelled by the user.
+pub async fn pick_directory_dialog(window: Window) -> Result<Option<PathBuf>, String> {
+    let (tx, rx) = oneshot::channel();
+
+    window.dialog().file().pick_folder(move |result| {
+        let path_to_send = result.and_then(|fp| match fp {
+            tauri_plugin_dialog::FilePath::Path(p) => Some(p),
+            _ => None,
+        });
+        let _ = tx.send(path_to_send);
+    });
+
+    rx.await
+        .map_err(|e| format!("Failed to receive directory path from dialog: {}", e))
+}
@@ -0,0 +1,413 @@
+use notify::{recommended_watcher, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::models::control::ControlEvent;
+use crate::models::directory::DirectoryChange;
+use crate::utils::archive::{archive_kind, list_archive_images, split_archive_path};
+use crate::utils::color_profile::load_convert_to_srgb;
+use crate::utils::control_socket::publish_control_event;
+use crate::utils::decode_limits::load_decode_limits;
+use crate::utils::file_sorting::{load_sort_order, SortOrder};
+use crate::utils::file_system::{get_filtered_files_in_directory, get_parent_directory};
+use crate::utils::folder_browsing::{
+    get_filtered_files_in_tree, load_folder_browsing, FolderBrowsing,
+};
+use crate::utils::image_cache::ImageCache;
+use crate::utils::image_processing::{get_modified_time, read_image_file};
+use crate::utils::image_protocol::ImageRegistry;
+use crate::utils::tone_mapping::load_tone_map_settings;
+
+/// How long to wait after a filesystem event before rescanning, so bursts of events
+/// (e.g. an editor writing a file in several steps) only cause a single refresh.
+const REFRESH_DELAY: Duration = Duration::from_millis(300);
+
+/// The mutable part of the directory index, guarded by a single lock.
+#[derive(Default)]
+struct IndexState {
+    directory: Option<PathBuf>,
+    recursive: bool,
+    files: Option<Vec<String>>,
+    playlist: Option<Vec<String>>,
+    watcher: Option<RecommendedWatcher>,
+    watching: bool,
+    current_image: Option<String>,
+    current_image_modified: Option<SystemTime>,
+    refresh_pending: bool,
+}
+
+/// State struct holding the image listing of the directory currently being viewed.
+///
+/// The listing is kept current by a filesystem watcher. Changes to the directory are pushed
+/// to the frontend as `directory-changed` events, and a rewrite of the displayed image as a
+/// `current-image-changed` event. A directory that cannot be watched is not kept in the index,
+/// so it is read again on every lookup.
+#[derive(Default)]
+pub struct DirectoryIndex {
+    state: Mutex<IndexState>,
+}
+
+/// Returns the non-hidden image files in the directory containing the given path.
+///
+/// The listing is served from the index if the directory is already being watched.
+/// Otherwise the directory is read, indexed and watched in place of the previous one.
+/// With recursive browsing enabled, the listing includes the images in subfolders, and
+/// an image in a subfolder of the indexed directory is served from its listing.
+/// For an archive entry, the archive is indexed as its directory.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `path` - A path to a file within the target directory.
+///
+/// # Returns
+/// `Result<Vec<String>, String>` - A list of filtered image file paths.
+pub async fn get_indexed_directory_files(
+    app: &AppHandle,
+    path: &str,
+) -> Result<Vec<String>, String> {
+    let directory = match split_archive_path(Path::new(path)) {
+        Some((archive, _)) => archive,
+        None => get_parent_directory(path)?.to_path_buf(),
+    };
+    get_indexed_files(app, directory, Some(path)).await
+}
+
+/// Returns the non-hidden image files in the given directory, indexing and watching it
+/// in place of the previous one if it is not indexed yet.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `directory` - The target directory.
+///
+/// # Returns
+/// `Result<Vec<String>, String>` - A list of filtered image file paths.
+pub async fn get_indexed_files_in_directory(
+    app: &AppHandle,
+    directory: &Path,
+) -> Result<Vec<String>, String> {
+    get_indexed_files(app, directory.to_path_buf(), None).await
+}
+
+/// Returns the directory currently indexed, which is the root of the listing in recursive mode.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `Option<PathBuf>` - The indexed directory, or `None` if no directory has been opened yet.
+pub fn get_indexed_directory(app: &AppHandle) -> Option<PathBuf> {
+    let index = app.state::<DirectoryIndex>();
+    let state = index.state.lock().unwrap();
+    state.directory.clone()
+}
+
+/// Serves the listing of the given directory from the index, or reads and indexes it.
+/// A listing containing `path` is served as well, so navigation stays within a recursive listing.
+/// An active playlist is served while `path` is part of it, and ended otherwise.
+async fn get_indexed_files(
+    app: &AppHandle,
+    directory: PathBuf,
+    path: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let browsing = load_folder_browsing(app);
+    let index = app.state::<DirectoryIndex>();
+
+    {
+        let mut state = index.state.lock().unwrap();
+        if let Some(playlist) = &state.playlist {
+            if path.is_some_and(|p| playlist.iter().any(|f| f == p)) {
+                return Ok(playlist.clone());
+            }
+            state.playlist = None;
+        }
+        if state.watching && state.recursive == browsing.recursive {
+            if let Some(files) = &state.files {
+                let same_directory = state.directory.as_deref() == Some(directory.as_path());
+                let contains_path =
+                    browsing.recursive && path.is_some_and(|p| files.iter().any(|f| f == p));
+                if same_directory || contains_path {
+                    return Ok(files.clone());
+                }
+            }
+        }
+    }
+
+    let files = list_index_files(&directory, browsing, load_sort_order(app)).await?;
+
+    let mut state = index.state.lock().unwrap();
+    if state.directory.as_deref() != Some(directory.as_path())
+        || state.recursive != browsing.recursive
+    {
+        let watching = watch_directory(app, &mut state, &directory, browsing.recursive);
+        state.watching = watching;
+        state.directory = Some(directory);
+        state.recursive = browsing.recursive;
+    }
+    if state.watching {
+        state.files = Some(files.clone());
+    }
+    Ok(files)
+}
+
+/// Lists the images of a directory, including its subfolders if browsing recursively,
+/// without indexing it. Archives list their image entries in natural order.
+///
+/// # Arguments
+/// * `directory` - The directory or archive to list.
+/// * `browsing` - The folder browsing settings, deciding whether subfolders are included.
+/// * `order` - The order to sort the files of each folder in.
+///
+/// # Returns
+/// `Result<Vec<String>, String>` - A list of image file paths.
+pub async fn list_index_files(
+    directory: &Path,
+    browsing: FolderBrowsing,
+    order: SortOrder,
+) -> Result<Vec<String>, String> {
+    if archive_kind(directory).is_some() {
+        let archive = directory.to_path_buf();
+        tokio::task::spawn_blocking(move || list_archive_images(&archive))
+            .await
+            .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+    } else if browsing.recursive {
+        get_filtered_files_in_tree(directory, order, browsing.max_depth).await
+    } else {
+        get_filtered_files_in_directory(directory, order).await
+    }
+}
+
+/// Replaces the directory listing with an explicit list of images, such as the files
+/// the application was launched with. Navigation stays within the playlist until an image
+/// outside of it, or a directory, is opened.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `files` - The images of the playlist, in the order they are shown.
+pub fn set_playlist(app: &AppHandle, files: Vec<String>) {
+    let index = app.state::<DirectoryIndex>();
+    index.state.lock().unwrap().playlist = Some(files);
+}
+
+/// Returns whether navigation currently follows a playlist rather than a directory listing.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `bool` - Whether a playlist is active.
+pub fn is_playlist_active(app: &AppHandle) -> bool {
+    let index = app.state::<DirectoryIndex>();
+    let state = index.state.lock().unwrap();
+    state.playlist.is_some()
+}
+
+/// Drops the indexed listing, so the next lookup reads the directory again.
+/// The directory stays watched.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+pub fn invalidate_directory_index(app: &AppHandle) {
+    let index = app.state::<DirectoryIndex>();
+    index.state.lock().unwrap().files = None;
+}
+
+/// Records the image currently displayed, so it can be reloaded when its file is rewritten.
+/// Subscribers of the control socket are told when another image is displayed.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `path` - The path of the displayed image.
+pub fn set_current_image(app: &AppHandle, path: &str) {
+    let changed = {
+        let index = app.state::<DirectoryIndex>();
+        let mut state = index.state.lock().unwrap();
+        let changed = state.current_image.as_deref() != Some(path);
+        state.current_image = Some(path.to_string());
+        state.current_image_modified = get_modified_time(Path::new(path)).ok();
+        changed
+    };
+    if changed {
+        publish_control_event(
+            app,
+            ControlEvent::ImageChanged {
+                path: path.to_string(),
+            },
+        );
+    }
+}
+
+/// Returns the path of the image currently displayed.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `Option<String>` - The path of the displayed image, or `None` if no image has been opened yet.
+pub fn get_current_image(app: &AppHandle) -> Option<String> {
+    let index = app.state::<DirectoryIndex>();
+    let state = index.state.lock().unwrap();
+    state.current_image.clone()
+}
+
+/// Moves the filesystem watch to the given directory, creating the watcher on first use.
+/// Recursive listings watch the whole tree below the directory.
+///
+/// # Returns
+/// `bool` - Whether the directory is being watched.
+fn watch_directory(
+    app: &AppHandle,
+    state: &mut IndexState,
+    directory: &Path,
+    recursive: bool,
+) -> bool {
+    if state.watcher.is_none() {
+        let app_handle = app.clone();
+        match recommended_watcher(move |result: notify::Result<Event>| match result {
+            Ok(event) => handle_watch_event(&app_handle, event),
+            Err(e) => eprintln!("Directory watcher error: {}", e),
+        }) {
+            Ok(watcher) => state.watcher = Some(watcher),
+            Err(e) => {
+                eprintln!("Failed to create directory watcher: {}", e);
+                return false;
+            }
+        }
+    }
+
+    let Some(watcher) = state.watcher.as_mut() else {
+        return false;
+    };
+    if let Some(previous) = &state.directory {
+        if state.watching {
+            let _ = watcher.unwatch(previous);
+        }
+    }
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    match watcher.watch(directory, mode) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Failed to watch directory '{}': {}", directory.display(), e);
+            false
+        }
+    }
+}
+
+/// Handles a filesystem event by scheduling a refresh of the index.
+fn handle_watch_event(app: &AppHandle, event: Event) {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return;
+    }
+
+    let index = app.state::<DirectoryIndex>();
+    let mut state = index.state.lock().unwrap();
+    if state.refresh_pending {
+        return;
+    }
+    state.refresh_pending = true;
+
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(REFRESH_DELAY).await;
+        refresh_index(&app_handle).await;
+    });
+}
+
+/// Rescans the watched directory and notifies the frontend about changes.
+///
+/// Emits `directory-changed` if the image listing differs from the indexed one, and
+/// `current-image-changed` with the reloaded metadata if the displayed image was rewritten.
+async fn refresh_index(app: &AppHandle) {
+    let index = app.state::<DirectoryIndex>();
+    let (directory, recursive, current_image, current_image_modified) = {
+        let mut state = index.state.lock().unwrap();
+        state.refresh_pending = false;
+        (
+            state.directory.clone(),
+            state.recursive,
+            state.current_image.clone(),
+            state.current_image_modified,
+        )
+    };
+
+    let Some(directory) = directory else {
+        return;
+    };
+
+    let browsing = FolderBrowsing {
+        recursive,
+        ..load_folder_browsing(app)
+    };
+    match list_index_files(&directory, browsing, load_sort_order(app)).await {
+        Ok(files) => {
+            let changed = {
+                let mut state = index.state.lock().unwrap();
+                let changed = state.directory.as_deref() == Some(directory.as_path())
+                    && state.recursive == recursive
+                    && state.files.as_ref() != Some(&files);
+                if changed {
+                    state.files = Some(files.clone());
+                }
+                changed
+            };
+
+            if changed {
+                let payload = DirectoryChange {
+                    directory: directory.to_string_lossy().to_string(),
+                    files,
+                };
+                if let Err(e) = app.emit("directory-changed", payload) {
+                    eprintln!("Failed to emit 'directory-changed' event: {}", e);
+                }
+            }
+        }
+        Err(e) => eprintln!("Failed to refresh directory index: {}", e),
+    }
+
+    if let Some(current_image) = current_image {
+        reload_if_modified(app, &current_image, current_image_modified).await;
+    }
+}
+
+/// Reloads the displayed image and emits `current-image-changed` if its file was modified.
+async fn reload_if_modified(app: &AppHandle, path: &str, known_modified: Option<SystemTime>) {
+    let Ok(modified) = get_modified_time(Path::new(path)) else {
+        return;
+    };
+    if Some(modified) == known_modified {
+        return;
+    }
+
+    let registry = app.state::<ImageRegistry>();
+    let cache = app.state::<ImageCache>();
+    match read_image_file(
+        path,
+        &registry,
+        &cache,
+        load_tone_map_settings(app),
+        load_convert_to_srgb(app),
+        load_decode_limits(app),
+    )
+    .await
+    {
+        Ok(metadata) => {
+            {
+                let index = app.state::<DirectoryIndex>();
+                let mut state = index.state.lock().unwrap();
+                if state.current_image.as_deref() != Some(path) {
+                    return;
+                }
+                state.current_image_modified = Some(modified);
+            }
+            if let Err(e) = app.emit("current-image-changed", (metadata, path)) {
+                eprintln!("Failed to emit 'current-image-changed' event: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Failed to reload image '{}': {}", path, e),
+    }
+}
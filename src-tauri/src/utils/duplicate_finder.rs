@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::{DefaultHasher, Hasher};
+use std::io::{BufReader, ErrorKind, Read};
+use std::path::Path;
+
+use crate::models::duplicates::{DuplicateFile, DuplicateGroup};
+use crate::utils::decode_limits::DecodeLimits;
+use crate::utils::image_formats::decode_image;
+use crate::utils::perceptual_hash::{hamming_distance, perceptual_hash, HashAlgorithm};
+
+/// A scanned image file with the hashes it is compared by.
+struct ScannedFile {
+    file: DuplicateFile,
+    content_hash: u64,
+    perceptual_hash: u64,
+}
+
+/// Groups the given image files into exact duplicates and near-duplicates.
+///
+/// Files with identical size and content hash are compared byte for byte, and identical files
+/// form an exact group. Files whose perceptual
+/// hashes differ in at most `threshold` bits are linked, and each set of linked files forms a
+/// similar group, unless all of its files are exact copies already. Files that cannot be read
+/// or decoded are skipped.
+///
+/// # Arguments
+/// * `paths` - The paths of the image files to compare.
+/// * `algorithm` - The perceptual hash algorithm to use.
+/// * `threshold` - The largest Hamming distance at which two images count as near-duplicates.
+/// * `limits` - The limits the images are decoded under.
+/// * `on_progress` - Called after every file with the number of processed files and the file's path.
+///
+/// # Returns
+/// `Vec<DuplicateGroup>` - The groups of duplicates, exact groups first.
+pub fn find_duplicates(
+    paths: &[String],
+    algorithm: HashAlgorithm,
+    threshold: u32,
+    limits: &DecodeLimits,
+    mut on_progress: impl FnMut(usize, &str),
+) -> Vec<DuplicateGroup> {
+    let mut scanned = Vec::with_capacity(paths.len());
+    for (index, path) in paths.iter().enumerate() {
+        match scan_file(path, algorithm, limits) {
+            Ok(file) => scanned.push(file),
+            Err(e) => eprintln!("Skipping '{}' in duplicate scan: {}", path, e),
+        }
+        on_progress(index + 1, path);
+    }
+
+    let mut exact_groups: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (index, file) in scanned.iter().enumerate() {
+        exact_groups
+            .entry((file.file.file_size, file.content_hash))
+            .or_default()
+            .push(index);
+    }
+    let exact_groups: Vec<Vec<usize>> = exact_groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .flat_map(|members| split_identical(&scanned, members))
+        .filter(|members| members.len() > 1)
+        .collect();
+    let mut exact_group_of = vec![None; scanned.len()];
+    for (group, members) in exact_groups.iter().enumerate() {
+        for &index in members {
+            exact_group_of[index] = Some(group);
+        }
+    }
+
+    let mut parents: Vec<usize> = (0..scanned.len()).collect();
+    for a in 0..scanned.len() {
+        for b in a + 1..scanned.len() {
+            if hamming_distance(scanned[a].perceptual_hash, scanned[b].perceptual_hash) <= threshold
+            {
+                let (root_a, root_b) = (find_root(&mut parents, a), find_root(&mut parents, b));
+                parents[root_b] = root_a;
+            }
+        }
+    }
+    let mut similar_groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for index in 0..scanned.len() {
+        let root = find_root(&mut parents, index);
+        similar_groups.entry(root).or_default().push(index);
+    }
+    let is_exact_group = |members: &Vec<usize>| {
+        let first = exact_group_of[members[0]];
+        first.is_some() && members.iter().all(|&i| exact_group_of[i] == first)
+    };
+    let similar_groups: Vec<Vec<usize>> = similar_groups
+        .into_values()
+        .filter(|members| members.len() > 1 && !is_exact_group(members))
+        .collect();
+
+    let to_group = |kind: &str, members: Vec<usize>| {
+        let mut files: Vec<DuplicateFile> = members
+            .into_iter()
+            .map(|i| scanned[i].file.clone())
+            .collect();
+        files.sort_by(|a, b| {
+            (b.width as u64 * b.height as u64, b.file_size, &a.path).cmp(&(
+                a.width as u64 * a.height as u64,
+                a.file_size,
+                &b.path,
+            ))
+        });
+        DuplicateGroup {
+            kind: kind.to_string(),
+            files,
+        }
+    };
+    let mut groups: Vec<DuplicateGroup> = exact_groups
+        .into_iter()
+        .map(|members| to_group("exact", members))
+        .chain(
+            similar_groups
+                .into_iter()
+                .map(|members| to_group("similar", members)),
+        )
+        .collect();
+    groups.sort_by(|a, b| {
+        (a.kind != "exact", &a.files[0].path).cmp(&(b.kind != "exact", &b.files[0].path))
+    });
+    groups
+}
+
+/// Reads and decodes an image file and computes its hashes.
+fn scan_file(
+    path: &str,
+    algorithm: HashAlgorithm,
+    limits: &DecodeLimits,
+) -> Result<ScannedFile, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&bytes);
+    let img = decode_image(&bytes, Path::new(path), limits)?;
+
+    Ok(ScannedFile {
+        file: DuplicateFile {
+            path: path.to_string(),
+            file_size: bytes.len() as u64,
+            width: img.width(),
+            height: img.height(),
+        },
+        content_hash: hasher.finish(),
+        perceptual_hash: perceptual_hash(&img, algorithm),
+    })
+}
+
+/// Splits files with equal size and content hash into sets of files with identical bytes.
+///
+/// Hash collisions are rare, so this usually returns the given files as a single set. Files
+/// that cannot be read for the comparison are left out.
+fn split_identical(scanned: &[ScannedFile], members: Vec<usize>) -> Vec<Vec<usize>> {
+    let mut sets: Vec<Vec<usize>> = Vec::new();
+    for index in members {
+        let path = &scanned[index].file.path;
+        let mut placed = false;
+        for set in sets.iter_mut() {
+            match files_identical(&scanned[set[0]].file.path, path) {
+                Ok(true) => {
+                    set.push(index);
+                    placed = true;
+                    break;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    eprintln!("Skipping '{}' in duplicate comparison: {}", path, e);
+                    placed = true;
+                    break;
+                }
+            }
+        }
+        if !placed {
+            sets.push(vec![index]);
+        }
+    }
+    sets
+}
+
+/// Compares two files byte for byte.
+///
+/// # Arguments
+/// * `a` - The path to the first file.
+/// * `b` - The path to the second file.
+///
+/// # Returns
+/// `Result<bool, String>` - Whether the files have identical contents, or an error string if
+/// either file cannot be read.
+fn files_identical(a: &str, b: &str) -> Result<bool, String> {
+    let open = |path: &str| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| format!("Failed to open file: {}", e))
+    };
+    let (mut a, mut b) = (open(a)?, open(b)?);
+    let (mut buffer_a, mut buffer_b) = ([0u8; 8192], [0u8; 8192]);
+    loop {
+        let read = a
+            .read(&mut buffer_a)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if read == 0 {
+            let mut rest = [0u8; 1];
+            return b
+                .read(&mut rest)
+                .map(|n| n == 0)
+                .map_err(|e| format!("Failed to read file: {}", e));
+        }
+        match b.read_exact(&mut buffer_b[..read]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(format!("Failed to read file: {}", e)),
+        }
+        if buffer_a[..read] != buffer_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Returns the representative of the set the given element belongs to, compressing the path to it.
+fn find_root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
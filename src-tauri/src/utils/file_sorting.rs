@@ -0,0 +1,233 @@
+use exif::{In, Reader, Tag, Value};
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use std::time::SystemTime;
+use tauri::AppHandle;
+
+use crate::models::config::Config;
+use crate::utils::config_utils::read_config;
+use crate::utils::image_formats::read_image_dimensions;
+
+/// The property image files are sorted by.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SortKey {
+    Natural,
+    Modified,
+    Size,
+    ExifDate,
+    Dimensions,
+}
+
+impl SortKey {
+    /// Returns the sort key with the given name, as stored in the configuration.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the key ("natural", "modified", "size", "exif-date" or "dimensions").
+    ///
+    /// # Returns
+    /// `Option<SortKey>` - The sort key, or `None` if the name is unknown.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "natural" => Some(SortKey::Natural),
+            "modified" => Some(SortKey::Modified),
+            "size" => Some(SortKey::Size),
+            "exif-date" => Some(SortKey::ExifDate),
+            "dimensions" => Some(SortKey::Dimensions),
+            _ => None,
+        }
+    }
+}
+
+/// The order in which the image files of a directory are listed and navigated.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Default for SortOrder {
+    fn default() -> Self {
+        Self {
+            key: SortKey::Natural,
+            descending: false,
+        }
+    }
+}
+
+impl SortOrder {
+    /// Builds the sort order stored in the application configuration.
+    /// Unknown sort keys fall back to natural order.
+    ///
+    /// # Arguments
+    /// * `config` - The application configuration.
+    pub fn from_config(config: &Config) -> Self {
+        let key = SortKey::from_name(&config.sort_order).unwrap_or(SortKey::Natural);
+        Self {
+            key,
+            descending: config.sort_descending,
+        }
+    }
+}
+
+/// Reads the configured sort order, falling back to the default if the configuration cannot be read.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `SortOrder` - The configured sort order.
+pub fn load_sort_order(app: &AppHandle) -> SortOrder {
+    read_config(app)
+        .ok()
+        .and_then(|config_str| serde_json::from_str::<Config>(&config_str).ok())
+        .map(|config| SortOrder::from_config(&config))
+        .unwrap_or_default()
+}
+
+/// Sorts a list of image file paths in the given order.
+///
+/// Sorting by modification time, size, EXIF date or dimensions reads each file's metadata,
+/// so this should be called off the async runtime. Files with equal keys, as well as files
+/// lacking an EXIF date or readable dimensions, are ordered naturally after the others.
+///
+/// # Arguments
+/// * `files` - The image file paths to sort.
+/// * `order` - The sort order to apply.
+///
+/// # Returns
+/// `Vec<String>` - The sorted image file paths.
+pub fn sort_image_files(files: Vec<String>, order: SortOrder) -> Vec<String> {
+    let mut keyed: Vec<(Option<SortValue>, String)> = files
+        .into_iter()
+        .map(|path| (read_sort_value(Path::new(&path), order.key), path))
+        .collect();
+
+    keyed.sort_by(|(value_a, path_a), (value_b, path_b)| {
+        let by_value = match (value_a, value_b) {
+            (Some(a), Some(b)) => apply_direction(a.cmp(b), order.descending),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        by_value.then_with(|| {
+            let natural = natural_cmp(path_a, path_b);
+            if order.key == SortKey::Natural {
+                apply_direction(natural, order.descending)
+            } else {
+                natural
+            }
+        })
+    });
+
+    keyed.into_iter().map(|(_, path)| path).collect()
+}
+
+/// A comparable value read from a file for sorting.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Time(SystemTime),
+    Number(u64),
+    Text(String),
+}
+
+/// Reads the value a file is sorted by, or `None` if it has no such value.
+fn read_sort_value(path: &Path, key: SortKey) -> Option<SortValue> {
+    match key {
+        SortKey::Natural => None,
+        SortKey::Modified => fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(SortValue::Time),
+        SortKey::Size => fs::metadata(path).ok().map(|m| SortValue::Number(m.len())),
+        SortKey::ExifDate => read_exif_date_time_original(path).map(SortValue::Text),
+        SortKey::Dimensions => read_image_dimensions(path)
+            .ok()
+            .map(|(width, height)| SortValue::Number(width as u64 * height as u64)),
+    }
+}
+
+/// Reads the EXIF `DateTimeOriginal` tag of a file.
+///
+/// The value has the form "YYYY:MM:DD HH:MM:SS", which sorts chronologically as text.
+fn read_exif_date_time_original(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+    let exif = Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let field = exif.get_field(Tag::DateTimeOriginal, In::PRIMARY)?;
+    match &field.value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|v| String::from_utf8_lossy(v).trim().to_string()),
+        _ => None,
+    }
+}
+
+/// Reverses an ordering if sorting in descending direction.
+fn apply_direction(ordering: Ordering, descending: bool) -> Ordering {
+    if descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+/// Compares two strings in natural (human) order, so that "img2" sorts before "img10".
+///
+/// Runs of digits are compared by their numeric value and all other characters
+/// case-insensitively. Strings that compare equal this way are ordered by their raw bytes.
+///
+/// # Arguments
+/// * `a` - The first string.
+/// * `b` - The second string.
+///
+/// # Returns
+/// `Ordering` - The natural ordering of the two strings.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut chars_a = a.chars().peekable();
+    let mut chars_b = b.chars().peekable();
+
+    loop {
+        match (chars_a.peek().copied(), chars_b.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let digits_a = take_digits(&mut chars_a);
+                let digits_b = take_digits(&mut chars_b);
+                let ordering = compare_digit_runs(&digits_a, &digits_b);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(ca), Some(cb)) => {
+                let ordering = ca.to_lowercase().cmp(cb.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                chars_a.next();
+                chars_b.next();
+            }
+        }
+    }
+}
+
+/// Consumes a run of ASCII digits from the iterator.
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+/// Compares two runs of digits by their numeric value without parsing them,
+/// so arbitrarily long runs are handled.
+fn compare_digit_runs(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
@@ -1,45 +1,67 @@
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
+use crate::utils::file_sorting::{sort_image_files, SortOrder};
+use crate::utils::image_formats::sniff_file_format;
+
 /// Reads the parent directory of the given file path and returns a sorted list of
 /// paths to image files found within that directory.
 ///
 /// # Arguments
 /// * `file_path` - A path to a file within the target directory.
+/// * `order` - The order to sort the files in.
 ///
 /// # Returns
 /// `Result<Vec<String>, String>` - A sorted vector of absolute paths to image files.
-pub async fn get_directory_files(file_path: &str) -> Result<Vec<String>, String> {
-    let path = Path::new(file_path);
-    let parent_dir = path
-        .parent()
-        .ok_or_else(|| "Could not determine parent directory".to_string())?;
-
-    let image_extensions = ["png", "jpg", "jpeg", "webp", "bmp", "gif"];
-    let mut image_files = Vec::new();
-
-    let mut dir = fs::read_dir(parent_dir)
-        .await
-        .map_err(|e| format!("Failed to read directory: {}", e))?;
+pub async fn get_directory_files(file_path: &str, order: SortOrder) -> Result<Vec<String>, String> {
+    list_directory_images(get_parent_directory(file_path)?, order).await
+}
 
-    while let Some(entry) = dir
-        .next_entry()
-        .await
-        .map_err(|e| format!("Failed to read directory entry: {}", e))?
-    {
-        let path = entry.path();
+/// Returns the directory containing the given file path.
+///
+/// # Arguments
+/// * `file_path` - A path to a file.
+///
+/// # Returns
+/// `Result<&Path, String>` - The parent directory of the file.
+pub fn get_parent_directory(file_path: &str) -> Result<&Path, String> {
+    Path::new(file_path)
+        .parent()
+        .ok_or_else(|| "Could not determine parent directory".to_string())
+}
 
-        if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-            if image_extensions.contains(&ext.to_lowercase().as_str()) {
-                if let Some(path_str) = path.to_str() {
-                    image_files.push(path_str.to_string());
-                }
+/// Reads the given directory and returns a sorted list of paths to image files found within it.
+///
+/// Files are identified by their content rather than their extension, so images with
+/// missing or wrong extensions are found and non-images with image extensions are skipped.
+///
+/// # Arguments
+/// * `directory` - The directory to read.
+/// * `order` - The order to sort the files in.
+///
+/// # Returns
+/// `Result<Vec<String>, String>` - A sorted vector of absolute paths to image files.
+async fn list_directory_images(directory: &Path, order: SortOrder) -> Result<Vec<String>, String> {
+    let directory = directory.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut image_files = Vec::new();
+        for entry in
+            std::fs::read_dir(&directory).map_err(|e| format!("Failed to read directory: {}", e))?
+        {
+            let path = entry
+                .map_err(|e| format!("Failed to read directory entry: {}", e))?
+                .path();
+            if path.is_dir() || sniff_file_format(&path).is_none() {
+                continue;
+            }
+            if let Some(path_str) = path.to_str() {
+                image_files.push(path_str.to_string());
             }
         }
-    }
-
-    image_files.sort();
-    Ok(image_files)
+        Ok::<_, String>(sort_image_files(image_files, order))
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
 }
 
 /// Filters a list of file paths, removing any files whose names start with a dot ('.').
@@ -69,10 +91,69 @@ pub fn filter_dot_files(paths: Vec<String>) -> Vec<String> {
 ///
 /// # Arguments
 /// * `path` - A path within the target directory.
+/// * `order` - The order to sort the files in.
 ///
 /// # Returns
 /// `Result<Vec<String>, String>` - A list of filtered image file paths.
-pub async fn get_filtered_directory_files(path: &str) -> Result<Vec<String>, String> {
-    let files = get_directory_files(path).await?;
+pub async fn get_filtered_directory_files(
+    path: &str,
+    order: SortOrder,
+) -> Result<Vec<String>, String> {
+    let files = get_directory_files(path, order).await?;
     Ok(filter_dot_files(files))
 }
+
+/// Retrieves a sorted list of non-hidden image files from the given directory.
+///
+/// # Arguments
+/// * `directory` - The directory to read.
+/// * `order` - The order to sort the files in.
+///
+/// # Returns
+/// `Result<Vec<String>, String>` - A list of filtered image file paths.
+pub async fn get_filtered_files_in_directory(
+    directory: &Path,
+    order: SortOrder,
+) -> Result<Vec<String>, String> {
+    let files = list_directory_images(directory, order).await?;
+    Ok(filter_dot_files(files))
+}
+
+/// Lists the non-hidden subdirectories of the given directory.
+/// Unless symbolic links are followed, directory loops cannot occur when walking the tree.
+///
+/// # Arguments
+/// * `directory` - The directory to read.
+/// * `follow_symlinks` - Whether symbolic links to directories are listed as subdirectories.
+///
+/// # Returns
+/// `Result<Vec<PathBuf>, String>` - The paths of the subdirectories, sorted by name.
+pub async fn list_subdirectories(
+    directory: &Path,
+    follow_symlinks: bool,
+) -> Result<Vec<PathBuf>, String> {
+    let mut subdirectories = Vec::new();
+
+    let mut dir = fs::read_dir(directory)
+        .await
+        .map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    while let Some(entry) = dir
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read directory entry: {}", e))?
+    {
+        let is_dir = if follow_symlinks {
+            fs::metadata(entry.path()).await.is_ok_and(|m| m.is_dir())
+        } else {
+            entry.file_type().await.is_ok_and(|t| t.is_dir())
+        };
+        let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if is_dir && !is_hidden {
+            subdirectories.push(entry.path());
+        }
+    }
+
+    subdirectories.sort();
+    Ok(subdirectories)
+}
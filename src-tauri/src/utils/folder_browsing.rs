@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tokio::fs;
+
+use crate::models::config::Config;
+use crate::utils::config_utils::read_config;
+use crate::utils::file_sorting::SortOrder;
+use crate::utils::file_system::{get_filtered_files_in_directory, list_subdirectories};
+
+/// How the folders of an image collection are browsed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FolderBrowsing {
+    /// Whether images in subfolders are listed along with the folder's own.
+    pub recursive: bool,
+    /// How many levels of subfolders are included in a recursive listing.
+    pub max_depth: u32,
+    /// Whether navigating past either end of the listing moves on to the neighbouring folder.
+    pub continue_into_next_folder: bool,
+}
+
+impl Default for FolderBrowsing {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+impl FolderBrowsing {
+    /// Builds the folder browsing settings stored in the application configuration.
+    ///
+    /// # Arguments
+    /// * `config` - The application configuration.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            recursive: config.recursive_browsing,
+            max_depth: config.recursive_max_depth,
+            continue_into_next_folder: config.continue_into_next_folder,
+        }
+    }
+}
+
+/// Reads the configured folder browsing settings, falling back to the defaults if the configuration cannot be read.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `FolderBrowsing` - The configured folder browsing settings.
+pub fn load_folder_browsing(app: &AppHandle) -> FolderBrowsing {
+    read_config(app)
+        .ok()
+        .and_then(|config_str| serde_json::from_str::<Config>(&config_str).ok())
+        .map(|config| FolderBrowsing::from_config(&config))
+        .unwrap_or_default()
+}
+
+/// Lists the non-hidden image files of a directory and its subdirectories.
+///
+/// The tree is walked depth-first with subfolders in name order, each folder's images sorted in
+/// the given order and listed before those of its subfolders. Symbolic links to directories are
+/// followed, but every directory is visited only once, so links pointing back up the tree cannot
+/// cause an endless walk. Subfolders that cannot be read are skipped.
+///
+/// # Arguments
+/// * `directory` - The root of the tree.
+/// * `order` - The order to sort the files of each folder in.
+/// * `max_depth` - How many levels of subfolders to descend into, 0 listing only the directory itself.
+///
+/// # Returns
+/// `Result<Vec<String>, String>` - A list of filtered image file paths, or an error string if the root cannot be read.
+pub async fn get_filtered_files_in_tree(
+    directory: &Path,
+    order: SortOrder,
+    max_depth: u32,
+) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![(directory.to_path_buf(), 0)];
+
+    while let Some((current, depth)) = pending.pop() {
+        let Ok(canonical) = fs::canonicalize(&current).await else {
+            continue;
+        };
+        if !visited.insert(canonical) {
+            continue;
+        }
+
+        match get_filtered_files_in_directory(&current, order).await {
+            Ok(images) => files.extend(images),
+            Err(e) if depth == 0 => return Err(e),
+            Err(e) => {
+                eprintln!("Skipping folder '{}': {}", current.display(), e);
+                continue;
+            }
+        }
+
+        if depth < max_depth {
+            let subdirectories = match list_subdirectories(&current, true).await {
+                Ok(subdirectories) => subdirectories,
+                Err(e) => {
+                    eprintln!("Skipping subfolders of '{}': {}", current.display(), e);
+                    Vec::new()
+                }
+            };
+            pending.extend(subdirectories.into_iter().rev().map(|d| (d, depth + 1)));
+        }
+    }
+
+    Ok(files)
+}
+
+/// Finds the image to continue with when navigating past either end of a folder.
+///
+/// The sibling folders of the given folder are searched in name order, skipping those
+/// without images. Moving forward continues with the first image of the next folder,
+/// moving backward with the last image of the previous one.
+///
+/// # Arguments
+/// * `directory` - The folder whose end was reached.
+/// * `forward` - Whether navigation moves forward.
+/// * `browsing` - The folder browsing settings, deciding whether subfolders are included.
+/// * `order` - The order the files of each folder are sorted in.
+///
+/// # Returns
+/// `Result<Option<(PathBuf, String)>, String>` - The folder and the path of the image to continue with,
+/// or `None` if no neighbouring folder has images or the parent folder cannot be read.
+pub async fn find_adjacent_folder_image(
+    directory: &Path,
+    forward: bool,
+    browsing: FolderBrowsing,
+    order: SortOrder,
+) -> Result<Option<(PathBuf, String)>, String> {
+    let Some(parent) = directory.parent() else {
+        return Ok(None);
+    };
+    // An unreadable parent folder only means there is nothing to continue with.
+    let siblings = match list_subdirectories(parent, true).await {
+        Ok(siblings) => siblings,
+        Err(e) => {
+            eprintln!("Failed to list folders in '{}': {}", parent.display(), e);
+            return Ok(None);
+        }
+    };
+    let Some(position) = siblings.iter().position(|d| d == directory) else {
+        return Ok(None);
+    };
+    let candidates: Vec<_> = if forward {
+        siblings[position + 1..].iter().collect()
+    } else {
+        siblings[..position].iter().rev().collect()
+    };
+
+    for sibling in candidates {
+        let files = if browsing.recursive {
+            get_filtered_files_in_tree(sibling, order, browsing.max_depth).await
+        } else {
+            get_filtered_files_in_directory(sibling, order).await
+        }
+        .unwrap_or_default();
+        let image = if forward { files.first() } else { files.last() };
+        if let Some(image) = image {
+            return Ok(Some((sibling.clone(), image.clone())));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,115 @@
+use image::{DynamicImage, ImageBuffer, Rgb, Rgba};
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+use std::io::Read;
+
+use crate::utils::decode_limits::{DecodeError, DecodeLimits};
+
+/// Decodes the primary image of a HEIF or AVIF file.
+///
+/// libheif applies the rotation and mirroring stored in the file while decoding, so the
+/// returned image is already upright. Images with more than 8 bits per channel are decoded
+/// to 16-bit samples.
+///
+/// # Arguments
+/// * `bytes` - The contents of the HEIF or AVIF file.
+/// * `limits` - The limits the decoder has to stay within, checked before any pixel data is decoded.
+///
+/// # Returns
+/// `Result<DynamicImage, DecodeError>` - The decoded image, or an error if it cannot be decoded
+/// or exceeds the limits.
+pub fn decode_heif(bytes: &[u8], limits: &DecodeLimits) -> Result<DynamicImage, DecodeError> {
+    let lib_heif = LibHeif::new();
+    let context = HeifContext::read_from_bytes(bytes)
+        .map_err(|e| format!("Failed to read HEIF file: {}", e))?;
+    let handle = context
+        .primary_image_handle()
+        .map_err(|e| format!("Failed to get primary HEIF image: {}", e))?;
+
+    let has_alpha = handle.has_alpha_channel();
+    let high_bit_depth = handle.luma_bits_per_pixel() > 8;
+    let channels = if has_alpha { 4 } else { 3 };
+    let bytes_per_sample = if high_bit_depth { 2 } else { 1 };
+    // libheif and the copy below each hold the decoded pixels.
+    limits.check(
+        handle.width(),
+        handle.height(),
+        2 * (channels * bytes_per_sample) as u64,
+    )?;
+    let chroma = match (high_bit_depth, has_alpha) {
+        (false, false) => RgbChroma::Rgb,
+        (false, true) => RgbChroma::Rgba,
+        (true, false) => RgbChroma::HdrRgbLe,
+        (true, true) => RgbChroma::HdrRgbaLe,
+    };
+    let image = lib_heif
+        .decode(&handle, ColorSpace::Rgb(chroma), None)
+        .map_err(|e| format!("Failed to decode HEIF image: {}", e))?;
+
+    let planes = image.planes();
+    let plane = planes
+        .interleaved
+        .ok_or_else(|| "Decoded HEIF image has no interleaved plane".to_string())?;
+    let (width, height) = (plane.width, plane.height);
+    let row_len = width as usize * channels * bytes_per_sample;
+
+    // Rows may be padded, so copy them out without the padding.
+    let mut data = Vec::with_capacity(row_len * height as usize);
+    for row in plane.data.chunks(plane.stride).take(height as usize) {
+        data.extend_from_slice(&row[..row_len]);
+    }
+
+    let invalid_size =
+        || DecodeError::Failed("Decoded HEIF image has an unexpected size".to_string());
+    if !high_bit_depth {
+        let image = if has_alpha {
+            ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, data).map(DynamicImage::ImageRgba8)
+        } else {
+            ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, data).map(DynamicImage::ImageRgb8)
+        };
+        return image.ok_or_else(invalid_size);
+    }
+
+    let samples = scale_to_16_bit(&data, plane.bits_per_pixel);
+    let image = if has_alpha {
+        ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, samples).map(DynamicImage::ImageRgba16)
+    } else {
+        ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, samples).map(DynamicImage::ImageRgb16)
+    };
+    image.ok_or_else(invalid_size)
+}
+
+/// Reads the dimensions and color depth of the primary image of a HEIF or AVIF file
+/// without decoding its pixel data.
+///
+/// # Arguments
+/// * `reader` - A reader over the HEIF or AVIF file.
+///
+/// # Returns
+/// `Result<((u32, u32), Option<u8>), String>` - The dimensions and bits per channel of the image.
+pub fn read_heif_details<R: Read>(mut reader: R) -> Result<((u32, u32), Option<u8>), String> {
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read HEIF file: {}", e))?;
+    let context = HeifContext::read_from_bytes(&bytes)
+        .map_err(|e| format!("Failed to read HEIF file: {}", e))?;
+    let handle = context
+        .primary_image_handle()
+        .map_err(|e| format!("Failed to get primary HEIF image: {}", e))?;
+    Ok((
+        (handle.width(), handle.height()),
+        Some(handle.luma_bits_per_pixel()),
+    ))
+}
+
+/// Converts little-endian samples of the given bit depth to the full 16-bit range.
+fn scale_to_16_bit(data: &[u8], bits_per_sample: u8) -> Vec<u16> {
+    let bits = bits_per_sample.clamp(1, 16) as u32;
+    let max = (1u32 << bits) - 1;
+    data.chunks_exact(2)
+        .map(|c| {
+            let value = u16::from_le_bytes([c[0], c[1]]) as u32;
+            (value.min(max) * u16::MAX as u32 / max) as u16
+        })
+        .collect()
+}
@@ -0,0 +1,406 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+use crate::models::config::Config;
+use crate::models::image::ImageMetadata;
+use crate::utils::color_profile::load_convert_to_srgb;
+use crate::utils::config_utils::read_config;
+use crate::utils::decode_limits::load_decode_limits;
+use crate::utils::image_processing::{get_modified_time, needs_rendition, read_image_file};
+use crate::utils::image_protocol::ImageRegistry;
+use crate::utils::tone_mapping::load_tone_map_settings;
+
+/// A prepared image held by the image cache.
+struct CacheEntry {
+    metadata: ImageMetadata,
+    bytes: Arc<Vec<u8>>,
+    modified: SystemTime,
+}
+
+/// The number of renditions the image protocol keeps for images not held by the cache itself.
+const MAX_RENDITIONS: usize = 4;
+
+/// The number of files whose rendition decision is remembered before the decisions are dropped.
+const MAX_RENDITION_DECISIONS: usize = 4096;
+
+/// Whether a file is served as a rendition, decided for its modification time and the
+/// `convert_to_srgb` setting.
+struct RenditionDecision {
+    modified: SystemTime,
+    convert_to_srgb: bool,
+    needs_rendition: bool,
+}
+
+/// A rendition rendered by the image protocol, for the settings it was rendered with.
+struct Rendition {
+    path: PathBuf,
+    settings: String,
+    bytes: Arc<Vec<u8>>,
+    modified: SystemTime,
+}
+
+/// The mutable part of the image cache, guarded by a single lock.
+#[derive(Default)]
+struct CacheContents {
+    entries: HashMap<PathBuf, CacheEntry>,
+    /// Paths ordered from least to most recently used.
+    recency: VecDeque<PathBuf>,
+    used_bytes: u64,
+}
+
+impl CacheContents {
+    /// Marks the given path as the most recently used entry.
+    fn touch(&mut self, path: &Path) {
+        if let Some(pos) = self.recency.iter().position(|p| p == path) {
+            if let Some(p) = self.recency.remove(pos) {
+                self.recency.push_back(p);
+            }
+        }
+    }
+
+    /// Removes the entry for the given path, if present.
+    fn remove(&mut self, path: &Path) {
+        if let Some(entry) = self.entries.remove(path) {
+            self.used_bytes -= entry.bytes.len() as u64;
+            self.recency.retain(|p| p != path);
+        }
+    }
+
+    /// Evicts least recently used entries until the used memory fits into the budget.
+    fn evict_to(&mut self, budget_bytes: u64) {
+        while self.used_bytes > budget_bytes {
+            match self.recency.pop_front() {
+                Some(path) => {
+                    if let Some(entry) = self.entries.remove(&path) {
+                        self.used_bytes -= entry.bytes.len() as u64;
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+/// State struct holding recently viewed and prefetched images.
+///
+/// Entries are evicted in least-recently-used order once the memory budget is exceeded,
+/// and are dropped when the modification time of their file changes.
+pub struct ImageCache {
+    contents: Mutex<CacheContents>,
+    /// Renditions served by the image protocol, most recently used last.
+    renditions: Mutex<VecDeque<Rendition>>,
+    /// Whether files are served as renditions, so their headers are not read on every request.
+    rendition_decisions: Mutex<HashMap<PathBuf, RenditionDecision>>,
+    budget_bytes: AtomicU64,
+    prefetch_count: AtomicUsize,
+    /// Incremented on every navigation so outdated prefetch tasks can stop early.
+    prefetch_generation: AtomicU64,
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        let config = Config::default();
+        Self {
+            contents: Mutex::default(),
+            renditions: Mutex::default(),
+            rendition_decisions: Mutex::default(),
+            budget_bytes: AtomicU64::new(megabytes_to_bytes(config.image_cache_budget_mb)),
+            prefetch_count: AtomicUsize::new(config.prefetch_count as usize),
+            prefetch_generation: AtomicU64::new(0),
+        }
+    }
+}
+
+impl ImageCache {
+    /// Applies new cache settings, evicting entries if the budget shrank.
+    ///
+    /// # Arguments
+    /// * `budget_mb` - The memory budget of the cache in megabytes.
+    /// * `prefetch_count` - The number of images to prefetch in each direction.
+    pub fn configure(&self, budget_mb: u32, prefetch_count: u32) {
+        let budget_bytes = megabytes_to_bytes(budget_mb);
+        self.budget_bytes.store(budget_bytes, Ordering::Relaxed);
+        self.prefetch_count
+            .store(prefetch_count as usize, Ordering::Relaxed);
+        self.contents.lock().unwrap().evict_to(budget_bytes);
+        self.renditions
+            .lock()
+            .unwrap()
+            .retain(|rendition| rendition.bytes.len() as u64 <= budget_bytes);
+    }
+
+    /// Drops all cached images, for example after a setting that affects their renditions changed.
+    pub fn clear(&self) {
+        *self.contents.lock().unwrap() = CacheContents::default();
+        self.renditions.lock().unwrap().clear();
+        self.rendition_decisions.lock().unwrap().clear();
+    }
+
+    /// Returns the largest file size in bytes that may be cached.
+    pub fn max_entry_size(&self) -> u64 {
+        self.budget_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Returns the cached metadata for the given path if the file has not changed since it was cached.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the image file.
+    ///
+    /// # Returns
+    /// `Option<ImageMetadata>` - The cached metadata, or `None` if there is no valid entry.
+    pub fn get_metadata(&self, path: &Path) -> Option<ImageMetadata> {
+        self.get_valid_entry(path, |entry| entry.metadata.clone())
+    }
+
+    /// Returns the cached file contents for the given path if the file has not changed since it was cached.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the image file.
+    ///
+    /// # Returns
+    /// `Option<Arc<Vec<u8>>>` - The cached file contents, or `None` if there is no valid entry.
+    pub fn get_bytes(&self, path: &Path) -> Option<Arc<Vec<u8>>> {
+        self.get_valid_entry(path, |entry| entry.bytes.clone())
+    }
+
+    /// Returns `true` if there is a valid entry for the given path.
+    fn contains(&self, path: &Path) -> bool {
+        self.get_valid_entry(path, |_| ()).is_some()
+    }
+
+    /// Looks up a valid entry, dropping it if its file has been modified since it was cached.
+    fn get_valid_entry<T>(&self, path: &Path, read: impl FnOnce(&CacheEntry) -> T) -> Option<T> {
+        let modified = get_modified_time(path).ok();
+        let mut contents = self.contents.lock().unwrap();
+
+        let is_current = contents
+            .entries
+            .get(path)
+            .map(|entry| Some(entry.modified) == modified)?;
+        if !is_current {
+            contents.remove(path);
+            return None;
+        }
+
+        contents.touch(path);
+        contents.entries.get(path).map(read)
+    }
+
+    /// Inserts a prepared image into the cache, evicting older entries if needed.
+    /// Images larger than the whole budget are not cached.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the image file.
+    /// * `metadata` - The image metadata.
+    /// * `bytes` - The file contents.
+    /// * `modified` - The modification time of the file when it was read.
+    pub fn insert(
+        &self,
+        path: &Path,
+        metadata: ImageMetadata,
+        bytes: Vec<u8>,
+        modified: SystemTime,
+    ) {
+        let budget_bytes = self.budget_bytes.load(Ordering::Relaxed);
+        let size = bytes.len() as u64;
+        if size > budget_bytes {
+            return;
+        }
+
+        let mut contents = self.contents.lock().unwrap();
+        contents.remove(path);
+        contents.evict_to(budget_bytes - size);
+        contents.entries.insert(
+            path.to_path_buf(),
+            CacheEntry {
+                metadata,
+                bytes: Arc::new(bytes),
+                modified,
+            },
+        );
+        contents.recency.push_back(path.to_path_buf());
+        contents.used_bytes += size;
+    }
+
+    /// Returns `true` if the image file at the given path has to be served as a rendition,
+    /// see `image_processing::needs_rendition`. The decision is remembered until the file changes.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the image file.
+    /// * `convert_to_srgb` - Whether images with an embedded color profile are converted to sRGB.
+    ///
+    /// # Returns
+    /// `bool` - Whether the image has to be shown through a rendition.
+    pub fn needs_rendition(&self, path: &Path, convert_to_srgb: bool) -> bool {
+        let Ok(modified) = get_modified_time(path) else {
+            return needs_rendition(path, convert_to_srgb);
+        };
+        if let Some(decision) = self.rendition_decisions.lock().unwrap().get(path) {
+            if decision.modified == modified && decision.convert_to_srgb == convert_to_srgb {
+                return decision.needs_rendition;
+            }
+        }
+
+        let decision = needs_rendition(path, convert_to_srgb);
+        let mut decisions = self.rendition_decisions.lock().unwrap();
+        if decisions.len() >= MAX_RENDITION_DECISIONS {
+            decisions.clear();
+        }
+        decisions.insert(
+            path.to_path_buf(),
+            RenditionDecision {
+                modified,
+                convert_to_srgb,
+                needs_rendition: decision,
+            },
+        );
+        decision
+    }
+
+    /// Returns the rendition of the given image rendered with the given settings, if it was
+    /// rendered since the file last changed.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the image file.
+    /// * `settings` - A string identifying the settings the rendition is rendered with.
+    ///
+    /// # Returns
+    /// `Option<Arc<Vec<u8>>>` - The encoded rendition, or `None` if there is no valid one.
+    pub fn get_rendition(&self, path: &Path, settings: &str) -> Option<Arc<Vec<u8>>> {
+        let modified = get_modified_time(path).ok()?;
+        let mut renditions = self.renditions.lock().unwrap();
+        let position = renditions
+            .iter()
+            .position(|rendition| rendition.path == path && rendition.settings == settings)?;
+        let rendition = renditions.remove(position)?;
+        if rendition.modified != modified {
+            return None;
+        }
+        let bytes = rendition.bytes.clone();
+        renditions.push_back(rendition);
+        Some(bytes)
+    }
+
+    /// Keeps a rendition rendered by the image protocol, so further requests for the same image,
+    /// such as range requests, are served without rendering it again.
+    /// Renditions larger than the whole budget are not kept.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the image file.
+    /// * `settings` - A string identifying the settings the rendition was rendered with.
+    /// * `bytes` - The encoded rendition.
+    /// * `modified` - The modification time of the file when it was read.
+    ///
+    /// # Returns
+    /// `Arc<Vec<u8>>` - The rendition, to be served.
+    pub fn insert_rendition(
+        &self,
+        path: &Path,
+        settings: String,
+        bytes: Vec<u8>,
+        modified: SystemTime,
+    ) -> Arc<Vec<u8>> {
+        let bytes = Arc::new(bytes);
+        if bytes.len() as u64 > self.budget_bytes.load(Ordering::Relaxed) {
+            return bytes;
+        }
+        let mut renditions = self.renditions.lock().unwrap();
+        renditions.retain(|rendition| rendition.path != path || rendition.settings != settings);
+        if renditions.len() >= MAX_RENDITIONS {
+            renditions.pop_front();
+        }
+        renditions.push_back(Rendition {
+            path: path.to_path_buf(),
+            settings,
+            bytes: bytes.clone(),
+            modified,
+        });
+        bytes
+    }
+}
+
+/// Converts a size in megabytes to bytes.
+fn megabytes_to_bytes(megabytes: u32) -> u64 {
+    megabytes as u64 * 1024 * 1024
+}
+
+/// Applies the image cache settings from the application configuration.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `Result<(), String>` - Ok if the settings were applied, an error string otherwise.
+pub fn apply_image_cache_config(app: &AppHandle) -> Result<(), String> {
+    let config_str = read_config(app)?;
+    let config: Config = serde_json::from_str(&config_str)
+        .map_err(|e| format!("Failed to deserialize config: {}", e))?;
+    app.state::<ImageCache>()
+        .configure(config.image_cache_budget_mb, config.prefetch_count);
+    Ok(())
+}
+
+/// Prefetches the images surrounding the given index in the background.
+///
+/// Images are loaded alternately after and before the current one, closest first.
+/// A newer call supersedes any prefetch that is still running.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `files` - The list of image files in the current directory.
+/// * `current_index` - The index of the currently displayed image.
+pub fn prefetch_neighbours(app: &AppHandle, files: Vec<String>, current_index: usize) {
+    let cache = app.state::<ImageCache>();
+    let generation = cache.prefetch_generation.fetch_add(1, Ordering::Relaxed) + 1;
+    let count = cache.prefetch_count.load(Ordering::Relaxed);
+    if count == 0 || files.len() <= 1 {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let cache = app.state::<ImageCache>();
+        let registry = app.state::<ImageRegistry>();
+        let tone_map = load_tone_map_settings(&app);
+        let convert_to_srgb = load_convert_to_srgb(&app);
+        let limits = load_decode_limits(&app);
+
+        for path in neighbour_paths(&files, current_index, count) {
+            if cache.prefetch_generation.load(Ordering::Relaxed) != generation {
+                return;
+            }
+            if cache.contains(Path::new(&path)) {
+                continue;
+            }
+            if let Err(e) =
+                read_image_file(&path, &registry, &cache, tone_map, convert_to_srgb, limits).await
+            {
+                eprintln!("Failed to prefetch image '{}': {}", path, e);
+            }
+        }
+    });
+}
+
+/// Returns the paths surrounding the given index, alternating between the next and the
+/// previous image and wrapping around at the ends of the list.
+fn neighbour_paths(files: &[String], current_index: usize, count: usize) -> Vec<String> {
+    let len = files.len();
+    let mut paths: Vec<String> = Vec::new();
+
+    for offset in 1..=count.min(len / 2 + 1) {
+        let next = &files[(current_index + offset) % len];
+        let previous = &files[(current_index + len - offset % len) % len];
+        for path in [next, previous] {
+            if path != &files[current_index] && !paths.contains(path) {
+                paths.push(path.clone());
+            }
+        }
+    }
+    paths
+}
@@ -0,0 +1,156 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, Rgb, RgbImage};
+
+/// The metrics and difference heatmap of two aligned images.
+pub struct Comparison {
+    pub psnr: Option<f64>,
+    pub ssim: f64,
+    pub max_error: u8,
+    pub heatmap: RgbImage,
+}
+
+/// Brings two images to the same size. If their sizes differ, the larger one is scaled
+/// to the size of the smaller one.
+///
+/// # Arguments
+/// * `a` - The first image.
+/// * `b` - The second image.
+///
+/// # Returns
+/// `(RgbImage, RgbImage, bool)` - Both images in 8-bit RGB, and whether one of them was scaled.
+pub fn align_images(a: &DynamicImage, b: &DynamicImage) -> (RgbImage, RgbImage, bool) {
+    if a.width() == b.width() && a.height() == b.height() {
+        return (a.to_rgb8(), b.to_rgb8(), false);
+    }
+    let area = |img: &DynamicImage| img.width() as u64 * img.height() as u64;
+    if area(a) <= area(b) {
+        let scaled = b.resize_exact(a.width(), a.height(), FilterType::Lanczos3);
+        (a.to_rgb8(), scaled.to_rgb8(), true)
+    } else {
+        let scaled = a.resize_exact(b.width(), b.height(), FilterType::Lanczos3);
+        (scaled.to_rgb8(), b.to_rgb8(), true)
+    }
+}
+
+/// Compares two images of the same size.
+///
+/// PSNR and the maximum error are computed over all RGB channels. SSIM is computed on the
+/// luma of both images over 8x8 windows, moved in steps of 4 pixels. The heatmap shows the
+/// largest channel difference of every pixel, scaled so the largest difference is brightest.
+///
+/// # Arguments
+/// * `a` - The first image.
+/// * `b` - The second image, of the same size as the first.
+///
+/// # Returns
+/// `Comparison` - The metrics and the difference heatmap.
+pub fn compare_images(a: &RgbImage, b: &RgbImage) -> Comparison {
+    let mut squared_error_sum = 0u64;
+    let mut max_error = 0u8;
+    let differences: Vec<u8> = a
+        .pixels()
+        .zip(b.pixels())
+        .map(|(pa, pb)| {
+            let mut largest = 0;
+            for (ca, cb) in pa.0.into_iter().zip(pb.0) {
+                let difference = ca.abs_diff(cb);
+                squared_error_sum += difference as u64 * difference as u64;
+                largest = largest.max(difference);
+            }
+            max_error = max_error.max(largest);
+            largest
+        })
+        .collect();
+
+    let sample_count = (a.width() as u64 * a.height() as u64 * 3).max(1);
+    let mse = squared_error_sum as f64 / sample_count as f64;
+    let psnr = (mse > 0.0).then(|| 10.0 * (255.0 * 255.0 / mse).log10());
+
+    let heatmap = RgbImage::from_fn(a.width(), a.height(), |x, y| {
+        let difference = differences[(y * a.width() + x) as usize];
+        heat_color(if max_error == 0 {
+            0.0
+        } else {
+            difference as f32 / max_error as f32
+        })
+    });
+
+    Comparison {
+        psnr,
+        ssim: mean_ssim(&to_luma(a), &to_luma(b)),
+        max_error,
+        heatmap,
+    }
+}
+
+/// Converts an RGB image to its Rec. 709 luma.
+fn to_luma(img: &RgbImage) -> GrayImage {
+    DynamicImage::ImageRgb8(img.clone()).to_luma8()
+}
+
+/// Computes the mean structural similarity of two grayscale images of the same size.
+fn mean_ssim(a: &GrayImage, b: &GrayImage) -> f64 {
+    const WINDOW: u32 = 8;
+    const STEP: u32 = 4;
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let window_width = WINDOW.min(a.width());
+    let window_height = WINDOW.min(a.height());
+    if window_width == 0 || window_height == 0 {
+        return 1.0;
+    }
+
+    let mut total = 0.0;
+    let mut windows = 0u64;
+    let mut y = 0;
+    while y + window_height <= a.height() {
+        let mut x = 0;
+        while x + window_width <= a.width() {
+            let samples = (y..y + window_height).flat_map(|wy| {
+                (x..x + window_width).map(move |wx| {
+                    (
+                        a.get_pixel(wx, wy).0[0] as f64,
+                        b.get_pixel(wx, wy).0[0] as f64,
+                    )
+                })
+            });
+            let n = (window_width * window_height) as f64;
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+                (0.0, 0.0, 0.0, 0.0, 0.0);
+            for (va, vb) in samples {
+                sum_a += va;
+                sum_b += vb;
+                sum_aa += va * va;
+                sum_bb += vb * vb;
+                sum_ab += va * vb;
+            }
+            let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+            let variance_a = sum_aa / n - mean_a * mean_a;
+            let variance_b = sum_bb / n - mean_b * mean_b;
+            let covariance = sum_ab / n - mean_a * mean_b;
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2));
+            windows += 1;
+            x += STEP;
+        }
+        y += STEP;
+    }
+    total / windows.max(1) as f64
+}
+
+/// Maps a value from 0 to 1 to a color running from black through purple and orange to pale yellow.
+fn heat_color(value: f32) -> Rgb<u8> {
+    const STOPS: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [120.0, 28.0, 109.0],
+        [237.0, 105.0, 37.0],
+        [252.0, 255.0, 164.0],
+    ];
+    let position = value.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (position as usize).min(STOPS.len() - 2);
+    let t = position - index as f32;
+    let [from, to] = [STOPS[index], STOPS[index + 1]];
+    Rgb([0, 1, 2].map(|c| (from[c] + (to[c] - from[c]) * t).round() as u8))
+}
@@ -0,0 +1,618 @@
+use image::{DynamicImage, ImageFormat, ImageReader};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek};
+use std::path::Path;
+
+use crate::utils::archive::{read_image_bytes, split_archive_path};
+use crate::utils::decode_limits::{DecodeError, DecodeLimits};
+#[cfg(feature = "heif")]
+use crate::utils::heif_decoder;
+use crate::utils::raw_preview::{find_raw_preview, read_raw_preview, RAF_MAGIC};
+use crate::utils::svg_renderer::{read_svg_info, render_svg};
+
+/// The number of leading bytes read from a file to identify its format.
+pub const FORMAT_HEADER_LEN: u64 = 64;
+
+/// The decoder an image format is read with.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FormatDecoder {
+    /// Decoded by the `image` crate, which also identifies the format by its magic bytes.
+    Image(ImageFormat),
+    /// Decoded by libheif. Only available with the `heif` feature, which is enabled by default.
+    Heif,
+    /// A camera RAW file, shown through its largest embedded JPEG preview.
+    RawPreview,
+    /// A vector image, rasterized by resvg at the requested scale.
+    Svg,
+}
+
+impl FormatDecoder {
+    /// Returns `true` if this decoder was compiled in.
+    pub fn is_available(&self) -> bool {
+        match self {
+            FormatDecoder::Image(_) => true,
+            FormatDecoder::Heif => cfg!(feature = "heif"),
+            FormatDecoder::RawPreview => true,
+            FormatDecoder::Svg => true,
+        }
+    }
+}
+
+/// Describes an image format the viewer can open.
+pub struct ImageFormatInfo {
+    /// The display name of the format, e.g. "PNG".
+    pub name: &'static str,
+    /// The file extensions commonly used for the format, in lowercase. The first one is preferred.
+    pub extensions: &'static [&'static str],
+    pub mime_type: &'static str,
+    /// The decoder used for the format.
+    pub decoder: FormatDecoder,
+    /// Whether webviews can display the format directly. Other formats are shown through a rendition.
+    pub webview_native: bool,
+    /// Whether images can be exported to this format.
+    pub exportable: bool,
+}
+
+/// All image formats known to the viewer. Adding a format here makes it discoverable in
+/// directories, selectable in the open dialog and, if exportable, available for saving.
+pub const IMAGE_FORMATS: &[ImageFormatInfo] = &[
+    ImageFormatInfo {
+        name: "PNG",
+        extensions: &["png", "apng"],
+        mime_type: "image/png",
+        decoder: FormatDecoder::Image(ImageFormat::Png),
+        webview_native: true,
+        exportable: true,
+    },
+    ImageFormatInfo {
+        name: "JPEG",
+        extensions: &["jpeg", "jpg", "jpe", "jfif"],
+        mime_type: "image/jpeg",
+        decoder: FormatDecoder::Image(ImageFormat::Jpeg),
+        webview_native: true,
+        exportable: true,
+    },
+    ImageFormatInfo {
+        name: "WEBP",
+        extensions: &["webp"],
+        mime_type: "image/webp",
+        decoder: FormatDecoder::Image(ImageFormat::WebP),
+        webview_native: true,
+        exportable: true,
+    },
+    ImageFormatInfo {
+        name: "BMP",
+        extensions: &["bmp", "dib"],
+        mime_type: "image/bmp",
+        decoder: FormatDecoder::Image(ImageFormat::Bmp),
+        webview_native: true,
+        exportable: true,
+    },
+    ImageFormatInfo {
+        name: "GIF",
+        extensions: &["gif"],
+        mime_type: "image/gif",
+        decoder: FormatDecoder::Image(ImageFormat::Gif),
+        webview_native: true,
+        exportable: true,
+    },
+    ImageFormatInfo {
+        name: "TIFF",
+        extensions: &["tiff", "tif"],
+        mime_type: "image/tiff",
+        decoder: FormatDecoder::Image(ImageFormat::Tiff),
+        webview_native: false,
+        exportable: true,
+    },
+    ImageFormatInfo {
+        name: "TGA",
+        extensions: &["tga", "icb", "vda", "vst"],
+        mime_type: "image/x-targa",
+        decoder: FormatDecoder::Image(ImageFormat::Tga),
+        webview_native: false,
+        exportable: true,
+    },
+    ImageFormatInfo {
+        name: "QOI",
+        extensions: &["qoi"],
+        mime_type: "image/x-qoi",
+        decoder: FormatDecoder::Image(ImageFormat::Qoi),
+        webview_native: false,
+        exportable: true,
+    },
+    ImageFormatInfo {
+        name: "PNM",
+        extensions: &["pnm", "pbm", "pgm", "ppm", "pam"],
+        mime_type: "image/x-portable-anymap",
+        decoder: FormatDecoder::Image(ImageFormat::Pnm),
+        webview_native: false,
+        exportable: true,
+    },
+    ImageFormatInfo {
+        name: "ICO",
+        extensions: &["ico", "cur"],
+        mime_type: "image/x-icon",
+        decoder: FormatDecoder::Image(ImageFormat::Ico),
+        webview_native: false,
+        exportable: false,
+    },
+    ImageFormatInfo {
+        name: "DDS",
+        extensions: &["dds"],
+        mime_type: "image/vnd-ms.dds",
+        decoder: FormatDecoder::Image(ImageFormat::Dds),
+        webview_native: false,
+        exportable: false,
+    },
+    ImageFormatInfo {
+        name: "EXR",
+        extensions: &["exr"],
+        mime_type: "image/x-exr",
+        decoder: FormatDecoder::Image(ImageFormat::OpenExr),
+        webview_native: false,
+        exportable: false,
+    },
+    ImageFormatInfo {
+        name: "HDR",
+        extensions: &["hdr", "rgbe"],
+        mime_type: "image/vnd.radiance",
+        decoder: FormatDecoder::Image(ImageFormat::Hdr),
+        webview_native: false,
+        exportable: false,
+    },
+    ImageFormatInfo {
+        name: "HEIF",
+        extensions: &["heic", "heif", "hif"],
+        mime_type: "image/heif",
+        decoder: FormatDecoder::Heif,
+        webview_native: false,
+        exportable: false,
+    },
+    ImageFormatInfo {
+        name: "AVIF",
+        extensions: &["avif"],
+        mime_type: "image/avif",
+        decoder: FormatDecoder::Heif,
+        webview_native: false,
+        exportable: false,
+    },
+    ImageFormatInfo {
+        name: "CR2",
+        extensions: &["cr2"],
+        mime_type: "image/x-canon-cr2",
+        decoder: FormatDecoder::RawPreview,
+        webview_native: false,
+        exportable: false,
+    },
+    ImageFormatInfo {
+        name: "NEF",
+        extensions: &["nef"],
+        mime_type: "image/x-nikon-nef",
+        decoder: FormatDecoder::RawPreview,
+        webview_native: false,
+        exportable: false,
+    },
+    ImageFormatInfo {
+        name: "ARW",
+        extensions: &["arw"],
+        mime_type: "image/x-sony-arw",
+        decoder: FormatDecoder::RawPreview,
+        webview_native: false,
+        exportable: false,
+    },
+    ImageFormatInfo {
+        name: "DNG",
+        extensions: &["dng"],
+        mime_type: "image/x-adobe-dng",
+        decoder: FormatDecoder::RawPreview,
+        webview_native: false,
+        exportable: false,
+    },
+    ImageFormatInfo {
+        name: "RAF",
+        extensions: &["raf"],
+        mime_type: "image/x-fuji-raf",
+        decoder: FormatDecoder::RawPreview,
+        webview_native: false,
+        exportable: false,
+    },
+    ImageFormatInfo {
+        name: "SVG",
+        extensions: &["svg", "svgz"],
+        mime_type: "image/svg+xml",
+        decoder: FormatDecoder::Svg,
+        webview_native: false,
+        exportable: false,
+    },
+];
+
+/// The magic bytes of Windows cursor files, which the `image` crate decodes as ICO but does not recognise.
+const CUR_MAGIC: [u8; 4] = [0, 0, 2, 0];
+
+/// `ftyp` brands marking an ISO base media file as an AVIF image.
+const AVIF_BRANDS: &[&[u8; 4]] = &[b"avif", b"avis"];
+
+/// `ftyp` brands marking an ISO base media file as a HEIF image.
+const HEIF_BRANDS: &[&[u8; 4]] = &[
+    b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"hevm", b"hevs", b"mif1", b"msf1",
+];
+
+/// Looks up the available registry entry with the given name.
+fn format_named(name: &str) -> Option<&'static ImageFormatInfo> {
+    IMAGE_FORMATS
+        .iter()
+        .find(|info| info.name == name && info.decoder.is_available())
+}
+
+/// Looks up the registry entry for an `image` crate format.
+fn format_info(image_format: ImageFormat) -> Option<&'static ImageFormatInfo> {
+    IMAGE_FORMATS
+        .iter()
+        .find(|info| info.decoder == FormatDecoder::Image(image_format))
+}
+
+/// Identifies AVIF and HEIF images by the brands in their `ftyp` box.
+///
+/// AVIF brands take precedence, as AVIF files also carry the generic HEIF brands.
+fn sniff_isobmff_brand(header: &[u8]) -> Option<&'static ImageFormatInfo> {
+    if header.len() < 16 || &header[4..8] != b"ftyp" {
+        return None;
+    }
+    let box_size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let box_end = box_size.clamp(16, header.len());
+
+    // The major brand, followed by the minor version and the compatible brands.
+    let brands: Vec<&[u8]> = std::iter::once(&header[8..12])
+        .chain(header[16..box_end].chunks_exact(4))
+        .collect();
+    let has_brand = |known: &[&[u8; 4]]| brands.iter().any(|b| known.iter().any(|k| &k[..] == *b));
+
+    if has_brand(AVIF_BRANDS) {
+        format_named("AVIF")
+    } else if has_brand(HEIF_BRANDS) {
+        format_named("HEIF")
+    } else {
+        None
+    }
+}
+
+/// Identifies Canon CR2 and Fujifilm RAF files by their signatures.
+///
+/// CR2 files are TIFF files marked with "CR" and a major version after the TIFF header.
+fn sniff_raw_signature(header: &[u8]) -> Option<&'static ImageFormatInfo> {
+    if header.starts_with(RAF_MAGIC) {
+        format_named("RAF")
+    } else if header.len() >= 10 && header.starts_with(b"II*\0") && &header[8..10] == b"CR" {
+        format_named("CR2")
+    } else {
+        None
+    }
+}
+
+/// Identifies SVG images by their XML markup, and gzip-compressed SVGZ images by their extension.
+///
+/// SVG files often start with a long prologue, so markup without an `<svg` element in the
+/// header is accepted if the file has an SVG extension.
+fn sniff_svg(extension: &str, header: &[u8]) -> Option<&'static ImageFormatInfo> {
+    let has_svg_extension =
+        format_from_extension(extension).is_some_and(|info| info.decoder == FormatDecoder::Svg);
+    if header.starts_with(&[0x1f, 0x8b]) {
+        return has_svg_extension.then(|| format_named("SVG")).flatten();
+    }
+
+    let text = String::from_utf8_lossy(header);
+    let markup = text.trim_start_matches('\u{feff}').trim_start();
+    let is_svg = markup.starts_with('<') && (text.contains("<svg") || has_svg_extension);
+    is_svg.then(|| format_named("SVG")).flatten()
+}
+
+/// Identifies the format of an image from its leading bytes.
+///
+/// # Arguments
+/// * `header` - The first bytes of the image data.
+///
+/// # Returns
+/// `Option<&'static ImageFormatInfo>` - The detected format, or `None` if the data is not a supported image.
+pub fn sniff_image_format(header: &[u8]) -> Option<&'static ImageFormatInfo> {
+    if let Some(info) = sniff_isobmff_brand(header).or_else(|| sniff_raw_signature(header)) {
+        return Some(info);
+    }
+    match image::guess_format(header) {
+        Ok(image_format) => format_info(image_format),
+        Err(_) if header.starts_with(&CUR_MAGIC) => format_info(ImageFormat::Ico),
+        Err(_) => None,
+    }
+}
+
+/// Identifies the format of an image from its leading bytes and path.
+///
+/// TGA files have no magic bytes, so they are recognised by a TGA extension combined with a
+/// plausible TGA header. NEF, ARW and DNG files are plain TIFF files, so TIFF data with one of
+/// their extensions is treated as RAW, and XML markup is only taken for an SVG image if it
+/// contains an `<svg` element or has an SVG extension. All other formats are identified by
+/// their content alone.
+///
+/// # Arguments
+/// * `path` - The path to the image file.
+/// * `header` - The first bytes of the image data.
+///
+/// # Returns
+/// `Option<&'static ImageFormatInfo>` - The detected format, or `None` if the data is not a supported image.
+pub fn identify_image_format(path: &Path, header: &[u8]) -> Option<&'static ImageFormatInfo> {
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    let by_extension = format_from_extension(extension);
+    match sniff_image_format(header) {
+        Some(info) if info.decoder == FormatDecoder::Image(ImageFormat::Tiff) => Some(
+            by_extension
+                .filter(|raw| raw.decoder == FormatDecoder::RawPreview)
+                .unwrap_or(info),
+        ),
+        Some(info) => Some(info),
+        None => by_extension
+            .filter(|info| info.decoder == FormatDecoder::Image(ImageFormat::Tga))
+            .filter(|_| is_plausible_tga_header(header))
+            .or_else(|| sniff_svg(extension, header)),
+    }
+}
+
+/// Checks whether the given bytes form a valid TGA header.
+fn is_plausible_tga_header(header: &[u8]) -> bool {
+    if header.len() < 18 {
+        return false;
+    }
+    let color_map_type = header[1];
+    let image_type = header[2];
+    let width = u16::from_le_bytes([header[12], header[13]]);
+    let height = u16::from_le_bytes([header[14], header[15]]);
+    let pixel_depth = header[16];
+
+    color_map_type <= 1
+        && matches!(image_type, 1 | 2 | 3 | 9 | 10 | 11)
+        && width > 0
+        && height > 0
+        && matches!(pixel_depth, 8 | 15 | 16 | 24 | 32)
+}
+
+/// Identifies the format of an image file from its content, regardless of its extension.
+///
+/// # Arguments
+/// * `path` - The path to the file.
+///
+/// # Returns
+/// `Option<&'static ImageFormatInfo>` - The detected format, or `None` if the file cannot be read
+/// or is not a supported image.
+pub fn sniff_file_format(path: &Path) -> Option<&'static ImageFormatInfo> {
+    let mut header = Vec::with_capacity(FORMAT_HEADER_LEN as usize);
+    File::open(path)
+        .ok()?
+        .take(FORMAT_HEADER_LEN)
+        .read_to_end(&mut header)
+        .ok()?;
+    identify_image_format(path, &header)
+}
+
+/// Looks up a format by one of its file extensions, ignoring case.
+///
+/// # Arguments
+/// * `extension` - The file extension without the leading dot.
+///
+/// # Returns
+/// `Option<&'static ImageFormatInfo>` - The matching format, if any.
+pub fn format_from_extension(extension: &str) -> Option<&'static ImageFormatInfo> {
+    let extension = extension.to_lowercase();
+    IMAGE_FORMATS
+        .iter()
+        .find(|info| info.decoder.is_available() && info.extensions.contains(&extension.as_str()))
+}
+
+/// Returns the file extensions offered in the open dialog filter.
+///
+/// Both lowercase and uppercase variants are listed, as some platforms match filters case-sensitively.
+///
+/// # Returns
+/// `Vec<String>` - The extensions of all supported formats.
+pub fn dialog_extensions() -> Vec<String> {
+    IMAGE_FORMATS
+        .iter()
+        .filter(|info| info.decoder.is_available())
+        .flat_map(|info| info.extensions.iter())
+        .flat_map(|ext| [ext.to_string(), ext.to_uppercase()])
+        .collect()
+}
+
+/// Returns the names of the formats images can be exported to, as used for the save dialog.
+///
+/// # Returns
+/// `Vec<String>` - The preferred extension of every exportable format.
+pub fn export_format_names() -> Vec<String> {
+    IMAGE_FORMATS
+        .iter()
+        .filter(|info| info.exportable)
+        .map(|info| info.extensions[0].to_string())
+        .collect()
+}
+
+/// Guesses the MIME type and format name of an image given its bytes and path.
+///
+/// The format is identified from the content first. If the data is not recognised,
+/// the file extension is looked up in the registry, and as a last resort the MIME type
+/// is guessed from the path with the `mime_guess` crate.
+///
+/// # Arguments
+/// * `path` - The path to the image file.
+/// * `bytes` - The leading bytes of the image.
+///
+/// # Returns
+/// A tuple containing the MIME type and format name of the image.
+pub fn guess_image_format(path: &Path, bytes: &[u8]) -> (String, String) {
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    match identify_image_format(path, bytes).or_else(|| format_from_extension(extension)) {
+        Some(info) => (info.mime_type.to_string(), info.name.to_string()),
+        None => (
+            mime_guess::from_path(path)
+                .first_or_octet_stream()
+                .essence_str()
+                .to_string(),
+            extension.to_uppercase(),
+        ),
+    }
+}
+
+/// Identifies the format of the image read from the given reader.
+/// The reader is rewound to the start afterwards.
+///
+/// # Arguments
+/// * `reader` - A reader over the image data, positioned at its start.
+/// * `path` - The path the data was read from, used to recognise formats without magic bytes.
+///
+/// # Returns
+/// `Result<&'static ImageFormatInfo, String>` - The detected format, or an error string if
+/// the format is not supported.
+pub fn identify_reader_format<R: BufRead + Seek>(
+    reader: &mut R,
+    path: &Path,
+) -> Result<&'static ImageFormatInfo, String> {
+    let mut header = Vec::with_capacity(FORMAT_HEADER_LEN as usize);
+    reader
+        .by_ref()
+        .take(FORMAT_HEADER_LEN)
+        .read_to_end(&mut header)
+        .map_err(|e| format!("Failed to read image header: {}", e))?;
+    reader
+        .rewind()
+        .map_err(|e| format!("Failed to rewind image reader: {}", e))?;
+
+    identify_image_format(path, &header)
+        .ok_or_else(|| format!("Unsupported image format: {}", path.display()))
+}
+
+/// Decodes an image from the given reader with the decoder registered for its format.
+///
+/// This is the single place images are decoded, so every decoder runs under the given limits.
+///
+/// # Arguments
+/// * `reader` - A reader over the image data, positioned at its start.
+/// * `path` - The path the data was read from, used to recognise formats without magic bytes.
+/// * `limits` - The limits the decoder has to stay within.
+///
+/// # Returns
+/// `Result<DynamicImage, DecodeError>` - The decoded image, or an error if it cannot be decoded
+/// or exceeds the limits.
+fn decode_from_reader<R: BufRead + Seek>(
+    mut reader: R,
+    path: &Path,
+    limits: &DecodeLimits,
+) -> Result<DynamicImage, DecodeError> {
+    match identify_reader_format(&mut reader, path)?.decoder {
+        FormatDecoder::Image(image_format) => {
+            decode_with_limits(ImageReader::with_format(reader, image_format), limits)
+        }
+        #[cfg(feature = "heif")]
+        FormatDecoder::Heif => heif_decoder::decode_heif(&read_all(reader)?, limits),
+        #[cfg(not(feature = "heif"))]
+        FormatDecoder::Heif => Err(DecodeError::Failed(
+            "HEIF support is not enabled in this build".to_string(),
+        )),
+        FormatDecoder::RawPreview => {
+            let jpeg = read_raw_preview(reader)?;
+            decode_with_limits(
+                ImageReader::with_format(Cursor::new(jpeg), ImageFormat::Jpeg),
+                limits,
+            )
+        }
+        FormatDecoder::Svg => render_svg(&read_all(reader)?, path, 1.0, limits),
+    }
+}
+
+/// Reads the remaining image data from the given reader, for decoders that work on a byte slice.
+pub fn read_all<R: Read>(mut reader: R) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read image: {}", e))?;
+    Ok(bytes)
+}
+
+/// Decodes an image with the `image` crate, enforcing the given limits.
+fn decode_with_limits<R: BufRead + Seek>(
+    mut image_reader: ImageReader<R>,
+    limits: &DecodeLimits,
+) -> Result<DynamicImage, DecodeError> {
+    image_reader.limits(limits.to_image_limits());
+    image_reader
+        .decode()
+        .map_err(|e| DecodeError::from_image_error(e, "Failed to decode image"))
+}
+
+/// Opens and decodes the image file at the given path.
+/// Images stored in archives are read from the archive into memory first.
+///
+/// # Arguments
+/// * `path` - The path to the image file or archive entry.
+/// * `limits` - The limits the decoder has to stay within.
+///
+/// # Returns
+/// `Result<DynamicImage, DecodeError>` - The decoded image, or an error if it cannot be decoded
+/// or exceeds the limits.
+pub fn open_image(path: &Path, limits: &DecodeLimits) -> Result<DynamicImage, DecodeError> {
+    if split_archive_path(path).is_some() {
+        return decode_image(&read_image_bytes(path, limits)?, path, limits);
+    }
+    let file =
+        File::open(path).map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+    decode_from_reader(BufReader::new(file), path, limits)
+}
+
+/// Decodes an image held in memory.
+///
+/// # Arguments
+/// * `bytes` - The encoded image data.
+/// * `path` - The path the data was read from, used to recognise formats without magic bytes.
+/// * `limits` - The limits the decoder has to stay within.
+///
+/// # Returns
+/// `Result<DynamicImage, DecodeError>` - The decoded image, or an error if it cannot be decoded
+/// or exceeds the limits.
+pub fn decode_image(
+    bytes: &[u8],
+    path: &Path,
+    limits: &DecodeLimits,
+) -> Result<DynamicImage, DecodeError> {
+    decode_from_reader(Cursor::new(bytes), path, limits)
+}
+
+/// Reads the dimensions of the image file at the given path without decoding its pixel data.
+///
+/// # Arguments
+/// * `path` - The path to the image file.
+///
+/// # Returns
+/// `Result<(u32, u32), String>` - The width and height of the image.
+pub fn read_image_dimensions(path: &Path) -> Result<(u32, u32), String> {
+    let file =
+        File::open(path).map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+    let mut reader = BufReader::new(file);
+    match identify_reader_format(&mut reader, path)?.decoder {
+        FormatDecoder::Image(image_format) => ImageReader::with_format(reader, image_format)
+            .into_dimensions()
+            .map_err(|e| format!("Failed to read image dimensions: {}", e)),
+        #[cfg(feature = "heif")]
+        FormatDecoder::Heif => heif_decoder::read_heif_details(reader).map(|(dims, _)| dims),
+        #[cfg(not(feature = "heif"))]
+        FormatDecoder::Heif => Err("HEIF support is not enabled in this build".to_string()),
+        FormatDecoder::RawPreview => find_raw_preview(reader).map(|p| (p.width, p.height)),
+        FormatDecoder::Svg => read_svg_info(&read_all(reader)?, path).map(|s| (s.width, s.height)),
+    }
+}
+
+/// Returns `true` if decoding the given data already applies the orientation stored in the
+/// file, as libheif does for HEIF and AVIF images. EXIF orientation must not be applied again.
+///
+/// # Arguments
+/// * `bytes` - The leading bytes of the image.
+///
+/// # Returns
+/// `bool` - Whether the decoded image is already upright.
+pub fn decoder_applies_orientation(bytes: &[u8]) -> bool {
+    sniff_image_format(bytes).is_some_and(|info| info.decoder == FormatDecoder::Heif)
+}
@@ -1,12 +1,35 @@
-use base64::{engine::general_purpose, Engine};
 use exif::Reader;
-use image::{self, DynamicImage, ImageDecoder, ImageFormat};
-use mime_guess;
+use image::codecs::png::PngEncoder;
+use image::{self, DynamicImage, ImageDecoder, ImageEncoder, ImageFormat};
 
-use crate::models::image::ImageMetadata;
-use std::fs;
-use std::io::Cursor;
+use crate::models::image::{ExportOptions, ImageMetadata};
+use crate::utils::animated_export::{probe_animated_source, save_animation};
+use crate::utils::animation::{read_animation_info, AnimationInfo};
+use crate::utils::archive::{
+    read_archive_entry, read_image_bytes, split_archive_path, storage_path,
+};
+use crate::utils::color_profile::{
+    can_embed_icc_profile, convert_to_srgb, embed_webp_icc_profile, needs_srgb_conversion,
+    profile_description, read_icc_profile,
+};
+use crate::utils::decode_limits::DecodeLimits;
+#[cfg(feature = "heif")]
+use crate::utils::heif_decoder::read_heif_details;
+use crate::utils::image_cache::ImageCache;
+use crate::utils::image_formats::{
+    decode_image, decoder_applies_orientation, export_format_names, guess_image_format,
+    identify_image_format, identify_reader_format, open_image, read_all, sniff_file_format,
+    FormatDecoder, FORMAT_HEADER_LEN,
+};
+use crate::utils::image_protocol::{register_image_url, ImageRegistry};
+use crate::utils::raw_preview::{find_raw_preview, read_raw_preview};
+use crate::utils::svg_renderer::{fit_svg_scale, read_svg_info, read_view_box, render_svg};
+use crate::utils::tiff_utils::{count_tiff_pages, decode_tiff_page};
+use crate::utils::tone_mapping::{measure_dynamic_range, to_display_image, ToneMapSettings};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use webp;
 
 // C2PA UUID for ai generated content detection
@@ -36,32 +59,517 @@ fn gcd(a: u32, b: u32) -> u32 {
 
 /// Reads an image file from the specified path and extracts its metadata.
 ///
+/// The image is registered with the image protocol, and the returned metadata carries
+/// the URL the webview can fetch it from. Files that fit into the image cache are read
+/// into memory and cached, so the protocol can serve them without touching the disk again.
+///
 /// # Arguments
 /// * `path` - The path to the image file.
+/// * `registry` - The image registry used by the image protocol.
+/// * `cache` - The image cache.
+/// * `tone_map` - The settings used to render high dynamic range images for display.
+/// * `convert_to_srgb` - Whether images with an embedded color profile are converted to sRGB for display.
+/// * `limits` - The limits images are decoded under.
 ///
 /// # Returns
 /// `Result<ImageMetadata, String>` - The extracted image metadata.
-pub async fn read_image_file(path: &str) -> Result<ImageMetadata, String> {
+pub async fn read_image_file(
+    path: &str,
+    registry: &ImageRegistry,
+    cache: &ImageCache,
+    tone_map: ToneMapSettings,
+    convert_to_srgb: bool,
+    limits: DecodeLimits,
+) -> Result<ImageMetadata, String> {
     let path_buf = PathBuf::from(&path);
-    let bytes = read_file_bytes(&path_buf).await?;
-    let metadata = tokio::task::spawn_blocking(move || process_image_metadata(&path_buf, &bytes))
-        .await
-        .map_err(|e| format!("Failed to spawn blocking task: {}", e))??;
+    if let Some(metadata) = cache.get_metadata(&path_buf) {
+        return Ok(metadata);
+    }
+
+    let modified = get_modified_time(&path_buf)?;
+    let image_url = register_image_url(registry, &path_buf);
+    let max_cached_size = cache.max_entry_size();
+    let load_path = path_buf.clone();
+    let (metadata, bytes) = tokio::task::spawn_blocking(move || {
+        load_image_file(
+            &load_path,
+            image_url,
+            max_cached_size,
+            &tone_map,
+            convert_to_srgb,
+            &limits,
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))??;
+
+    if let Some(bytes) = bytes {
+        cache.insert(&path_buf, metadata.clone(), bytes, modified);
+    }
     Ok(metadata)
 }
 
-/// Reads the contents of a file at the specified path.
+/// Loads the metadata of the image file at the given path.
+///
+/// If the file is no larger than `max_cached_size`, its contents are read into memory
+/// and returned alongside the metadata. Larger files are only read as far as needed.
+/// Images the webview cannot display as they are, because of their format, their bit depth
+/// or their color profile, are always decoded, and an 8-bit sRGB PNG rendition is returned
+/// in place of the file contents. For camera RAW files, the embedded JPEG preview is returned instead.
+/// Images stored in archives are always read into memory.
+///
+/// # Arguments
+/// * `path` - The path to the image file or archive entry.
+/// * `image_url` - The image protocol URL the image is served from.
+/// * `max_cached_size` - The largest file size in bytes that is read into memory.
+/// * `tone_map` - The settings used to render high dynamic range images for display.
+/// * `convert_to_srgb` - Whether images with an embedded color profile are converted to sRGB.
+/// * `limits` - The limits images are decoded under. Images exceeding them are rejected,
+///   even if the webview would decode them itself.
+///
+/// # Returns
+/// `Result<(ImageMetadata, Option<Vec<u8>>), String>` - The image metadata and, if read, the bytes to serve.
+fn load_image_file(
+    path: &Path,
+    image_url: String,
+    max_cached_size: u64,
+    tone_map: &ToneMapSettings,
+    convert_to_srgb: bool,
+    limits: &DecodeLimits,
+) -> Result<(ImageMetadata, Option<Vec<u8>>), String> {
+    if let Some((archive, entry)) = split_archive_path(path) {
+        let (metadata, bytes) = load_archive_entry(
+            path,
+            &archive,
+            &entry,
+            image_url,
+            tone_map,
+            convert_to_srgb,
+            limits,
+        )?;
+        return Ok((metadata, Some(bytes)));
+    }
+
+    let format = sniff_file_format(path);
+    if format.is_some_and(|info| info.decoder == FormatDecoder::RawPreview) {
+        let metadata =
+            process_image_metadata(path, &mut open_file_reader(path)?, image_url, limits)?;
+        let preview = read_upright_raw_preview(open_file_reader(path)?, limits)?;
+        return Ok((metadata, Some(preview)));
+    }
+
+    let webview_native = format.is_some_and(|info| info.webview_native);
+    if webview_native && get_file_size(path)? > max_cached_size {
+        let mut metadata = process_image_metadata(
+            path,
+            &mut open_file_reader(path)?,
+            image_url.clone(),
+            limits,
+        )?;
+        let icc_profile = read_icc_profile(open_file_reader(path)?, path);
+        metadata.color_profile = icc_profile.as_deref().and_then(profile_description);
+        if !needs_display_conversion(
+            metadata.color_depth,
+            icc_profile.as_deref(),
+            convert_to_srgb,
+        ) {
+            return Ok((metadata, None));
+        }
+    }
+
+    let bytes =
+        fs::read(path).map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+    let (metadata, bytes) =
+        load_image_bytes(path, bytes, image_url, tone_map, convert_to_srgb, limits)?;
+    Ok((metadata, Some(bytes)))
+}
+
+/// Loads the metadata of an image held in memory and prepares the bytes the webview is served.
+///
+/// Images the webview can display as they are are served unchanged. All others are decoded
+/// and served as an 8-bit sRGB PNG rendition, and camera RAW files as their embedded JPEG preview.
+///
+/// # Arguments
+/// * `path` - The path the image was read from, used to recognise formats without magic bytes.
+/// * `bytes` - The encoded image data.
+/// * `image_url` - The image protocol URL the image is served from.
+/// * `tone_map` - The settings used to render high dynamic range images for display.
+/// * `convert_to_srgb` - Whether images with an embedded color profile are converted to sRGB.
+/// * `limits` - The limits images are decoded under.
+///
+/// # Returns
+/// `Result<(ImageMetadata, Vec<u8>), String>` - The image metadata and the bytes to serve.
+fn load_image_bytes(
+    path: &Path,
+    bytes: Vec<u8>,
+    image_url: String,
+    tone_map: &ToneMapSettings,
+    convert_to_srgb: bool,
+    limits: &DecodeLimits,
+) -> Result<(ImageMetadata, Vec<u8>), String> {
+    let format = identify_image_format(path, &bytes);
+    let mut metadata = process_image_metadata(path, &mut Cursor::new(&bytes), image_url, limits)?;
+    if format.is_some_and(|info| info.decoder == FormatDecoder::RawPreview) {
+        let preview = read_upright_raw_preview(Cursor::new(&bytes), limits)?;
+        return Ok((metadata, preview));
+    }
+
+    let webview_native = format.is_some_and(|info| info.webview_native);
+    let icc_profile = read_icc_profile(Cursor::new(&bytes), path);
+    metadata.color_profile = icc_profile.as_deref().and_then(profile_description);
+    if webview_native
+        && !needs_display_conversion(
+            metadata.color_depth,
+            icc_profile.as_deref(),
+            convert_to_srgb,
+        )
+    {
+        return Ok((metadata, bytes));
+    }
+
+    let mut img = decode_image(&bytes, path, limits)?;
+    metadata.dynamic_range = measure_dynamic_range(&img);
+    // Renditions depend on the display settings, so the URL has to change along with them.
+    metadata.image_url = format!("{}&r={}", metadata.image_url, tone_map.cache_key());
+    if let Some(icc) = icc_profile.filter(|icc| convert_to_srgb && needs_srgb_conversion(icc)) {
+        img = to_srgb_for_display(img, &icc);
+        metadata.image_url.push_str("&cms=srgb");
+    }
+    let rendition = encode_png(&to_display_image(img, tone_map))?;
+    Ok((metadata, rendition))
+}
+
+/// Loads the metadata of an image stored in an archive, reading the entry from the archive
+/// stream without extracting it to disk.
+///
+/// # Arguments
+/// * `path` - The path of the archive entry, such as `comic.cbz/001.jpg`.
+/// * `archive` - The path of the archive.
+/// * `entry` - The name of the entry within the archive.
+/// * `image_url` - The image protocol URL the image is served from.
+/// * `tone_map` - The settings used to render high dynamic range images for display.
+/// * `convert_to_srgb` - Whether images with an embedded color profile are converted to sRGB.
+/// * `limits` - The limits images are decoded under, which also cap the size of the entry.
+///
+/// # Returns
+/// `Result<(ImageMetadata, Vec<u8>), String>` - The image metadata, carrying the archive and entry, and the bytes to serve.
+fn load_archive_entry(
+    path: &Path,
+    archive: &Path,
+    entry: &str,
+    image_url: String,
+    tone_map: &ToneMapSettings,
+    convert_to_srgb: bool,
+    limits: &DecodeLimits,
+) -> Result<(ImageMetadata, Vec<u8>), String> {
+    let bytes = read_archive_entry(archive, entry, limits.max_alloc)?;
+    let (mut metadata, bytes) =
+        load_image_bytes(path, bytes, image_url, tone_map, convert_to_srgb, limits)?;
+    metadata.archive_path = Some(archive.to_string_lossy().to_string());
+    metadata.archive_entry = Some(entry.to_string());
+    Ok((metadata, bytes))
+}
+
+/// Renders an image stored in an archive in a form the webview can display.
+///
+/// # Arguments
+/// * `path` - The path of the archive entry.
+/// * `tone_map` - The settings used to render high dynamic range images.
+/// * `convert_to_srgb` - Whether images with an embedded color profile are converted to sRGB.
+/// * `limits` - The limits the image is decoded under.
+///
+/// # Returns
+/// `Result<Vec<u8>, String>` - The bytes to serve, or an error string if the path is no readable archive entry.
+pub fn render_archive_entry(
+    path: &Path,
+    tone_map: &ToneMapSettings,
+    convert_to_srgb: bool,
+    limits: &DecodeLimits,
+) -> Result<Vec<u8>, String> {
+    let (archive, entry) = split_archive_path(path)
+        .ok_or_else(|| format!("Not an archive entry: {}", path.display()))?;
+    load_archive_entry(
+        path,
+        &archive,
+        &entry,
+        String::new(),
+        tone_map,
+        convert_to_srgb,
+        limits,
+    )
+    .map(|(_, bytes)| bytes)
+}
+
+/// Reads the metadata of an image file or archive entry without preparing it for display.
+///
+/// Only as much of the image as the metadata needs is read, and the returned metadata
+/// carries no image URL, since the image is not registered with the image protocol.
+///
+/// # Arguments
+/// * `path` - The path to the image file or archive entry.
+/// * `limits` - The limits the image has to stay within.
+///
+/// # Returns
+/// `Result<ImageMetadata, String>` - The image metadata, or an error string if the image cannot be read
+/// or exceeds the decoding limits.
+pub fn read_image_metadata(path: &Path, limits: &DecodeLimits) -> Result<ImageMetadata, String> {
+    match split_archive_path(path) {
+        Some((archive, entry)) => {
+            let bytes = read_archive_entry(&archive, &entry, limits.max_alloc)?;
+            let mut metadata =
+                process_image_metadata(path, &mut Cursor::new(&bytes), String::new(), limits)?;
+            metadata.color_profile = read_icc_profile(Cursor::new(&bytes), path)
+                .as_deref()
+                .and_then(profile_description);
+            metadata.archive_path = Some(archive.to_string_lossy().to_string());
+            metadata.archive_entry = Some(entry);
+            Ok(metadata)
+        }
+        None => {
+            let mut metadata =
+                process_image_metadata(path, &mut open_file_reader(path)?, String::new(), limits)?;
+            metadata.color_profile = read_icc_profile(open_file_reader(path)?, path)
+                .as_deref()
+                .and_then(profile_description);
+            Ok(metadata)
+        }
+    }
+}
+
+/// Returns `true` if images of the given color depth have to be reduced to 8 bits for display.
+fn is_high_bit_depth(color_depth: Option<u8>) -> bool {
+    color_depth.is_some_and(|depth| depth > 8)
+}
+
+/// Returns `true` if an image the webview could decode has to be rendered again to be shown correctly,
+/// because it has more than 8 bits per channel or a color profile that is converted to sRGB.
+fn needs_display_conversion(
+    color_depth: Option<u8>,
+    icc_profile: Option<&[u8]>,
+    convert_to_srgb: bool,
+) -> bool {
+    is_high_bit_depth(color_depth)
+        || (convert_to_srgb && icc_profile.is_some_and(needs_srgb_conversion))
+}
+
+/// Converts an image with the given color profile to sRGB for display.
+/// If the profile cannot be used, the image is shown unconverted.
+fn to_srgb_for_display(img: DynamicImage, icc: &[u8]) -> DynamicImage {
+    match convert_to_srgb(&img, icc) {
+        Ok(converted) => converted,
+        Err(e) => {
+            eprintln!("Failed to convert image to sRGB: {}", e);
+            img
+        }
+    }
+}
+
+/// Returns `true` if the image file at the given path cannot be displayed by the webview as it is,
+/// because of its format, because it has more than 8 bits per channel, or because its color profile
+/// is converted to sRGB.
+///
+/// # Arguments
+/// * `path` - The path to the image file.
+/// * `convert_to_srgb` - Whether images with an embedded color profile are converted to sRGB.
+///
+/// # Returns
+/// `bool` - Whether the image has to be shown through a rendition.
+pub fn needs_rendition(path: &Path, convert_to_srgb: bool) -> bool {
+    match sniff_file_format(path) {
+        Some(info) if info.webview_native => {
+            let Ok((_, color_depth)) =
+                open_file_reader(path).and_then(|reader| get_image_details(reader, path))
+            else {
+                return false;
+            };
+            let icc_profile = convert_to_srgb
+                .then(|| open_file_reader(path).ok())
+                .flatten()
+                .and_then(|reader| read_icc_profile(reader, path));
+            needs_display_conversion(color_depth, icc_profile.as_deref(), convert_to_srgb)
+        }
+        Some(_) => true,
+        None => false,
+    }
+}
+
+/// Renders a page of a multi-page TIFF file in a form the webview can display.
+///
+/// # Arguments
+/// * `path` - The path to the TIFF file.
+/// * `page` - The index of the page, starting at 0.
+/// * `tone_map` - The settings used to render high dynamic range images.
+/// * `convert_to_srgb` - Whether images with an embedded color profile are converted to sRGB.
+/// * `limits` - The limits the page is decoded under.
+///
+/// # Returns
+/// `Result<Vec<u8>, String>` - The page encoded as an 8-bit PNG, or an error string if it cannot be decoded.
+pub fn render_tiff_page(
+    path: &Path,
+    page: u32,
+    tone_map: &ToneMapSettings,
+    convert_to_srgb: bool,
+    limits: &DecodeLimits,
+) -> Result<Vec<u8>, String> {
+    let mut img = decode_tiff_page(open_file_reader(path)?, page, limits)?;
+    if convert_to_srgb {
+        if let Some(icc) = read_icc_profile(open_file_reader(path)?, path) {
+            img = to_srgb_for_display(img, &icc);
+        }
+    }
+    encode_png(&to_display_image(img, tone_map))
+}
+
+/// Reads the embedded JPEG preview of a camera RAW file, turned upright.
+///
+/// Previews rarely carry an orientation of their own, so the Orientation of the RAW file is
+/// applied to them. Upright previews are returned as they are, rotated ones are re-encoded.
+///
+/// # Arguments
+/// * `reader` - A reader over the RAW file.
+/// * `limits` - The limits a rotated preview is decoded under.
+///
+/// # Returns
+/// `Result<Vec<u8>, String>` - The JPEG data, or an error string if the file has no usable preview.
+fn read_upright_raw_preview<R: BufRead + Seek>(
+    mut reader: R,
+    limits: &DecodeLimits,
+) -> Result<Vec<u8>, String> {
+    let orientation = Reader::new()
+        .read_from_container(&mut reader)
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        });
+    let preview = read_raw_preview(&mut reader)?;
+    let orientation = match orientation {
+        Some(orientation) if orientation != 1 => orientation as u16,
+        _ => return Ok(preview),
+    };
+
+    let mut decoder = image::ImageReader::with_format(Cursor::new(&preview), ImageFormat::Jpeg);
+    decoder.limits(limits.to_image_limits());
+    let img = decoder
+        .decode()
+        .map_err(|e| format!("Failed to decode RAW preview: {}", e))?;
+    let img = apply_orientation_correction(img, orientation).to_rgb8();
+
+    let mut bytes = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, 92)
+        .encode_image(&img)
+        .map_err(|e| format!("Failed to encode RAW preview: {}", e))?;
+    Ok(bytes)
+}
+
+/// Renders the image file at the given path in a form the webview can display.
+///
+/// Camera RAW files are rendered as their embedded JPEG preview. All other images are
+/// decoded and encoded as an 8-bit PNG.
+///
+/// # Arguments
+/// * `path` - The path to the image file.
+/// * `tone_map` - The settings used to render high dynamic range images.
+/// * `convert_to_srgb` - Whether images with an embedded color profile are converted to sRGB.
+/// * `limits` - The limits the image is decoded under.
+///
+/// # Returns
+/// `Result<Vec<u8>, String>` - The encoded rendition.
+pub fn render_rendition(
+    path: &Path,
+    tone_map: &ToneMapSettings,
+    convert_to_srgb: bool,
+    limits: &DecodeLimits,
+) -> Result<Vec<u8>, String> {
+    if sniff_file_format(path).is_some_and(|info| info.decoder == FormatDecoder::RawPreview) {
+        return read_upright_raw_preview(open_file_reader(path)?, limits);
+    }
+    let mut img = open_image(path, limits)?;
+    if convert_to_srgb {
+        if let Some(icc) = read_icc_profile(open_file_reader(path)?, path) {
+            img = to_srgb_for_display(img, &icc);
+        }
+    }
+    encode_png(&to_display_image(img, tone_map))
+}
+
+/// Renders the SVG image at the given path as a PNG at a multiple of its intrinsic size,
+/// so it stays sharp when zoomed in. The scale is reduced as far as needed to stay within the limits.
+///
+/// # Arguments
+/// * `path` - The path to the SVG file.
+/// * `scale` - The requested factor the intrinsic size is multiplied with.
+/// * `limits` - The limits the rasterized image has to stay within.
+///
+/// # Returns
+/// `Result<Vec<u8>, String>` - The encoded rendition, or an error string if the file is no SVG image.
+pub fn render_svg_rendition(
+    path: &Path,
+    scale: f32,
+    limits: &DecodeLimits,
+) -> Result<Vec<u8>, String> {
+    let bytes = read_image_bytes(path, limits)?;
+    if !is_svg(&bytes, path) {
+        return Err(format!("Not an SVG image: {}", path.display()));
+    }
+    let info = read_svg_info(&bytes, path)?;
+    let img = render_svg(&bytes, path, fit_svg_scale(&info, scale, limits), limits)?;
+    encode_png(&img)
+}
+
+/// Returns `true` if the given image data is an SVG image.
+fn is_svg(bytes: &[u8], path: &Path) -> bool {
+    let header = &bytes[..bytes.len().min(FORMAT_HEADER_LEN as usize)];
+    identify_image_format(path, header).is_some_and(|info| info.decoder == FormatDecoder::Svg)
+}
+
+/// Encodes an image as PNG.
+///
+/// # Arguments
+/// * `img` - The image to encode.
+///
+/// # Returns
+/// `Result<Vec<u8>, String>` - The PNG-encoded image.
+pub fn encode_png(img: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode image rendition: {}", e))?;
+    Ok(bytes)
+}
+
+/// Opens the file at the specified path for buffered reading.
 ///
 /// # Arguments
-/// * `path` - The path to the file to read.
+/// * `path` - The path to the file to open.
 ///
 /// # Returns
-/// `Result<Vec<u8>, String>` - A result containing the file contents as a vector of bytes if successful,
-/// or an error string if the file cannot be read.
-async fn read_file_bytes(path: &Path) -> Result<Vec<u8>, String> {
-    tokio::fs::read(path)
-        .await
-        .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))
+/// `Result<BufReader<File>, String>` - A buffered reader over the file if successful,
+/// or an error string if the file cannot be opened.
+fn open_file_reader(path: &Path) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))
+}
+
+/// Reads the leading bytes from the given reader, which is enough to identify the image format.
+/// The reader is rewound to the start afterwards.
+///
+/// # Arguments
+/// * `reader` - A reader over the image data.
+///
+/// # Returns
+/// `Result<Vec<u8>, String>` - The first bytes of the data, or an error string if they cannot be read.
+fn read_header<R: BufRead + Seek>(reader: &mut R) -> Result<Vec<u8>, String> {
+    let mut header = Vec::with_capacity(64);
+    reader
+        .by_ref()
+        .take(64)
+        .read_to_end(&mut header)
+        .map_err(|e| format!("Failed to read image header: {}", e))?;
+    reader
+        .rewind()
+        .map_err(|e| format!("Failed to rewind image reader: {}", e))?;
+    Ok(header)
 }
 
 /// Returns the color depth of the given image color type in bits.
@@ -90,6 +598,23 @@ fn get_color_depth(color_type: image::ColorType) -> Option<u8> {
     }
 }
 
+/// Returns the size of a pixel decoded at the given bit depth per channel, counting four channels
+/// since images are expanded to RGBA for display.
+///
+/// # Arguments
+/// * `color_depth` - The bits per channel, `None` if unknown.
+///
+/// # Returns
+/// `u64` - The size of a decoded pixel in bytes, 16 for 32-bit float channels.
+fn decoded_bytes_per_pixel(color_depth: Option<u8>) -> u64 {
+    let bytes_per_sample = match color_depth {
+        Some(17..) => 4,
+        Some(9..=16) => 2,
+        _ => 1,
+    };
+    4 * bytes_per_sample
+}
+
 /// Returns the size of the file at the given path in bytes.
 ///
 /// # Arguments
@@ -103,10 +628,27 @@ fn get_file_size(path: &Path) -> Result<u64, String> {
         .map(|m| m.len())
 }
 
-/// Returns the dimensions and color depth of the image given in the bytes.
+/// Returns the last modification time of the file at the given path.
+/// Archive entries report the modification time of their archive.
+///
+/// # Arguments
+/// * `path` - The path to the file to get the modification time of.
+///
+/// # Returns
+/// `Result<SystemTime, String>` - The modification time if successful, or an error string if the file cannot be accessed.
+pub fn get_modified_time(path: &Path) -> Result<SystemTime, String> {
+    fs::metadata(storage_path(path))
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Failed to get file modification time: {}", e))
+}
+
+/// Returns the dimensions and color depth of the image read from the given reader.
+///
+/// Only the image header is read, the pixel data is not decoded.
 ///
 /// # Arguments
-/// * `bytes` - The image bytes to get the dimensions and color depth of.
+/// * `reader` - A reader over the image data to get the dimensions and color depth of.
+/// * `path` - The path the data is read from, used to identify formats without magic bytes.
 ///
 /// # Returns
 /// `Result<((u32, u32), Option<u8>), String>` - The dimensions and color depth of the image if successful,
@@ -116,12 +658,27 @@ fn get_file_size(path: &Path) -> Result<u64, String> {
 /// and the second element is the color depth of the image in bits as an `Option<u8>`.
 ///
 /// If the image color type is not supported, the color depth will be `None`.
-fn get_image_details(bytes: &[u8]) -> Result<((u32, u32), Option<u8>), String> {
-    let reader = image::ImageReader::new(Cursor::new(bytes))
-        .with_guessed_format()
-        .map_err(|e| format!("Failed to guess image format: {}", e))?;
+fn get_image_details<R: BufRead + Seek>(
+    mut reader: R,
+    path: &Path,
+) -> Result<((u32, u32), Option<u8>), String> {
+    let image_format = match identify_reader_format(&mut reader, path)?.decoder {
+        FormatDecoder::Image(image_format) => image_format,
+        #[cfg(feature = "heif")]
+        FormatDecoder::Heif => return read_heif_details(reader),
+        #[cfg(not(feature = "heif"))]
+        FormatDecoder::Heif => return Err("HEIF support is not enabled in this build".to_string()),
+        FormatDecoder::RawPreview => {
+            let preview = find_raw_preview(reader)?;
+            return Ok(((preview.width, preview.height), Some(8)));
+        }
+        FormatDecoder::Svg => {
+            let info = read_svg_info(&read_all(reader)?, path)?;
+            return Ok(((info.width, info.height), Some(8)));
+        }
+    };
 
-    let decoder = reader
+    let decoder = image::ImageReader::with_format(reader, image_format)
         .into_decoder()
         .map_err(|e| format!("Failed to create decoder: {}", e))?;
 
@@ -132,25 +689,81 @@ fn get_image_details(bytes: &[u8]) -> Result<((u32, u32), Option<u8>), String> {
     Ok((dimensions, color_depth))
 }
 
-/// Processes the given image bytes and returns an `ImageMetadata` object containing the image data as a base64-encoded string, its EXIF data as a JSON string, its width, height, aspect ratio, format, color depth, and file size.
+/// Processes the given image data and returns an `ImageMetadata` object containing the URL the image is served from, its EXIF data as a JSON string, its width, height, aspect ratio, format, color depth, and file size.
 ///
 /// # Arguments
 /// * `path` - The path to the image file.
-/// * `bytes` - The image bytes to process.
+/// * `reader` - A reader over the image data.
+/// * `image_url` - The image protocol URL the image is served from.
+/// * `limits` - The decoding limits the image has to stay within.
 ///
 /// # Returns
-/// `Result<ImageMetadata, String>` - The processed image metadata if successful, or an error string if the image cannot be processed.
-fn process_image_metadata(path: &Path, bytes: &[u8]) -> Result<ImageMetadata, String> {
-    let (mime_type, format) = guess_image_format(path, bytes);
-    let ((width, height), color_depth) = get_image_details(bytes)?;
+/// `Result<ImageMetadata, String>` - The processed image metadata if successful, or an error string if the image cannot be processed
+/// or exceeds the decoding limits.
+fn process_image_metadata<R: BufRead + Seek>(
+    path: &Path,
+    reader: &mut R,
+    image_url: String,
+    limits: &DecodeLimits,
+) -> Result<ImageMetadata, String> {
+    let header = read_header(reader)?;
+    let (_, format) = guess_image_format(path, &header);
+    let ((width, height), color_depth) = get_image_details(&mut *reader, path)?;
+    // Reject decompression bombs before anything, including the webview, decodes them.
+    limits.check(width, height, decoded_bytes_per_pixel(color_depth))?;
 
+    reader
+        .rewind()
+        .map_err(|e| format!("Failed to rewind image reader: {}", e))?;
+    let decoder = identify_image_format(path, &header).map(|info| info.decoder);
+    let page_count = match decoder {
+        Some(FormatDecoder::Image(ImageFormat::Tiff)) => count_tiff_pages(&mut *reader).ok(),
+        _ => None,
+    };
+    let animation = match decoder {
+        Some(FormatDecoder::Image(image_format)) => {
+            read_animation_info(&mut *reader, image_format).unwrap_or(None)
+        }
+        _ => None,
+    };
+    let (sensor_width, sensor_height) = match decoder {
+        Some(FormatDecoder::RawPreview) => find_raw_preview(&mut *reader)
+            .ok()
+            .and_then(|preview| preview.sensor_dimensions)
+            .unzip(),
+        _ => (None, None),
+    };
+    let view_box = match decoder {
+        Some(FormatDecoder::Svg) => {
+            reader
+                .rewind()
+                .map_err(|e| format!("Failed to rewind image reader: {}", e))?;
+            read_all(&mut *reader)
+                .ok()
+                .and_then(|bytes| read_view_box(&bytes))
+        }
+        _ => None,
+    };
+    reader
+        .rewind()
+        .map_err(|e| format!("Failed to rewind image reader: {}", e))?;
     let aspect_ratio = compute_aspect_ratio(width, height);
-    let data_url = build_data_url(&mime_type, bytes);
-    let exif_data = extract_exif_json(bytes);
-    let file_size = get_file_size(path)?;
+    let mut exif_data = extract_exif_json(reader);
+    if exif_data.is_empty() && decoder == Some(FormatDecoder::RawPreview) {
+        // RAF files are no TIFF containers, but their preview carries the EXIF data.
+        reader
+            .rewind()
+            .map_err(|e| format!("Failed to rewind image reader: {}", e))?;
+        exif_data = read_raw_preview(&mut *reader)
+            .map(|jpeg| extract_exif_json(&mut Cursor::new(jpeg)))
+            .unwrap_or_default();
+    }
+    let file_size = reader
+        .seek(SeekFrom::End(0))
+        .map_err(|e| format!("Failed to get image size: {}", e))?;
 
     Ok(ImageMetadata {
-        image_data: data_url,
+        image_url,
         exif_data,
         width,
         height,
@@ -158,42 +771,21 @@ fn process_image_metadata(path: &Path, bytes: &[u8]) -> Result<ImageMetadata, St
         format,
         color_depth,
         file_size,
+        page_count,
+        dynamic_range: None,
+        sensor_width,
+        sensor_height,
+        frame_count: animation.as_ref().map(AnimationInfo::frame_count),
+        loop_count: animation.as_ref().map(|info| info.loop_count),
+        total_duration: animation.as_ref().map(AnimationInfo::total_duration),
+        frame_delays: animation.map(|info| info.frame_delays),
+        view_box,
+        color_profile: None,
+        archive_path: None,
+        archive_entry: None,
     })
 }
 
-/// Guesses the MIME type and format of an image given its bytes and path.
-///
-/// If the image format can be determined using the `image` crate, it returns the MIME type and format as a string.
-/// If the image format cannot be determined, it falls back to using the `mime_guess` crate to determine the MIME type and uses the file extension to determine the format.
-///
-/// # Arguments
-///
-/// * `path` - The path to the image file.
-/// * `bytes` - The image bytes to guess the format of.
-///
-/// # Returns
-///
-/// A tuple containing the MIME type and format of the image as strings.
-fn guess_image_format(path: &Path, bytes: &[u8]) -> (String, String) {
-    let image_format_guess = image::guess_format(bytes);
-    let mime_type = match image_format_guess {
-        Ok(format) => format.to_mime_type().to_string(),
-        Err(_) => mime_guess::from_path(path)
-            .first_or_octet_stream()
-            .essence_str()
-            .to_string(),
-    };
-    let format = match image_format_guess {
-        Ok(f) => format!("{:?}", f).to_uppercase(),
-        Err(_) => path
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_uppercase(),
-    };
-    (mime_type, format)
-}
-
 /// Computes the aspect ratio of an image given its width and height.
 ///
 /// The aspect ratio is returned as a string in the format "width:height".
@@ -216,30 +808,15 @@ fn compute_aspect_ratio(width: u32, height: u32) -> String {
     format!("{}:{}", width / divisor, height / divisor)
 }
 
-/// Builds a data URL string from the given mime type and bytes.
-///
-/// # Arguments
-///
-/// * `mime_type` - The mime type of the data.
-/// * `bytes` - The bytes of the data.
-///
-/// # Returns
-///
-/// A string containing the data URL.
-fn build_data_url(mime_type: &str, bytes: &[u8]) -> String {
-    let base64_str = general_purpose::STANDARD.encode(bytes);
-    format!("data:{};base64,{}", mime_type, base64_str)
-}
-
-/// Extracts the EXIF data from the given bytes and returns it as a JSON string.
+/// Extracts the EXIF data from the given reader and returns it as a JSON string.
 ///
 /// The extracted EXIF data is a map of tag names to their corresponding values.
 /// The values are strings, and are either the original byte value if it is a valid UTF-8 string,
 /// or the value of `display_value` with the unit if it is not a valid UTF-8 string.
 ///
-/// If the bytes do not contain valid EXIF data, an empty string is returned.
-fn extract_exif_json(bytes: &[u8]) -> String {
-    match Reader::new().read_from_container(&mut std::io::Cursor::new(bytes)) {
+/// If the data does not contain valid EXIF data, an empty string is returned.
+fn extract_exif_json<R: BufRead + Seek>(reader: &mut R) -> String {
+    match Reader::new().read_from_container(reader) {
         Ok(exif) => {
             let exif_map: std::collections::HashMap<_, _> = exif
                 .fields()
@@ -267,14 +844,18 @@ fn extract_exif_json(bytes: &[u8]) -> String {
 
 /// Extracts the original orientation of an image from its EXIF data.
 ///
-/// Returns `None` if the bytes do not contain valid EXIF data or if the Orientation tag is not present.
+/// Returns `None` if the bytes do not contain valid EXIF data or if the Orientation tag is not present,
+/// and for formats whose decoder already applies the orientation.
 /// Returns `Some(orientation)` if the Orientation tag is present, where `orientation` is the value of the tag as a `u16`.
 ///
 /// # Arguments
 ///
 /// * `bytes` - The raw image data as a byte slice.
 fn extract_original_orientation(bytes: &[u8]) -> Option<u16> {
-    match Reader::new().read_from_container(&mut std::io::Cursor::new(bytes)) {
+    if decoder_applies_orientation(bytes) {
+        return None;
+    }
+    match Reader::new().read_from_container(&mut Cursor::new(bytes)) {
         Ok(exif) => {
             if let Some(orientation_field) =
                 exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
@@ -311,6 +892,20 @@ fn apply_orientation_correction(img: DynamicImage, orientation: u16) -> DynamicI
     }
 }
 
+/// Turns a decoded image upright according to the EXIF orientation stored in its data,
+/// so its pixels are laid out as the viewer displays them.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw image data the image was decoded from.
+/// * `img` - The decoded image.
+pub fn apply_exif_orientation(bytes: &[u8], img: DynamicImage) -> DynamicImage {
+    match extract_original_orientation(bytes) {
+        Some(orientation) => apply_orientation_correction(img, orientation),
+        None => img,
+    }
+}
+
 fn handle_exif_orientation(
     bytes: &[u8],
     img: DynamicImage,
@@ -336,7 +931,7 @@ fn handle_exif_orientation(
 /// * 270: Rotate the image 270 degrees clockwise.
 ///
 /// If the rotation is not one of the above values, the image is returned unchanged.
-fn apply_user_rotation(img: DynamicImage, rotation: i32) -> DynamicImage {
+pub fn apply_user_rotation(img: DynamicImage, rotation: i32) -> DynamicImage {
     match rotation {
         90 => img.rotate90(),
         180 => img.rotate180(),
@@ -353,6 +948,7 @@ fn apply_user_rotation(img: DynamicImage, rotation: i32) -> DynamicImage {
 /// * `save_path` - The destination path to save the image.
 /// * `format` - The desired output format (e.g., "png", "jpeg").
 /// * `quality` - Optional quality setting for formats like JPEG (0.0-100.0).
+/// * `icc_profile` - Optional ICC profile embedded into PNG, JPEG and WebP files.
 ///
 /// # Returns
 /// `Result<(), String>` - The result of the save operation. Returns an error string if the save operation fails.
@@ -361,13 +957,21 @@ fn save_image(
     save_path: &Path,
     format: &str,
     quality: Option<f32>,
+    icc_profile: Option<&[u8]>,
 ) -> Result<(), String> {
     let image_format = ImageFormat::from_extension(format)
         .ok_or_else(|| format!("Invalid image format: {}", format))?;
 
     match image_format {
-        ImageFormat::WebP => save_webp(img, save_path, quality)?,
-        ImageFormat::Jpeg => save_jpeg(img, save_path, quality)?,
+        ImageFormat::WebP => save_webp(img, save_path, quality, icc_profile)?,
+        ImageFormat::Jpeg => save_jpeg(img, save_path, quality, icc_profile)?,
+        ImageFormat::Png => save_png(img, save_path, icc_profile)?,
+        // These encoders only accept 8-bit data.
+        ImageFormat::Tga | ImageFormat::Qoi | ImageFormat::Pnm | ImageFormat::Gif => {
+            DynamicImage::ImageRgba8(img.to_rgba8())
+                .save_with_format(save_path, image_format)
+                .map_err(|e| format!("Failed to save image: {}", e))?
+        }
         _ => img
             .save_with_format(save_path, image_format)
             .map_err(|e| format!("Failed to save image: {}", e))?,
@@ -377,34 +981,65 @@ fn save_image(
 
 /// Saves an image to a file in the specified format.
 ///
+/// Animated GIF, APNG and WebP images stay animated when saved as one of these formats.
+/// The color profile of the source image is embedded into PNG, JPEG and WebP files unless
+/// a conversion to sRGB is requested. Images saved in other formats are always converted.
+///
 /// # Arguments
 ///
 /// * `bytes` - The image bytes to save.
+/// * `source_path` - The path or name the image bytes were read from, used to identify their format.
 /// * `save_path` - The path to save the image file to.
 /// * `format` - The desired image format (e.g., "png", "jpeg").
-/// * `quality` - Optional quality setting for the saved image (0.0-100.0).
-/// * `rotation` - The rotation angle in degrees (0, 90, 180, 270).
+/// * `options` - The quality, rotation, animation, SVG rasterization and color profile settings of the saved image.
+/// * `limits` - The limits the image bytes are decoded under.
 ///
 /// # Returns
 ///
 /// A `Result` containing the path to the saved image file as a string if successful, or an error string if the save operation fails.
 pub fn save_image_to_format(
     bytes: &[u8],
+    source_path: &Path,
     save_path: &Path,
     format: &str,
-    quality: Option<f32>,
-    rotation: i32,
+    options: &ExportOptions,
+    limits: &DecodeLimits,
 ) -> Result<String, String> {
     let image_format = ImageFormat::from_extension(format)
         .ok_or_else(|| format!("Invalid image format: {}", format))?;
 
-    let mut img =
-        image::load_from_memory(bytes).map_err(|e| format!("Failed to decode image: {}", e))?;
+    if let Some(source) = probe_animated_source(bytes, source_path, image_format) {
+        save_animation(&source, bytes, save_path, image_format, options, limits)?;
+        return Ok(save_path.to_string_lossy().to_string());
+    }
+
+    let mut img = match options.scale {
+        Some(scale) if is_svg(bytes, source_path) => render_svg(bytes, source_path, scale, limits)?,
+        _ => decode_image(bytes, source_path, limits)?,
+    };
 
     img = handle_exif_orientation(bytes, img, image_format);
-    img = apply_user_rotation(img, rotation);
+    img = apply_user_rotation(img, options.rotation);
 
-    save_image(&img, save_path, format, quality)?;
+    let embed_profile =
+        options.color_profile.as_deref() != Some("srgb") && can_embed_icc_profile(image_format);
+    let icc_profile = read_icc_profile(Cursor::new(bytes), source_path);
+    let embedded_profile = match icc_profile {
+        Some(icc) if embed_profile => Some(icc),
+        Some(icc) if needs_srgb_conversion(&icc) => {
+            img = to_srgb_for_display(img, &icc);
+            None
+        }
+        _ => None,
+    };
+
+    save_image(
+        &img,
+        save_path,
+        format,
+        options.quality,
+        embedded_profile.as_deref(),
+    )?;
 
     Ok(save_path.to_string_lossy().to_string())
 }
@@ -416,15 +1051,29 @@ pub fn save_image_to_format(
 /// * `img` - The image to save.
 /// * `save_path` - The destination path to save the image.
 /// * `quality` - Optional quality setting for the saved WebP image (0.0-100.0). Defaults to 75.0 if not provided.
+/// * `icc_profile` - Optional ICC profile to embed.
 ///
 /// # Returns
 /// `Result<(), String>` - The result of the save operation. Returns an error string if the save operation fails.
-fn save_webp(img: &DynamicImage, save_path: &Path, quality: Option<f32>) -> Result<(), String> {
+fn save_webp(
+    img: &DynamicImage,
+    save_path: &Path,
+    quality: Option<f32>,
+    icc_profile: Option<&[u8]>,
+) -> Result<(), String> {
     let rgba_image = img.to_rgba8();
     let encoder = webp::Encoder::from_rgba(&rgba_image, rgba_image.width(), rgba_image.height());
     let memory_encoder = encoder.encode(quality.unwrap_or(75.0) as f32);
-    std::fs::write(save_path, &*memory_encoder)
-        .map_err(|e| format!("Failed to save WebP image: {}", e))
+    let bytes = match icc_profile {
+        Some(icc) => embed_webp_icc_profile(
+            &memory_encoder,
+            rgba_image.width(),
+            rgba_image.height(),
+            icc,
+        )?,
+        None => memory_encoder.to_vec(),
+    };
+    std::fs::write(save_path, bytes).map_err(|e| format!("Failed to save WebP image: {}", e))
 }
 
 /// Saves a `DynamicImage` to a JPEG file.
@@ -434,30 +1083,61 @@ fn save_webp(img: &DynamicImage, save_path: &Path, quality: Option<f32>) -> Resu
 /// * `img` - The image to save.
 /// * `save_path` - The destination path to save the image.
 /// * `quality` - Optional quality setting for the saved JPEG image (1.0-100.0). Defaults to 75.0 if not provided.
+/// * `icc_profile` - Optional ICC profile to embed.
 ///
 /// # Returns
 /// `Result<(), String>` - The result of the save operation. Returns an error string if the save operation fails.
-fn save_jpeg(img: &DynamicImage, save_path: &Path, quality: Option<f32>) -> Result<(), String> {
+fn save_jpeg(
+    img: &DynamicImage,
+    save_path: &Path,
+    quality: Option<f32>,
+    icc_profile: Option<&[u8]>,
+) -> Result<(), String> {
     let mut file = std::fs::File::create(save_path)
         .map_err(|e| format!("Failed to create JPEG file: {}", e))?;
     let quality_u8 = quality.map(|q| q.clamp(1.0, 100.0) as u8).unwrap_or(75);
-    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut file, quality_u8);
+    let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut file, quality_u8);
+    if let Some(icc) = icc_profile {
+        encoder
+            .set_icc_profile(icc.to_vec())
+            .map_err(|e| format!("Failed to embed color profile: {}", e))?;
+    }
     img.write_with_encoder(encoder)
         .map_err(|e| format!("Failed to save JPEG image: {}", e))
 }
 
+/// Saves a `DynamicImage` to a PNG file.
+///
+/// # Arguments
+///
+/// * `img` - The image to save.
+/// * `save_path` - The destination path to save the image.
+/// * `icc_profile` - Optional ICC profile to embed as an iCCP chunk.
+///
+/// # Returns
+/// `Result<(), String>` - The result of the save operation. Returns an error string if the save operation fails.
+fn save_png(
+    img: &DynamicImage,
+    save_path: &Path,
+    icc_profile: Option<&[u8]>,
+) -> Result<(), String> {
+    let file = File::create(save_path).map_err(|e| format!("Failed to create PNG file: {}", e))?;
+    let mut encoder = PngEncoder::new(BufWriter::new(file));
+    if let Some(icc) = icc_profile {
+        encoder
+            .set_icc_profile(icc.to_vec())
+            .map_err(|e| format!("Failed to embed color profile: {}", e))?;
+    }
+    img.write_with_encoder(encoder)
+        .map_err(|e| format!("Failed to save PNG image: {}", e))
+}
+
 /// Returns a list of image formats supported for saving.
 ///
 /// # Returns
 /// `Result<Vec<String>, String>` - A vector of supported image format strings (e.g., "png", "jpeg", "webp", "bmp").
 pub fn get_supported_image_formats() -> Result<Vec<String>, String> {
-    let formats = vec![
-        "png".to_string(),
-        "jpeg".to_string(),
-        "webp".to_string(),
-        "bmp".to_string(),
-    ];
-    Ok(formats)
+    Ok(export_format_names())
 }
 
 /// Detects C2PA (Content Authenticity Initiative) metadata in an image file.
@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use std::time::UNIX_EPOCH;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, UriSchemeContext, UriSchemeResponder};
+
+use crate::utils::archive::split_archive_path;
+use crate::utils::color_profile::load_convert_to_srgb;
+use crate::utils::decode_limits::load_decode_limits;
+use crate::utils::image_cache::ImageCache;
+use crate::utils::image_formats::{guess_image_format, FORMAT_HEADER_LEN};
+use crate::utils::image_processing::{
+    get_modified_time, render_archive_entry, render_rendition, render_svg_rendition,
+    render_tiff_page,
+};
+use crate::utils::thumbnails::{resolve_thumbnail_name, thumbnail_cache_dir};
+use crate::utils::tone_mapping::load_tone_map_settings;
+
+/// The name of the custom URI scheme used to serve image files to the webview.
+pub const IMAGE_PROTOCOL_SCHEME: &str = "blickfang";
+
+/// The number of files the image registry holds before the least recently used are dropped.
+const MAX_REGISTERED_IMAGES: usize = 4096;
+
+/// State struct mapping the numeric image handles handed out to the frontend to the files they refer to.
+/// Only files registered here can be fetched through the image protocol.
+///
+/// The registry holds at most `MAX_REGISTERED_IMAGES` files. Beyond that, the handles used least
+/// recently are dropped, and the frontend has to register their files again.
+#[derive(Default)]
+pub struct ImageRegistry {
+    entries: Mutex<RegistryEntries>,
+    next_id: AtomicU64,
+}
+
+/// The files of the image registry, with the last use of each handle.
+#[derive(Default)]
+struct RegistryEntries {
+    ids_by_path: HashMap<PathBuf, u64>,
+    paths_by_id: HashMap<u64, (PathBuf, u64)>,
+    clock: u64,
+}
+
+impl RegistryEntries {
+    /// Records a use of the given handle and returns its path.
+    fn touch(&mut self, id: u64) -> Option<PathBuf> {
+        self.clock += 1;
+        let (path, last_used) = self.paths_by_id.get_mut(&id)?;
+        *last_used = self.clock;
+        Some(path.clone())
+    }
+
+    /// Drops the least recently used quarter of the handles once the registry is full.
+    fn evict(&mut self) {
+        if self.paths_by_id.len() <= MAX_REGISTERED_IMAGES {
+            return;
+        }
+        let mut by_use: Vec<(u64, u64)> = self
+            .paths_by_id
+            .iter()
+            .map(|(id, (_, last_used))| (*last_used, *id))
+            .collect();
+        by_use.sort_unstable();
+        for (_, id) in by_use.into_iter().take(MAX_REGISTERED_IMAGES / 4) {
+            if let Some((path, _)) = self.paths_by_id.remove(&id) {
+                self.ids_by_path.remove(&path);
+            }
+        }
+    }
+}
+
+impl ImageRegistry {
+    /// Registers a file with the registry and returns its handle.
+    /// Registering the same path twice returns the same handle.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the image file.
+    ///
+    /// # Returns
+    /// `u64` - The handle under which the file is served.
+    pub fn register(&self, path: &Path) -> u64 {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(id) = entries.ids_by_path.get(path).copied() {
+            entries.touch(id);
+            return id;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        entries.clock += 1;
+        let clock = entries.clock;
+        entries.ids_by_path.insert(path.to_path_buf(), id);
+        entries.paths_by_id.insert(id, (path.to_path_buf(), clock));
+        entries.evict();
+        id
+    }
+
+    /// Looks up the file registered under the given handle.
+    ///
+    /// # Arguments
+    /// * `id` - The image handle.
+    ///
+    /// # Returns
+    /// `Option<PathBuf>` - The path of the file, or `None` if the handle is unknown or was dropped.
+    pub fn resolve(&self, id: u64) -> Option<PathBuf> {
+        self.entries.lock().unwrap().touch(id)
+    }
+}
+
+/// Registers the given file and builds the URL the webview can load it from.
+///
+/// The file's modification time is appended as a version parameter so the webview
+/// does not show a stale cached copy after the file has been rewritten.
+///
+/// # Arguments
+/// * `registry` - The image registry.
+/// * `path` - The path of the image file.
+///
+/// # Returns
+/// `String` - The URL of the image.
+pub fn register_image_url(registry: &ImageRegistry, path: &Path) -> String {
+    let id = registry.register(path);
+    format!(
+        "{}image/{}?v={}",
+        protocol_base_url(),
+        id,
+        file_version(path)
+    )
+}
+
+/// Builds the URL the webview can load a cached thumbnail from.
+///
+/// Thumbnails are addressed by their name within the thumbnail cache, so they need no entry
+/// in the image registry.
+///
+/// # Arguments
+/// * `thumbnail` - The path of the thumbnail in the thumbnail cache.
+///
+/// # Returns
+/// `Option<String>` - The URL of the thumbnail, or `None` if the path is no cached thumbnail.
+pub fn thumbnail_url(thumbnail: &Path) -> Option<String> {
+    let file_name = thumbnail.file_name()?.to_str()?;
+    let directory = thumbnail.parent()?.file_name()?.to_str()?;
+    Some(format!(
+        "{}thumbnail/{}/{}?v={}",
+        protocol_base_url(),
+        directory,
+        file_name,
+        file_version(thumbnail)
+    ))
+}
+
+/// Returns the modification time of a file in milliseconds, used as the version parameter of its URL.
+fn file_version(path: &Path) -> u128 {
+    get_modified_time(path)
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+/// Returns the base URL of the image protocol for the current platform.
+///
+/// Windows and Android webviews do not support custom schemes directly, so Tauri
+/// exposes them as `http://<scheme>.localhost/` there.
+fn protocol_base_url() -> String {
+    #[cfg(any(target_os = "windows", target_os = "android"))]
+    {
+        format!("http://{}.localhost/", IMAGE_PROTOCOL_SCHEME)
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "android")))]
+    {
+        format!("{}://localhost/", IMAGE_PROTOCOL_SCHEME)
+    }
+}
+
+/// Handles a request made to the image protocol.
+///
+/// The request is answered off the main thread. The body is served from the image cache
+/// if possible and read straight from disk otherwise, honouring a single `Range` header if present.
+/// Images stored in archives are read from the archive.
+/// Images the webview cannot display as they are, because of their format, their bit depth
+/// or their color profile, are served as a rendition. SVG images requested with an `s` query parameter are rasterized
+/// at that multiple of their intrinsic size, and multi-page TIFF files requested with a `p` query
+/// parameter are served as a rendition of that page. Cached thumbnails are served by their name in the
+/// thumbnail cache.
+///
+/// # Arguments
+/// * `ctx` - The URI scheme context.
+/// * `request` - The incoming request.
+/// * `responder` - The responder used to send the response.
+pub fn handle_image_protocol_request(
+    ctx: UriSchemeContext<'_, tauri::Wry>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app_handle = ctx.app_handle().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let response = build_image_response(&app_handle, &request).unwrap_or_else(|(status, e)| {
+            eprintln!("Image protocol request failed: {}", e);
+            error_response(status)
+        });
+        responder.respond(response);
+    });
+}
+
+/// Builds the response for an image protocol request.
+///
+/// # Arguments
+/// * `app_handle` - The Tauri application handle.
+/// * `request` - The incoming request.
+///
+/// # Returns
+/// `Result<Response<Vec<u8>>, (StatusCode, String)>` - The response, or the status code and message to fail with.
+fn build_image_response(
+    app_handle: &AppHandle,
+    request: &Request<Vec<u8>>,
+) -> Result<Response<Vec<u8>>, (StatusCode, String)> {
+    if let Some(name) = parse_thumbnail_name(request.uri().path()) {
+        let cache_dir = thumbnail_cache_dir(app_handle).map_err(|e| (StatusCode::NOT_FOUND, e))?;
+        let path = resolve_thumbnail_name(&cache_dir, name).ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Invalid thumbnail URL: {}", request.uri()),
+            )
+        })?;
+        return build_file_response(request, &path);
+    }
+
+    let id = parse_image_id(request.uri().path()).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Invalid image URL: {}", request.uri()),
+        )
+    })?;
+    let path = app_handle
+        .state::<ImageRegistry>()
+        .resolve(id)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Unknown image handle: {}", id),
+            )
+        })?;
+
+    // SVG images are rasterized again at the scale the viewer asks for when zooming in.
+    if let Some(scale) = parse_scale(request.uri().query()) {
+        let limits = load_decode_limits(app_handle);
+        let rendition = render_svg_rendition(&path, scale, &limits)
+            .map_err(|e| (StatusCode::UNSUPPORTED_MEDIA_TYPE, e))?;
+        return build_body_response(
+            request,
+            "image/png",
+            rendition.len() as u64,
+            |start, len| Ok(rendition[start as usize..(start + len) as usize].to_vec()),
+        );
+    }
+
+    let cache = app_handle.state::<ImageCache>();
+    if let Some(bytes) = cache.get_bytes(&path) {
+        let mime_type = sniff_mime_type(&bytes, &path);
+        return build_body_response(request, &mime_type, bytes.len() as u64, |start, len| {
+            Ok(bytes[start as usize..(start + len) as usize].to_vec())
+        });
+    }
+
+    let convert_to_srgb = load_convert_to_srgb(app_handle);
+    let source = match parse_page(request.uri().query()) {
+        Some(page) if page > 0 => Some(RenditionSource::Page(page)),
+        _ if split_archive_path(&path).is_some() => Some(RenditionSource::ArchiveEntry),
+        _ if cache.needs_rendition(&path, convert_to_srgb) => Some(RenditionSource::File),
+        _ => None,
+    };
+    if let Some(source) = source {
+        let rendition = cached_rendition(app_handle, &path, convert_to_srgb, source)?;
+        let mime_type = sniff_mime_type(&rendition, &path);
+        return build_body_response(request, &mime_type, rendition.len() as u64, |start, len| {
+            Ok(rendition[start as usize..(start + len) as usize].to_vec())
+        });
+    }
+
+    build_file_response(request, &path)
+}
+
+/// What the image protocol renders for an image that is not served as it is.
+#[derive(Clone, Copy)]
+enum RenditionSource {
+    /// A rendition of the whole file.
+    File,
+    /// The bytes of an image stored in an archive, rendered if needed.
+    ArchiveEntry,
+    /// A page of a multi-page TIFF file other than the first.
+    Page(u32),
+}
+
+/// Returns the rendition of an image, rendering it only if it is not in the image cache for the
+/// current settings yet.
+///
+/// # Arguments
+/// * `app_handle` - The Tauri application handle.
+/// * `path` - The path of the image.
+/// * `convert_to_srgb` - Whether images with an embedded color profile are converted to sRGB.
+/// * `source` - What is rendered.
+///
+/// # Returns
+/// `Result<Arc<Vec<u8>>, (StatusCode, String)>` - The bytes to serve, or the status code and message to fail with.
+fn cached_rendition(
+    app_handle: &AppHandle,
+    path: &Path,
+    convert_to_srgb: bool,
+    source: RenditionSource,
+) -> Result<Arc<Vec<u8>>, (StatusCode, String)> {
+    let cache = app_handle.state::<ImageCache>();
+    let tone_map = load_tone_map_settings(app_handle);
+    let page = match source {
+        RenditionSource::Page(page) => page,
+        _ => 0,
+    };
+    let settings = format!("{}/{}/{}", tone_map.cache_key(), convert_to_srgb, page);
+    if let Some(rendition) = cache.get_rendition(path, &settings) {
+        return Ok(rendition);
+    }
+
+    let modified = get_modified_time(path).map_err(|e| (StatusCode::NOT_FOUND, e))?;
+    let limits = load_decode_limits(app_handle);
+    let rendition = match source {
+        RenditionSource::File => render_rendition(path, &tone_map, convert_to_srgb, &limits)
+            .map_err(|e| (StatusCode::UNSUPPORTED_MEDIA_TYPE, e))?,
+        RenditionSource::ArchiveEntry => {
+            render_archive_entry(path, &tone_map, convert_to_srgb, &limits)
+                .map_err(|e| (StatusCode::NOT_FOUND, e))?
+        }
+        RenditionSource::Page(page) => {
+            render_tiff_page(path, page, &tone_map, convert_to_srgb, &limits)
+                .map_err(|e| (StatusCode::NOT_FOUND, e))?
+        }
+    };
+    Ok(cache.insert_rendition(path, settings, rendition, modified))
+}
+
+/// Builds the response for a file served as it is, reading only the requested range of it.
+///
+/// # Arguments
+/// * `request` - The incoming request.
+/// * `path` - The path of the file.
+///
+/// # Returns
+/// `Result<Response<Vec<u8>>, (StatusCode, String)>` - The response, or the status code and message to fail with.
+fn build_file_response(
+    request: &Request<Vec<u8>>,
+    path: &Path,
+) -> Result<Response<Vec<u8>>, (StatusCode, String)> {
+    let mut file = File::open(path).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Failed to open file '{}': {}", path.display(), e),
+        )
+    })?;
+    let file_size = file
+        .metadata()
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get file metadata: {}", e),
+            )
+        })?
+        .len();
+    let header = read_file_range(&mut file, 0, FORMAT_HEADER_LEN)?;
+    let mime_type = sniff_mime_type(&header, path);
+
+    build_body_response(request, &mime_type, file_size, |start, len| {
+        read_file_range(&mut file, start, len)
+    })
+}
+
+/// Builds a response for a body of the given size, reading only the requested range of it.
+///
+/// # Arguments
+/// * `request` - The incoming request.
+/// * `mime_type` - The MIME type of the body.
+/// * `total_size` - The size of the complete body in bytes.
+/// * `read_range` - Reads `len` bytes of the body starting at `start`.
+///
+/// # Returns
+/// `Result<Response<Vec<u8>>, (StatusCode, String)>` - The response, or the status code and message to fail with.
+fn build_body_response<F>(
+    request: &Request<Vec<u8>>,
+    mime_type: &str,
+    total_size: u64,
+    mut read_range: F,
+) -> Result<Response<Vec<u8>>, (StatusCode, String)>
+where
+    F: FnMut(u64, u64) -> Result<Vec<u8>, (StatusCode, String)>,
+{
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| parse_range_header(v, total_size));
+
+    let builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime_type)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+
+    let response = match range {
+        Some(Some((start, end))) => {
+            let body = read_range(start, end - start + 1)?;
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, total_size),
+                )
+                .header(header::CONTENT_LENGTH, body.len())
+                .body(body)
+        }
+        Some(None) => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", total_size))
+            .body(Vec::new()),
+        None => {
+            let body = read_range(0, total_size)?;
+            builder
+                .status(StatusCode::OK)
+                .header(header::CONTENT_LENGTH, body.len())
+                .body(body)
+        }
+    };
+
+    response.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to build response: {}", e),
+        )
+    })
+}
+
+/// Extracts the image handle from a request path of the form `/image/<id>`.
+fn parse_image_id(path: &str) -> Option<u64> {
+    path.trim_start_matches('/')
+        .strip_prefix("image/")
+        .and_then(|id| id.parse().ok())
+}
+
+/// Extracts the thumbnail name from a request path of the form `/thumbnail/<directory>/<file>`.
+fn parse_thumbnail_name(path: &str) -> Option<&str> {
+    path.trim_start_matches('/').strip_prefix("thumbnail/")
+}
+
+/// Extracts the rasterization scale from the `s` parameter of a request query.
+fn parse_scale(query: Option<&str>) -> Option<f32> {
+    query?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("s="))
+        .and_then(|scale| scale.parse().ok())
+        .filter(|scale: &f32| scale.is_finite() && *scale > 0.0)
+}
+
+/// Extracts the page index from the `p` parameter of a request query.
+fn parse_page(query: Option<&str>) -> Option<u32> {
+    query?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("p="))
+        .and_then(|page| page.parse().ok())
+}
+
+/// Parses a `Range` header value against the size of the requested file.
+///
+/// Only single byte ranges are supported (`bytes=start-end`, `bytes=start-` and `bytes=-suffix`).
+///
+/// # Arguments
+/// * `value` - The value of the `Range` header.
+/// * `file_size` - The size of the requested file in bytes.
+///
+/// # Returns
+/// `Option<(u64, u64)>` - The inclusive start and end offsets, or `None` if the range cannot be satisfied.
+fn parse_range_header(value: &str, file_size: u64) -> Option<(u64, u64)> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') || file_size == 0 {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let last = file_size - 1;
+
+    match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 {
+                return None;
+            }
+            Some((file_size.saturating_sub(suffix), last))
+        }
+        (start, "") => {
+            let start: u64 = start.parse().ok()?;
+            (start <= last).then_some((start, last))
+        }
+        (start, end) => {
+            let start: u64 = start.parse().ok()?;
+            let end: u64 = end.parse::<u64>().ok()?.min(last);
+            (start <= end).then_some((start, end))
+        }
+    }
+}
+
+/// Reads `len` bytes starting at `start` from the given file.
+fn read_file_range(file: &mut File, start: u64, len: u64) -> Result<Vec<u8>, (StatusCode, String)> {
+    let mut body = Vec::with_capacity(len as usize);
+    file.seek(SeekFrom::Start(start))
+        .and_then(|_| file.take(len).read_to_end(&mut body))
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to read file: {}", e),
+            )
+        })?;
+    Ok(body)
+}
+
+/// Determines the MIME type of a file from its leading bytes, falling back to its extension.
+fn sniff_mime_type(header: &[u8], path: &Path) -> String {
+    guess_image_format(path, header).0
+}
+
+/// Builds an empty response with the given status code.
+fn error_response(status: StatusCode) -> Response<Vec<u8>> {
+    let mut response = Response::new(Vec::new());
+    *response.status_mut() = status;
+    response
+}
@@ -0,0 +1,85 @@
+use image::DynamicImage;
+
+use crate::models::image::{ChannelStatistics, ImageStatistics};
+
+/// Computes the histograms and statistics of the red, green, blue and luma channels of an image.
+///
+/// The image is expected in its 8-bit display form, so high dynamic range images should be
+/// tone mapped first. Every pixel counts, regardless of its opacity.
+///
+/// # Arguments
+/// * `img` - The image to analyze.
+///
+/// # Returns
+/// `ImageStatistics` - The statistics of the image.
+pub fn compute_image_statistics(img: &DynamicImage) -> ImageStatistics {
+    let has_alpha = img.color().has_alpha();
+    let rgba = img.to_rgba8();
+    let mut histograms = [[0u64; 256]; 4];
+    let mut alpha_used = false;
+
+    for pixel in rgba.pixels() {
+        let [r, g, b, a] = pixel.0;
+        histograms[0][r as usize] += 1;
+        histograms[1][g as usize] += 1;
+        histograms[2][b as usize] += 1;
+        histograms[3][luma(r, g, b) as usize] += 1;
+        alpha_used |= a < u8::MAX;
+    }
+
+    let [red, green, blue, luma] = histograms.map(|histogram| channel_statistics(&histogram));
+    ImageStatistics {
+        red,
+        green,
+        blue,
+        luma,
+        pixel_count: rgba.width() as u64 * rgba.height() as u64,
+        has_alpha,
+        alpha_used,
+    }
+}
+
+/// Returns the Rec. 709 luma of an 8-bit RGB value.
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32).round() as u8
+}
+
+/// Derives the statistics of a channel from its histogram.
+fn channel_statistics(histogram: &[u64; 256]) -> ChannelStatistics {
+    let count: u64 = histogram.iter().sum();
+    let occupied = || histogram.iter().enumerate().filter(|(_, &n)| n > 0);
+    let min = occupied().next().map_or(0, |(value, _)| value as u8);
+    let max = occupied().last().map_or(0, |(value, _)| value as u8);
+
+    let (mean, std_dev) = if count == 0 {
+        (0.0, 0.0)
+    } else {
+        let total = count as f64;
+        let mean = occupied()
+            .map(|(value, &n)| value as f64 * n as f64)
+            .sum::<f64>()
+            / total;
+        let variance = occupied()
+            .map(|(value, &n)| (value as f64 - mean).powi(2) * n as f64)
+            .sum::<f64>()
+            / total;
+        (mean, variance.sqrt())
+    };
+    let percentage = |n: u64| {
+        if count == 0 {
+            0.0
+        } else {
+            n as f64 * 100.0 / count as f64
+        }
+    };
+
+    ChannelStatistics {
+        histogram: histogram.to_vec(),
+        min,
+        max,
+        mean,
+        std_dev,
+        clipped_shadows: percentage(histogram[0]),
+        clipped_highlights: percentage(histogram[255]),
+    }
+}
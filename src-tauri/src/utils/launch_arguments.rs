@@ -0,0 +1,235 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::models::launch::LaunchRequest;
+use crate::utils::archive::{archive_kind, split_archive_path};
+use crate::utils::directory_index::{
+    get_indexed_directory_files, get_indexed_files_in_directory, list_index_files, set_playlist,
+};
+use crate::utils::file_sorting::load_sort_order;
+use crate::utils::folder_browsing::load_folder_browsing;
+use crate::utils::image_formats::sniff_file_format;
+
+/// The arguments the application was launched with, split into paths and flags.
+#[derive(Default, Debug)]
+pub struct LaunchArguments {
+    /// The files, folders and archives to open, made absolute.
+    pub paths: Vec<PathBuf>,
+    /// Whether `--fullscreen` was given.
+    pub fullscreen: bool,
+    /// Whether `--zen` was given.
+    pub zen: bool,
+    /// The interval given with `--slideshow=<secs>`.
+    pub slideshow: Option<f64>,
+    /// The image given with `--start-at=<file>`, made absolute.
+    pub start_at: Option<PathBuf>,
+    /// Messages about the arguments that were dropped.
+    pub errors: Vec<String>,
+}
+
+/// A path given at launch, by what it opens.
+enum LaunchTarget {
+    /// A single image file or archive entry.
+    Image(String),
+    /// A folder or archive whose images are opened.
+    Folder(PathBuf),
+}
+
+/// Splits the launch arguments into paths and the flags `--fullscreen`, `--zen`,
+/// `--slideshow=<secs>` and `--start-at=<file>`. Arguments after `--` are always paths.
+/// Relative paths are resolved against the working directory. Unknown flags and invalid values
+/// are dropped and reported in `errors`.
+///
+/// # Arguments
+/// * `args` - The launch arguments, without the program name.
+///
+/// # Returns
+/// `LaunchArguments` - The parsed arguments.
+pub fn parse_launch_arguments(args: Vec<String>) -> LaunchArguments {
+    let current_dir = std::env::current_dir().ok();
+    let absolute = |path: &str| match &current_dir {
+        Some(current_dir) => current_dir.join(path),
+        None => PathBuf::from(path),
+    };
+    let mut arguments = LaunchArguments::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            arguments
+                .paths
+                .extend(args.by_ref().map(|path| absolute(&path)));
+            break;
+        }
+        if !arg.starts_with('-') {
+            arguments.paths.push(absolute(&arg));
+            continue;
+        }
+        match arg.split_once('=') {
+            None if arg == "--fullscreen" => arguments.fullscreen = true,
+            None if arg == "--zen" => arguments.zen = true,
+            Some(("--slideshow", seconds)) => match seconds.parse::<f64>() {
+                Ok(seconds) if seconds.is_finite() && seconds > 0.0 => {
+                    arguments.slideshow = Some(seconds)
+                }
+                _ => arguments
+                    .errors
+                    .push(format!("Invalid slideshow interval '{}'", seconds)),
+            },
+            Some(("--start-at", file)) => arguments.start_at = Some(absolute(file)),
+            // Process serial numbers passed by older versions of macOS.
+            _ if arg.starts_with("-psn_") => {}
+            _ => arguments.errors.push(format!("Unknown option '{}'", arg)),
+        }
+    }
+    arguments
+}
+
+/// Decides which image to show for the parsed launch arguments.
+///
+/// A single image opens as usual, browsing its folder. A single folder or archive opens its
+/// first image in the configured sort order. Several paths form a playlist of the given images
+/// and the images of the given folders, in the order they were given, and navigation stays within
+/// it. `--start-at` picks the image to show first among these. Paths that are no images, folders
+/// or archives are dropped and reported in `errors`.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `arguments` - The parsed launch arguments.
+///
+/// # Returns
+/// `(Option<LaunchRequest>, Vec<String>)` - What to show, or `None` if there is nothing to open,
+/// and the messages about dropped arguments.
+pub async fn resolve_launch_request(
+    app: &AppHandle,
+    arguments: LaunchArguments,
+) -> (Option<LaunchRequest>, Vec<String>) {
+    let LaunchArguments {
+        mut paths,
+        fullscreen,
+        zen,
+        slideshow,
+        start_at,
+        mut errors,
+    } = arguments;
+    let start_at = start_at.map(|path| path.to_string_lossy().to_string());
+    if paths.is_empty() {
+        paths.extend(start_at.as_ref().map(PathBuf::from));
+    }
+
+    let targets: Vec<LaunchTarget> = paths
+        .iter()
+        .filter_map(|path| classify_path(path).map_err(|e| errors.push(e)).ok())
+        .collect();
+
+    let path = match targets.as_slice() {
+        [] => None,
+        [LaunchTarget::Image(path)] => match &start_at {
+            Some(_) => match get_indexed_directory_files(app, path).await {
+                Ok(files) => pick_start(&files, start_at.as_deref(), &mut errors),
+                Err(e) => {
+                    errors.push(e);
+                    Some(path.clone())
+                }
+            },
+            None => Some(path.clone()),
+        },
+        [LaunchTarget::Folder(directory)] => {
+            match get_indexed_files_in_directory(app, directory).await {
+                Ok(files) if files.is_empty() => {
+                    errors.push(format!("No images found in '{}'", directory.display()));
+                    None
+                }
+                Ok(files) => pick_start(&files, start_at.as_deref(), &mut errors),
+                Err(e) => {
+                    errors.push(e);
+                    None
+                }
+            }
+        }
+        targets => {
+            let playlist = build_playlist(app, targets, &mut errors).await;
+            let path = pick_start(&playlist, start_at.as_deref(), &mut errors);
+            if !playlist.is_empty() {
+                set_playlist(app, playlist);
+            }
+            path
+        }
+    };
+
+    let request = path.map(|path| LaunchRequest {
+        path,
+        fullscreen,
+        zen,
+        slideshow,
+    });
+    (request, errors)
+}
+
+/// Tells what a launch path opens, or why it is dropped.
+fn classify_path(path: &Path) -> Result<LaunchTarget, String> {
+    if path.is_dir() || archive_kind(path).is_some() {
+        return Ok(LaunchTarget::Folder(path.to_path_buf()));
+    }
+    let path_str = path.to_string_lossy().to_string();
+    if path.is_file() {
+        return match sniff_file_format(path) {
+            Some(_) => Ok(LaunchTarget::Image(path_str)),
+            None => Err(format!("Not a supported image: '{}'", path.display())),
+        };
+    }
+    if split_archive_path(path).is_some() {
+        return Ok(LaunchTarget::Image(path_str));
+    }
+    Err(format!("File not found: '{}'", path.display()))
+}
+
+/// Lists the images of a playlist: the given images and the images of the given folders,
+/// in the order they were given, without duplicates.
+async fn build_playlist(
+    app: &AppHandle,
+    targets: &[LaunchTarget],
+    errors: &mut Vec<String>,
+) -> Vec<String> {
+    let browsing = load_folder_browsing(app);
+    let order = load_sort_order(app);
+    let mut playlist: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+
+    for target in targets {
+        let files = match target {
+            LaunchTarget::Image(path) => vec![path.clone()],
+            LaunchTarget::Folder(directory) => {
+                match list_index_files(directory, browsing, order).await {
+                    Ok(files) => files,
+                    Err(e) => {
+                        errors.push(e);
+                        continue;
+                    }
+                }
+            }
+        };
+        for file in files {
+            if seen.insert(file.clone()) {
+                playlist.push(file);
+            }
+        }
+    }
+    playlist
+}
+
+/// Returns the image given with `--start-at` if it is among `files`, the first file otherwise.
+fn pick_start(
+    files: &[String],
+    start_at: Option<&str>,
+    errors: &mut Vec<String>,
+) -> Option<String> {
+    if let Some(start_at) = start_at {
+        if let Some(file) = files.iter().find(|f| Path::new(f) == Path::new(start_at)) {
+            return Some(file.clone());
+        }
+        errors.push(format!("'{}' is not among the opened images", start_at));
+    }
+    files.first().cloned()
+}
@@ -1,8 +1,34 @@
+pub mod animated_export;
+pub mod animation;
+pub mod archive;
+pub mod color_profile;
 pub mod config_utils;
+pub mod control_socket;
+pub mod decode_limits;
 pub mod dialog_utils;
+pub mod directory_index;
+pub mod duplicate_finder;
+pub mod file_sorting;
 pub mod file_system;
+pub mod folder_browsing;
+#[cfg(feature = "heif")]
+pub mod heif_decoder;
+pub mod image_cache;
+pub mod image_compare;
+pub mod image_formats;
 pub mod image_processing;
+pub mod image_protocol;
+pub mod image_statistics;
+pub mod launch_arguments;
 pub mod os_integration_linux;
 pub mod os_specific_setup;
+pub mod perceptual_hash;
+pub mod pixel_inspector;
+pub mod raw_preview;
+pub mod single_instance;
 pub mod startup_handler;
+pub mod svg_renderer;
+pub mod thumbnails;
+pub mod tiff_utils;
+pub mod tone_mapping;
 pub mod window_utils;
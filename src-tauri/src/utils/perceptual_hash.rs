@@ -0,0 +1,103 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage};
+
+/// The algorithm used to compute a perceptual hash.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HashAlgorithm {
+    /// Compares every pixel of an 8x8 thumbnail with the mean brightness.
+    Average,
+    /// Compares neighbouring pixels of a 9x8 thumbnail, capturing the brightness gradient.
+    Difference,
+    /// Compares the low frequencies of the discrete cosine transform of a 32x32 thumbnail with their median.
+    Perceptual,
+}
+
+impl HashAlgorithm {
+    /// Parses the name of a hash algorithm ("ahash", "dhash" or "phash").
+    /// Unknown names fall back to the perceptual hash.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the algorithm.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "ahash" => HashAlgorithm::Average,
+            "dhash" => HashAlgorithm::Difference,
+            _ => HashAlgorithm::Perceptual,
+        }
+    }
+}
+
+/// Computes a 64-bit perceptual hash of an image. Images that look alike have hashes
+/// that differ in few bits, regardless of their size, format or compression.
+///
+/// # Arguments
+/// * `img` - The image to hash.
+/// * `algorithm` - The hash algorithm to use.
+///
+/// # Returns
+/// `u64` - The hash of the image.
+pub fn perceptual_hash(img: &DynamicImage, algorithm: HashAlgorithm) -> u64 {
+    match algorithm {
+        HashAlgorithm::Average => average_hash(&grayscale_thumbnail(img, 8, 8)),
+        HashAlgorithm::Difference => difference_hash(&grayscale_thumbnail(img, 9, 8)),
+        HashAlgorithm::Perceptual => dct_hash(&grayscale_thumbnail(img, 32, 32)),
+    }
+}
+
+/// Returns the number of bits two hashes differ in.
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Scales an image down to the given size, ignoring its aspect ratio, and converts it to grayscale.
+fn grayscale_thumbnail(img: &DynamicImage, width: u32, height: u32) -> GrayImage {
+    img.resize_exact(width, height, FilterType::Triangle)
+        .to_luma8()
+}
+
+/// Sets one bit per pixel of an 8x8 thumbnail that is brighter than the mean.
+fn average_hash(thumbnail: &GrayImage) -> u64 {
+    let mean = thumbnail.pixels().map(|p| p.0[0] as u32).sum::<u32>() / 64;
+    to_bits(thumbnail.pixels().map(|p| p.0[0] as u32 > mean))
+}
+
+/// Sets one bit per pixel of a 9x8 thumbnail that is brighter than its right neighbour.
+fn difference_hash(thumbnail: &GrayImage) -> u64 {
+    to_bits((0..8).flat_map(|y| {
+        (0..8).map(move |x| thumbnail.get_pixel(x, y).0[0] > thumbnail.get_pixel(x + 1, y).0[0])
+    }))
+}
+
+/// Sets one bit per low-frequency DCT coefficient of a 32x32 thumbnail that is above the median.
+/// The DC coefficient, which only reflects the mean brightness, is left out of the median.
+fn dct_hash(thumbnail: &GrayImage) -> u64 {
+    const SIZE: usize = 32;
+    let pixels: Vec<f64> = thumbnail.pixels().map(|p| p.0[0] as f64).collect();
+    let cosines: Vec<f64> = (0..8 * SIZE)
+        .map(|i| {
+            let (u, x) = (i / SIZE, i % SIZE);
+            (std::f64::consts::PI * u as f64 * (2 * x + 1) as f64 / (2 * SIZE) as f64).cos()
+        })
+        .collect();
+
+    let mut coefficients = [0f64; 64];
+    for (i, coefficient) in coefficients.iter_mut().enumerate() {
+        let (v, u) = (i / 8, i % 8);
+        *coefficient = (0..SIZE * SIZE)
+            .map(|p| {
+                let (y, x) = (p / SIZE, p % SIZE);
+                pixels[p] * cosines[u * SIZE + x] * cosines[v * SIZE + y]
+            })
+            .sum();
+    }
+
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    to_bits(coefficients.iter().map(|&c| c > median))
+}
+
+/// Packs up to 64 booleans into a hash, the first one in the most significant bit.
+fn to_bits(bits: impl Iterator<Item = bool>) -> u64 {
+    bits.take(64).fold(0, |hash, bit| (hash << 1) | bit as u64)
+}
@@ -0,0 +1,220 @@
+use image::DynamicImage;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::models::image::PixelInfo;
+use crate::utils::tone_mapping::{srgb_to_linear, tone_map_color, ToneMapSettings};
+
+/// State struct holding the image last inspected, decoded and turned upright, so moving the
+/// pointer over it does not decode it again.
+#[derive(Default)]
+pub struct PixelInspectorCache {
+    image: Mutex<Option<(PathBuf, SystemTime, Arc<DynamicImage>)>>,
+}
+
+impl PixelInspectorCache {
+    /// Returns the image at the given path, decoding it with `decode` unless it is the image
+    /// inspected last and has not changed since.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the image file.
+    /// * `modified` - The modification time of the file.
+    /// * `decode` - Decodes the image and turns it upright.
+    ///
+    /// # Returns
+    /// `Result<Arc<DynamicImage>, String>` - The upright image, or the error of `decode`.
+    pub fn get_or_decode(
+        &self,
+        path: &Path,
+        modified: SystemTime,
+        decode: impl FnOnce() -> Result<DynamicImage, String>,
+    ) -> Result<Arc<DynamicImage>, String> {
+        if let Some((cached_path, cached_modified, img)) = &*self.image.lock().unwrap() {
+            if cached_path == path && *cached_modified == modified {
+                return Ok(img.clone());
+            }
+        }
+        let img = Arc::new(decode()?);
+        *self.image.lock().unwrap() = Some((path.to_path_buf(), modified, img.clone()));
+        Ok(img)
+    }
+}
+
+/// Maps the coordinates of a pixel in an image displayed with a user rotation back to the
+/// unrotated image, so the image itself does not have to be rotated.
+///
+/// # Arguments
+/// * `x` - The column of the pixel as displayed.
+/// * `y` - The row of the pixel as displayed.
+/// * `rotation` - The rotation angle in degrees (0, 90, 180, 270) the image is displayed with.
+/// * `width` - The width of the unrotated image.
+/// * `height` - The height of the unrotated image.
+///
+/// # Returns
+/// `Result<(u32, u32), String>` - The column and row in the unrotated image, or an error string
+/// if the pixel lies outside the displayed image.
+pub fn unrotate_coordinates(
+    x: u32,
+    y: u32,
+    rotation: i32,
+    width: u32,
+    height: u32,
+) -> Result<(u32, u32), String> {
+    let (displayed_width, displayed_height) = match rotation {
+        90 | 270 => (height, width),
+        _ => (width, height),
+    };
+    if x >= displayed_width || y >= displayed_height {
+        return Err(format!(
+            "Pixel {},{} lies outside the {}x{} image",
+            x, y, displayed_width, displayed_height
+        ));
+    }
+    Ok(match rotation {
+        90 => (y, height - 1 - x),
+        180 => (width - 1 - x, height - 1 - y),
+        270 => (width - 1 - y, x),
+        _ => (x, y),
+    })
+}
+
+/// Reads the value of a pixel, or the average over a square area centred on it.
+///
+/// Areas reaching beyond the image are cut off at its edges.
+///
+/// # Arguments
+/// The raw values are read at the bit depth of the source. The hex, RGB, HSL, HSV and Lab values
+/// describe the color as it is displayed, so floating point images are tone mapped first.
+///
+/// # Arguments
+/// * `img` - The decoded image.
+/// * `x` - The column of the pixel.
+/// * `y` - The row of the pixel.
+/// * `area` - The side length of the area to average over. 0 and 1 read the single pixel.
+/// * `tone_map` - The settings floating point images are displayed with.
+///
+/// # Returns
+/// `Result<PixelInfo, String>` - The pixel value in several color spaces, or an error string
+/// if the coordinates lie outside the image.
+pub fn inspect_pixel(
+    img: &DynamicImage,
+    x: u32,
+    y: u32,
+    area: u32,
+    tone_map: &ToneMapSettings,
+) -> Result<PixelInfo, String> {
+    if x >= img.width() || y >= img.height() {
+        return Err(format!(
+            "Pixel {},{} lies outside the {}x{} image",
+            x,
+            y,
+            img.width(),
+            img.height()
+        ));
+    }
+
+    let area = area.max(1);
+    let radius = area / 2;
+    let left = x.saturating_sub(radius);
+    let top = y.saturating_sub(radius);
+    let right = x.saturating_add(area - radius).min(img.width());
+    let bottom = y.saturating_add(area - radius).min(img.height());
+    let region = img
+        .crop_imm(left, top, right - left, bottom - top)
+        .to_rgba32f();
+
+    let mut sum = [0f64; 4];
+    for pixel in region.pixels() {
+        for (total, value) in sum.iter_mut().zip(pixel.0) {
+            *total += value as f64;
+        }
+    }
+    let count = region.pixels().len().max(1) as f64;
+    let normalized = sum.map(|total| total / count);
+
+    let color = img.color();
+    let bit_depth = (color.bytes_per_pixel() / color.channel_count()) * 8;
+    let scale = match bit_depth {
+        8 => 255.0,
+        16 => 65535.0,
+        _ => 1.0,
+    };
+
+    let [r, g, b, a] = match img {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            let linear = [normalized[0], normalized[1], normalized[2]].map(|value| value as f32);
+            let [r, g, b] = tone_map_color(linear, tone_map).map(|value| value as f64);
+            [r, g, b, normalized[3].clamp(0.0, 1.0)]
+        }
+        _ => normalized.map(|value| value.clamp(0.0, 1.0)),
+    };
+    let rgb = [r, g, b].map(|value| (value * 255.0).round() as u8);
+    Ok(PixelInfo {
+        bit_depth,
+        raw: normalized.map(|value| value * scale),
+        hex: format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]),
+        rgb,
+        alpha: a,
+        hsl: to_hsl([r, g, b]),
+        hsv: to_hsv([r, g, b]),
+        lab: to_lab([r, g, b]),
+        area,
+    })
+}
+
+/// Returns the hue in degrees, and the chroma, the largest and the smallest component of an RGB color.
+fn hue_and_range([r, g, b]: [f64; 3]) -> (f64, f64, f64, f64) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    let hue = if chroma == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+    (hue, chroma, max, min)
+}
+
+/// Converts an sRGB color with components from 0 to 1 to HSL.
+fn to_hsl(rgb: [f64; 3]) -> [f64; 3] {
+    let (hue, chroma, max, min) = hue_and_range(rgb);
+    let lightness = (max + min) / 2.0;
+    let saturation = if chroma == 0.0 {
+        0.0
+    } else {
+        chroma / (1.0 - (2.0 * lightness - 1.0).abs())
+    };
+    [hue, saturation * 100.0, lightness * 100.0]
+}
+
+/// Converts an sRGB color with components from 0 to 1 to HSV.
+fn to_hsv(rgb: [f64; 3]) -> [f64; 3] {
+    let (hue, chroma, max, _) = hue_and_range(rgb);
+    let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+    [hue, saturation * 100.0, max * 100.0]
+}
+
+/// Converts an sRGB color with components from 0 to 1 to CIE L*a*b* under the D65 white point.
+fn to_lab(rgb: [f64; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(|value| srgb_to_linear(value as f32) as f64);
+    let x = 0.4124564 * r + 0.3575761 * g + 0.1804375 * b;
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    let z = 0.0193339 * r + 0.1191920 * g + 0.9503041 * b;
+
+    let f = |t: f64| {
+        const EPSILON: f64 = 216.0 / 24389.0;
+        const KAPPA: f64 = 24389.0 / 27.0;
+        if t > EPSILON {
+            t.cbrt()
+        } else {
+            (KAPPA * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x / 0.95047), f(y), f(z / 1.08883));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
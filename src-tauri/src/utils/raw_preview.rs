@@ -0,0 +1,343 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::utils::tiff_utils::{Ifd, TiffReader};
+
+/// The magic bytes at the start of Fujifilm RAF files.
+pub const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-RAW";
+
+const TAG_IMAGE_WIDTH: u16 = 256;
+const TAG_IMAGE_LENGTH: u16 = 257;
+const TAG_COMPRESSION: u16 = 259;
+const TAG_PHOTOMETRIC_INTERPRETATION: u16 = 262;
+const TAG_STRIP_OFFSETS: u16 = 273;
+const TAG_STRIP_BYTE_COUNTS: u16 = 279;
+const TAG_SUB_IFDS: u16 = 330;
+const TAG_JPEG_INTERCHANGE_FORMAT: u16 = 513;
+const TAG_JPEG_INTERCHANGE_FORMAT_LENGTH: u16 = 514;
+
+/// Photometric interpretations of unprocessed sensor data (CFA and LinearRaw).
+const RAW_PHOTOMETRIC_INTERPRETATIONS: [u64; 2] = [32803, 34892];
+
+/// The tag of the RAF record holding the full sensor size.
+const RAF_TAG_RAW_IMAGE_FULL_SIZE: u16 = 0x100;
+
+/// The maximum depth of nested SubIFDs followed, guarding against malformed files.
+const MAX_SUB_IFD_DEPTH: usize = 2;
+
+/// The largest embedded preview accepted, guarding against malformed files.
+const MAX_PREVIEW_LEN: u64 = 256 * 1024 * 1024;
+
+/// The location and size of the embedded JPEG preview of a camera RAW file.
+pub struct RawPreview {
+    /// The offset of the JPEG data from the start of the file.
+    pub offset: u64,
+    /// The length of the JPEG data in bytes.
+    pub length: u64,
+    pub width: u32,
+    pub height: u32,
+    /// The dimensions of the sensor data, if they could be determined.
+    pub sensor_dimensions: Option<(u32, u32)>,
+}
+
+/// The frame type of a JPEG stream, as declared by its start-of-frame marker.
+struct JpegFrame {
+    width: u32,
+    height: u32,
+    components: u32,
+    /// Whether the frame uses baseline, extended or progressive Huffman coding,
+    /// the only kinds the `image` crate can decode.
+    decodable: bool,
+    /// Whether the frame uses lossless coding, as the sensor data of CR2 files does.
+    lossless: bool,
+}
+
+/// Locates the largest embedded JPEG preview of a camera RAW file and determines its sensor size.
+///
+/// TIFF-based files (CR2, NEF, ARW and DNG) are searched by walking their image file directories
+/// and SubIFDs. RAF files store the offset of their preview in the file header.
+///
+/// # Arguments
+/// * `reader` - A reader over the RAW file.
+///
+/// # Returns
+/// `Result<RawPreview, String>` - The preview, or an error string if the file has no usable preview.
+pub fn find_raw_preview<R: Read + Seek>(mut reader: R) -> Result<RawPreview, String> {
+    let mut magic = [0u8; 15];
+    reader
+        .seek(SeekFrom::Start(0))
+        .and_then(|_| reader.read_exact(&mut magic))
+        .map_err(|e| format!("Failed to read RAW header: {}", e))?;
+
+    if magic == RAF_MAGIC {
+        find_raf_preview(reader)
+    } else {
+        find_tiff_preview(reader)
+    }
+}
+
+/// Reads the largest embedded JPEG preview of a camera RAW file.
+///
+/// # Arguments
+/// * `reader` - A reader over the RAW file.
+///
+/// # Returns
+/// `Result<Vec<u8>, String>` - The JPEG data, or an error string if the file has no usable preview.
+pub fn read_raw_preview<R: Read + Seek>(mut reader: R) -> Result<Vec<u8>, String> {
+    let preview = find_raw_preview(&mut reader)?;
+    // The length comes from the file, so the buffer only grows with what is actually read.
+    let mut jpeg = Vec::new();
+    reader
+        .seek(SeekFrom::Start(preview.offset))
+        .and_then(|_| reader.take(preview.length).read_to_end(&mut jpeg))
+        .map_err(|e| format!("Failed to read RAW preview: {}", e))?;
+    Ok(jpeg)
+}
+
+/// Searches the directories of a TIFF-based RAW file for its largest JPEG preview and its sensor data.
+fn find_tiff_preview<R: Read + Seek>(mut reader: R) -> Result<RawPreview, String> {
+    let ifds = {
+        let mut tiff = TiffReader::new(&mut reader)?;
+        let mut ifds = tiff.read_ifd_chain()?;
+        let mut pending: Vec<(u64, usize)> = ifds
+            .iter()
+            .flat_map(|ifd| sub_ifd_offsets(&mut tiff, ifd))
+            .map(|offset| (offset, 1))
+            .collect();
+        while let Some((offset, depth)) = pending.pop() {
+            let Ok(ifd) = tiff.read_ifd(offset) else {
+                continue;
+            };
+            if depth < MAX_SUB_IFD_DEPTH {
+                let nested = sub_ifd_offsets(&mut tiff, &ifd);
+                pending.extend(nested.into_iter().map(|offset| (offset, depth + 1)));
+            }
+            ifds.push(ifd);
+        }
+        // Resolve the tag values needed below while the TIFF reader is still alive.
+        ifds.iter()
+            .map(|ifd| IfdSummary::read(&mut tiff, ifd))
+            .collect::<Vec<_>>()
+    };
+
+    let mut preview: Option<RawPreview> = None;
+    let mut sensor_dimensions: Option<(u32, u32)> = None;
+    let mut consider_sensor = |dimensions: (u32, u32)| {
+        let area = |(w, h): (u32, u32)| w as u64 * h as u64;
+        if sensor_dimensions.is_none_or(|current| area(dimensions) > area(current)) {
+            sensor_dimensions = Some(dimensions);
+        }
+    };
+
+    for summary in &ifds {
+        if summary.is_raw {
+            if let Some(dimensions) = summary.dimensions {
+                consider_sensor(dimensions);
+            }
+        }
+        for &(offset, length) in &summary.jpeg_candidates {
+            if length == 0 || length > MAX_PREVIEW_LEN {
+                continue;
+            }
+            let Ok(frame) = read_jpeg_frame(&mut reader, offset) else {
+                continue;
+            };
+            if frame.lossless {
+                // CR2 sensor data is split into slices side by side, each holding all components.
+                consider_sensor((frame.width * frame.components, frame.height));
+                continue;
+            }
+            if !frame.decodable {
+                continue;
+            }
+            let area = frame.width as u64 * frame.height as u64;
+            if preview
+                .as_ref()
+                .is_none_or(|p| area > p.width as u64 * p.height as u64)
+            {
+                preview = Some(RawPreview {
+                    offset,
+                    length,
+                    width: frame.width,
+                    height: frame.height,
+                    sensor_dimensions: None,
+                });
+            }
+        }
+    }
+
+    let mut preview = preview.ok_or_else(|| "RAW file has no embedded JPEG preview".to_string())?;
+    preview.sensor_dimensions = sensor_dimensions;
+    Ok(preview)
+}
+
+/// The tag values of a directory that matter for finding previews and sensor data.
+struct IfdSummary {
+    /// The locations of JPEG streams referenced by the directory.
+    jpeg_candidates: Vec<(u64, u64)>,
+    dimensions: Option<(u32, u32)>,
+    /// Whether the directory holds unprocessed sensor data.
+    is_raw: bool,
+}
+
+impl IfdSummary {
+    fn read<R: Read + Seek>(tiff: &mut TiffReader<R>, ifd: &Ifd) -> Self {
+        let mut value = |tag| ifd.entry(tag).and_then(|entry| tiff.read_integer(entry));
+        let width = value(TAG_IMAGE_WIDTH);
+        let height = value(TAG_IMAGE_LENGTH);
+        let compression = value(TAG_COMPRESSION);
+        let photometric = value(TAG_PHOTOMETRIC_INTERPRETATION);
+        let jpeg_offset = value(TAG_JPEG_INTERCHANGE_FORMAT);
+        let jpeg_length = value(TAG_JPEG_INTERCHANGE_FORMAT_LENGTH);
+
+        let mut jpeg_candidates = Vec::new();
+        if let (Some(offset), Some(length)) = (jpeg_offset, jpeg_length) {
+            jpeg_candidates.push((offset, length));
+        }
+        // Old-style (6) and new-style (7) JPEG compression stored as a single strip.
+        if matches!(compression, Some(6 | 7)) {
+            let strips = ifd
+                .entry(TAG_STRIP_OFFSETS)
+                .and_then(|entry| tiff.read_integers(entry).ok());
+            let byte_counts = ifd
+                .entry(TAG_STRIP_BYTE_COUNTS)
+                .and_then(|entry| tiff.read_integers(entry).ok());
+            if let (Some([offset]), Some([length])) = (strips.as_deref(), byte_counts.as_deref()) {
+                jpeg_candidates.push((*offset, *length));
+            }
+        }
+
+        Self {
+            jpeg_candidates,
+            dimensions: width
+                .zip(height)
+                .map(|(w, h)| (w.min(u32::MAX as u64) as u32, h.min(u32::MAX as u64) as u32)),
+            is_raw: photometric.is_some_and(|p| RAW_PHOTOMETRIC_INTERPRETATIONS.contains(&p)),
+        }
+    }
+}
+
+/// Returns the offsets of the SubIFDs referenced by a directory.
+fn sub_ifd_offsets<R: Read + Seek>(tiff: &mut TiffReader<R>, ifd: &Ifd) -> Vec<u64> {
+    ifd.entry(TAG_SUB_IFDS)
+        .and_then(|entry| tiff.read_integers(entry).ok())
+        .unwrap_or_default()
+}
+
+/// Locates the JPEG preview of a Fujifilm RAF file and reads its sensor size.
+///
+/// The RAF header stores the offset and length of the preview and of a table of records
+/// describing the sensor data, all as big-endian 32-bit integers.
+fn find_raf_preview<R: Read + Seek>(mut reader: R) -> Result<RawPreview, String> {
+    let mut header = [0u8; 100];
+    reader
+        .seek(SeekFrom::Start(0))
+        .and_then(|_| reader.read_exact(&mut header))
+        .map_err(|e| format!("Failed to read RAF header: {}", e))?;
+    let be_u32 = |at: usize| {
+        u32::from_be_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]]) as u64
+    };
+    let (offset, length) = (be_u32(84), be_u32(88));
+    let records_offset = be_u32(92);
+
+    if length == 0 || length > MAX_PREVIEW_LEN {
+        return Err("RAF file has no embedded JPEG preview".to_string());
+    }
+    let frame = read_jpeg_frame(&mut reader, offset)?;
+    if !frame.decodable {
+        return Err("RAF file has no embedded JPEG preview".to_string());
+    }
+
+    Ok(RawPreview {
+        offset,
+        length,
+        width: frame.width,
+        height: frame.height,
+        sensor_dimensions: read_raf_sensor_dimensions(&mut reader, records_offset).ok(),
+    })
+}
+
+/// Reads the full sensor size from the record table of a RAF file.
+fn read_raf_sensor_dimensions<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+) -> Result<(u32, u32), String> {
+    let read_error = |e: std::io::Error| format!("Failed to read RAF records: {}", e);
+    let mut buf = [0u8; 4];
+    reader
+        .seek(SeekFrom::Start(offset))
+        .and_then(|_| reader.read_exact(&mut buf))
+        .map_err(read_error)?;
+    let count = u32::from_be_bytes(buf).min(256);
+
+    for _ in 0..count {
+        reader.read_exact(&mut buf).map_err(read_error)?;
+        let tag = u16::from_be_bytes([buf[0], buf[1]]);
+        let size = u16::from_be_bytes([buf[2], buf[3]]) as i64;
+        if tag == RAF_TAG_RAW_IMAGE_FULL_SIZE && size >= 4 {
+            reader.read_exact(&mut buf).map_err(read_error)?;
+            let height = u16::from_be_bytes([buf[0], buf[1]]) as u32;
+            let width = u16::from_be_bytes([buf[2], buf[3]]) as u32;
+            return Ok((width, height));
+        }
+        reader.seek(SeekFrom::Current(size)).map_err(read_error)?;
+    }
+    Err("RAF file does not record its sensor size".to_string())
+}
+
+/// Reads the start-of-frame segment of the JPEG stream at the given offset.
+///
+/// # Arguments
+/// * `reader` - A reader over the file containing the JPEG stream.
+/// * `offset` - The offset of the JPEG stream.
+///
+/// # Returns
+/// `Result<JpegFrame, String>` - The frame, or an error string if there is no valid JPEG stream at the offset.
+fn read_jpeg_frame<R: Read + Seek>(reader: &mut R, offset: u64) -> Result<JpegFrame, String> {
+    let read_error = |e: std::io::Error| format!("Failed to read JPEG preview: {}", e);
+    let mut marker = [0u8; 2];
+    reader
+        .seek(SeekFrom::Start(offset))
+        .and_then(|_| reader.read_exact(&mut marker))
+        .map_err(read_error)?;
+    if marker != [0xFF, 0xD8] {
+        return Err("No JPEG stream at the preview offset".to_string());
+    }
+
+    loop {
+        reader.read_exact(&mut marker).map_err(read_error)?;
+        if marker[0] != 0xFF {
+            return Err("Malformed JPEG stream".to_string());
+        }
+        // Skip fill bytes.
+        while marker[1] == 0xFF {
+            reader.read_exact(&mut marker[1..]).map_err(read_error)?;
+        }
+        if matches!(marker[1], 0x01 | 0xD0..=0xD7) {
+            continue;
+        }
+
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length).map_err(read_error)?;
+        let length = u16::from_be_bytes(length) as i64;
+        match marker[1] {
+            // Start of frame, excluding DHT (C4), JPG (C8) and DAC (CC).
+            0xC0..=0xCF if !matches!(marker[1], 0xC4 | 0xC8 | 0xCC) => {
+                let mut frame = [0u8; 6];
+                reader.read_exact(&mut frame).map_err(read_error)?;
+                return Ok(JpegFrame {
+                    height: u16::from_be_bytes([frame[1], frame[2]]) as u32,
+                    width: u16::from_be_bytes([frame[3], frame[4]]) as u32,
+                    components: frame[5] as u32,
+                    decodable: matches!(marker[1], 0xC0..=0xC2),
+                    lossless: matches!(marker[1], 0xC3 | 0xC7 | 0xCB | 0xCF),
+                });
+            }
+            0xD9 | 0xDA => return Err("JPEG stream has no frame header".to_string()),
+            _ => {
+                reader
+                    .seek(SeekFrom::Current(length - 2))
+                    .map_err(read_error)?;
+            }
+        }
+    }
+}
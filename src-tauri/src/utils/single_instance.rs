@@ -0,0 +1,239 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, RunEvent};
+
+use crate::models::config::Config;
+use crate::utils::config_utils::read_config;
+
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+#[cfg(unix)]
+use std::time::Duration;
+
+#[cfg(unix)]
+use crate::utils::startup_handler::handle_opened_paths;
+
+/// The name of the socket the running instance listens on for paths from later launches.
+#[cfg(unix)]
+const INSTANCE_SOCKET_NAME: &str = "blickfang.sock";
+
+/// How long a later launch waits for the running instance to accept its paths.
+#[cfg(unix)]
+const HANDOVER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// State struct holding the socket this instance listens on, so it can be removed on exit.
+#[derive(Default)]
+pub struct SingleInstance {
+    socket_path: Mutex<Option<PathBuf>>,
+}
+
+/// Reads whether only a single instance of the application runs at a time,
+/// falling back to the default if the configuration cannot be read.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `bool` - Whether later launches hand their paths to the running instance.
+pub fn load_single_instance(app: &AppHandle) -> bool {
+    read_config(app)
+        .ok()
+        .and_then(|config_str| serde_json::from_str::<Config>(&config_str).ok())
+        .unwrap_or_default()
+        .single_instance
+}
+
+/// Sets up single-instance mode, if it is enabled.
+///
+/// If another instance is already running, the launch arguments are handed to it over a
+/// per-user Unix socket and `true` is returned, telling the caller to exit. Otherwise this
+/// instance starts listening on the socket for the arguments of later launches.
+/// Single-instance mode is only available on Unix platforms.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `bool` - Whether the arguments were handed to a running instance and this process should exit.
+pub fn setup_single_instance(app: &AppHandle) -> bool {
+    #[cfg(unix)]
+    {
+        if !load_single_instance(app) {
+            return false;
+        }
+        let socket_path = match runtime_socket_path(app, INSTANCE_SOCKET_NAME) {
+            Ok(socket_path) => socket_path,
+            Err(e) => {
+                eprintln!("Failed to set up single-instance mode: {}", e);
+                return false;
+            }
+        };
+
+        match UnixStream::connect(&socket_path) {
+            Ok(stream) => match send_paths(stream, &launch_arguments()) {
+                Ok(()) => return true,
+                Err(e) => {
+                    // The socket belongs to a running instance, so it is not taken over.
+                    eprintln!("Failed to hand paths to the running instance: {}", e);
+                    return false;
+                }
+            },
+            // Nobody is listening, so the socket was left behind by an instance that crashed.
+            Err(_) => {
+                let _ = std::fs::remove_file(&socket_path);
+            }
+        }
+        listen_for_instances(app, socket_path);
+        false
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = app;
+        false
+    }
+}
+
+/// Removes the instance socket when the application exits.
+///
+/// # Arguments
+/// * `app_handle` - The Tauri application handle.
+/// * `event` - The `RunEvent` to handle.
+pub fn handle_exit_event(app_handle: &AppHandle, event: &RunEvent) {
+    if let RunEvent::Exit = event {
+        let state = app_handle.state::<SingleInstance>();
+        if let Some(socket_path) = state.socket_path.lock().unwrap().take() {
+            let _ = std::fs::remove_file(socket_path);
+        }
+    }
+}
+
+/// Returns the path of a per-user socket: in the user's runtime directory if there is one,
+/// in the configuration directory otherwise.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+/// * `name` - The file name of the socket.
+///
+/// # Returns
+/// `Result<PathBuf, String>` - The path of the socket, or an error string if no directory for it can be found.
+#[cfg(unix)]
+pub fn runtime_socket_path(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    if let Ok(runtime_dir) = app.path().runtime_dir() {
+        if runtime_dir.is_dir() {
+            return Ok(runtime_dir.join(name));
+        }
+    }
+    app.path()
+        .home_dir()
+        .map(|home| home.join(".blickfang").join(name))
+        .map_err(|e| format!("Failed to get home directory: {}", e))
+}
+
+/// Returns the arguments this process was launched with, with relative paths made absolute,
+/// since the running instance resolves them against its own working directory.
+#[cfg(unix)]
+fn launch_arguments() -> Vec<String> {
+    let current_dir = std::env::current_dir().ok();
+    let absolute = |path: &str| match &current_dir {
+        Some(current_dir) if Path::new(path).is_relative() => {
+            current_dir.join(path).to_string_lossy().to_string()
+        }
+        _ => path.to_string(),
+    };
+    std::env::args()
+        .skip(1)
+        .map(|arg| match arg.strip_prefix("--start-at=") {
+            Some(file) => format!("--start-at={}", absolute(file)),
+            None if arg.starts_with('-') => arg,
+            None => absolute(&arg),
+        })
+        .collect()
+}
+
+/// Sends paths to the running instance as a JSON array on a single line and waits for its confirmation.
+#[cfg(unix)]
+fn send_paths(mut stream: UnixStream, paths: &[String]) -> Result<(), String> {
+    let message =
+        serde_json::to_string(paths).map_err(|e| format!("Failed to serialize paths: {}", e))?;
+    stream
+        .set_read_timeout(Some(HANDOVER_TIMEOUT))
+        .and_then(|_| writeln!(stream, "{}", message))
+        .map_err(|e| format!("Failed to send paths: {}", e))?;
+
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .map_err(|e| format!("No reply from the running instance: {}", e))?;
+    if reply.trim() == "ok" {
+        Ok(())
+    } else {
+        Err("The running instance did not accept the paths".to_string())
+    }
+}
+
+/// Binds the instance socket and handles later launches on a background thread.
+#[cfg(unix)]
+fn listen_for_instances(app: &AppHandle, socket_path: PathBuf) {
+    let listener = match UnixListener::bind(&socket_path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to listen on '{}': {}", socket_path.display(), e);
+            return;
+        }
+    };
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600));
+    }
+    *app.state::<SingleInstance>().socket_path.lock().unwrap() = Some(socket_path);
+
+    let app_handle = app.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream
+                .map_err(|e| format!("Failed to accept connection: {}", e))
+                .and_then(|stream| handle_instance_connection(&app_handle, stream));
+            if let Err(e) = result {
+                eprintln!("Failed to handle a later launch: {}", e);
+            }
+        }
+    });
+}
+
+/// Reads the paths sent by a later launch, opens them and brings the window to the front.
+#[cfg(unix)]
+fn handle_instance_connection(app: &AppHandle, stream: UnixStream) -> Result<(), String> {
+    stream
+        .set_read_timeout(Some(HANDOVER_TIMEOUT))
+        .map_err(|e| format!("Failed to configure connection: {}", e))?;
+    let mut line = String::new();
+    let mut reader = BufReader::new(&stream);
+    reader
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read paths: {}", e))?;
+    let paths: Vec<String> =
+        serde_json::from_str(line.trim()).map_err(|e| format!("Invalid paths: {}", e))?;
+    writeln!(&stream, "ok").map_err(|e| format!("Failed to confirm paths: {}", e))?;
+
+    if !paths.is_empty() {
+        handle_opened_paths(app, paths);
+    }
+    focus_main_window(app);
+    Ok(())
+}
+
+/// Restores, shows and focuses the main window.
+#[cfg(unix)]
+fn focus_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        if let Err(e) = window.set_focus() {
+            eprintln!("Failed to focus window: {e}");
+        }
+    }
+}
@@ -4,6 +4,8 @@ use std::sync::{
 };
 use tauri::{AppHandle, Emitter, Manager, RunEvent, State};
 
+use crate::utils::launch_arguments::{parse_launch_arguments, resolve_launch_request};
+
 /// State struct to hold paths opened by the application before the frontend is ready.
 /// This is used to temporarily store file paths passed to the application at launch.
 #[derive(Default)]
@@ -20,7 +22,7 @@ pub struct AppReady(pub AtomicBool);
 ///
 /// This command is used to indicate that the frontend has finished loading and is ready
 /// to receive events. If there are any paths stored in the OpenedPathsState, they are
-/// emitted to the frontend as an "image-source" event.
+/// resolved and the image to show is emitted to the frontend as an "image-source" event.
 ///
 /// # Arguments
 /// * `opened_paths_state` - A mutable reference to the OpenedPathsState struct.
@@ -36,9 +38,7 @@ pub fn frontend_is_ready(
     let mut guard = opened_paths_state.paths.lock().unwrap();
     if !guard.is_empty() {
         let paths_to_send: Vec<String> = guard.drain(..).collect();
-        if let Err(e) = app.emit("image-source", paths_to_send) {
-            eprintln!("Failed to emit 'image-source' event: {}", e);
-        }
+        open_launch_arguments(&app, paths_to_send);
     }
 }
 
@@ -73,21 +73,44 @@ pub fn handle_run_event(app_handle: &AppHandle, event: &RunEvent) {
     }
 }
 
-/// Handles the paths passed to the application at launch and emits them to the frontend.
+/// Handles the arguments passed to the application at launch, or handed over by a later launch,
+/// and opens them once the frontend is ready.
 ///
 /// # Arguments
 /// * `app_handle` - The Tauri application handle.
-/// * `paths` - A vector of strings representing file paths.
-fn handle_opened_paths(app_handle: &AppHandle, paths: Vec<String>) {
+/// * `paths` - A vector of strings representing file paths and launch flags.
+pub fn handle_opened_paths(app_handle: &AppHandle, paths: Vec<String>) {
     let app_ready_state = app_handle.state::<AppReady>();
 
     if app_ready_state.0.load(Ordering::Relaxed) {
-        if let Err(e) = app_handle.emit("image-source", paths) {
-            eprintln!("Failed to emit 'image-source' event: {}", e);
-        }
+        open_launch_arguments(app_handle, paths);
     } else {
         let opened_paths_state = app_handle.state::<OpenedPathsState>();
         let mut guard = opened_paths_state.paths.lock().unwrap();
         guard.extend(paths);
     }
 }
+
+/// Parses and resolves launch arguments in the background, then emits the image to show
+/// as an "image-source" event and the dropped arguments as a "launch-error" event.
+///
+/// # Arguments
+/// * `app_handle` - The Tauri application handle.
+/// * `args` - The launch arguments, without the program name.
+fn open_launch_arguments(app_handle: &AppHandle, args: Vec<String>) {
+    let arguments = parse_launch_arguments(args);
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let (request, errors) = resolve_launch_request(&app_handle, arguments).await;
+        if !errors.is_empty() {
+            if let Err(e) = app_handle.emit("launch-error", errors) {
+                eprintln!("Failed to emit 'launch-error' event: {}", e);
+            }
+        }
+        if let Some(request) = request {
+            if let Err(e) = app_handle.emit("image-source", request) {
+                eprintln!("Failed to emit 'image-source' event: {}", e);
+            }
+        }
+    });
+}
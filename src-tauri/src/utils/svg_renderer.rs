@@ -0,0 +1,153 @@
+use image::{DynamicImage, RgbaImage};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{self, fontdb, roxmltree, Tree};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::utils::decode_limits::{DecodeError, DecodeLimits};
+
+lazy_static::lazy_static! {
+    /// The system fonts text in SVG images is rendered with, loaded once on first use.
+    static ref FONT_DATABASE: Arc<fontdb::Database> = {
+        let mut database = fontdb::Database::new();
+        database.load_system_fonts();
+        Arc::new(database)
+    };
+}
+
+/// The intrinsic size of an SVG image.
+pub struct SvgInfo {
+    /// The intrinsic width of the image in pixels.
+    pub width: u32,
+    /// The intrinsic height of the image in pixels.
+    pub height: u32,
+}
+
+/// Parses an SVG or SVGZ image.
+///
+/// Images and stylesheets referenced with relative paths are resolved against the directory of the file.
+///
+/// # Arguments
+/// * `bytes` - The SVG data, optionally gzip-compressed.
+/// * `path` - The path the data was read from.
+///
+/// # Returns
+/// `Result<Tree, String>` - The parsed image, or an error string if it is not a valid SVG image.
+fn parse_svg(bytes: &[u8], path: &Path) -> Result<Tree, String> {
+    let options = usvg::Options {
+        resources_dir: path.parent().map(Path::to_path_buf),
+        fontdb: FONT_DATABASE.clone(),
+        ..Default::default()
+    };
+    Tree::from_data(bytes, &options).map_err(|e| format!("Failed to parse SVG image: {}", e))
+}
+
+/// Reads the intrinsic size of an SVG image.
+///
+/// # Arguments
+/// * `bytes` - The SVG data, optionally gzip-compressed.
+/// * `path` - The path the data was read from.
+///
+/// # Returns
+/// `Result<SvgInfo, String>` - The size of the image, or an error string if it is not a valid SVG image.
+pub fn read_svg_info(bytes: &[u8], path: &Path) -> Result<SvgInfo, String> {
+    let size = parse_svg(bytes, path)?.size();
+    Ok(SvgInfo {
+        width: size.width().ceil() as u32,
+        height: size.height().ceil() as u32,
+    })
+}
+
+/// Reads the `viewBox` attribute of the root element of an SVG image, without parsing the whole image.
+///
+/// # Arguments
+/// * `bytes` - The SVG data, optionally gzip-compressed.
+///
+/// # Returns
+/// `Option<[f32; 4]>` - The `viewBox` as `[min-x, min-y, width, height]`, or `None` if there is none.
+pub fn read_view_box(bytes: &[u8]) -> Option<[f32; 4]> {
+    let decompressed;
+    let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+        decompressed = usvg::decompress_svgz(bytes).ok()?;
+        &decompressed
+    } else {
+        bytes
+    };
+    let text = std::str::from_utf8(bytes).ok()?;
+    let document = roxmltree::Document::parse(text).ok()?;
+    let values: Vec<f32> = document
+        .root_element()
+        .attribute("viewBox")?
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse().ok())
+        .collect::<Option<_>>()?;
+    values.try_into().ok()
+}
+
+/// Rasterizes an SVG image at the given scale of its intrinsic size.
+///
+/// # Arguments
+/// * `bytes` - The SVG data, optionally gzip-compressed.
+/// * `path` - The path the data was read from.
+/// * `scale` - The factor the intrinsic size is multiplied with.
+/// * `limits` - The limits the rasterized image has to stay within.
+///
+/// # Returns
+/// `Result<DynamicImage, DecodeError>` - The rasterized image, or an error if the image cannot be
+/// parsed or its rasterized size exceeds the limits.
+pub fn render_svg(
+    bytes: &[u8],
+    path: &Path,
+    scale: f32,
+    limits: &DecodeLimits,
+) -> Result<DynamicImage, DecodeError> {
+    let tree = parse_svg(bytes, path)?;
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(DecodeError::Failed(format!("Invalid scale: {}", scale)));
+    }
+    let size = tree.size();
+    let width = (size.width() * scale).ceil() as u32;
+    let height = (size.height() * scale).ceil() as u32;
+    limits.check(width, height, 4)?;
+
+    let mut pixmap = Pixmap::new(width, height).ok_or_else(|| {
+        DecodeError::Failed(format!("Invalid SVG raster size: {}x{}", width, height))
+    })?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny-skia renders premultiplied colors.
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(width, height, pixels)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| DecodeError::Failed("Failed to rasterize SVG image".to_string()))
+}
+
+/// Reduces a requested display scale until the rasterized image fits into the limits.
+///
+/// # Arguments
+/// * `info` - The size of the SVG image.
+/// * `scale` - The requested scale.
+/// * `limits` - The limits the rasterized image has to stay within.
+///
+/// # Returns
+/// `f32` - The largest scale up to `scale` whose raster fits the limits, but never less than 1.
+pub fn fit_svg_scale(info: &SvgInfo, scale: f32, limits: &DecodeLimits) -> f32 {
+    let longest_side = info.width.max(info.height).max(1) as f32;
+    let area = info.width.max(1) as f32 * info.height.max(1) as f32;
+    // Leave room for rounding the rasterized size up.
+    let by_dimension = limits.max_dimension.saturating_sub(1) as f32 / longest_side;
+    let by_memory = (limits.max_alloc as f32 / 4.0 / area).sqrt();
+    scale.min(by_dimension).min(by_memory).max(1.0)
+}
@@ -0,0 +1,302 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager};
+
+use crate::utils::decode_limits::DecodeLimits;
+use crate::utils::image_formats::{decode_image, guess_image_format};
+use crate::utils::image_processing::apply_exif_orientation;
+use crate::utils::tone_mapping::{to_display_image, ToneMapSettings};
+
+/// The name failed thumbnails are recorded under, so they are not attempted again.
+const FAIL_DIRECTORY: &str = "blickfang";
+
+/// The thumbnail sizes defined by the freedesktop thumbnail specification,
+/// as the name of their cache directory and their largest width or height.
+const THUMBNAIL_SIZES: [(&str, u32); 3] = [("normal", 128), ("large", 256), ("x-large", 512)];
+
+/// Returns the directory thumbnails are cached in: `$XDG_CACHE_HOME/thumbnails`,
+/// or `~/.cache/thumbnails` if `XDG_CACHE_HOME` is not set.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `Result<PathBuf, String>` - The thumbnail cache directory, or an error string if the home directory is unknown.
+pub fn thumbnail_cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    if let Some(cache_home) = std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from) {
+        if cache_home.is_absolute() {
+            return Ok(cache_home.join("thumbnails"));
+        }
+    }
+    app.path()
+        .home_dir()
+        .map(|home| home.join(".cache").join("thumbnails"))
+        .map_err(|e| format!("Failed to get home directory: {}", e))
+}
+
+/// Returns the index of the smallest standard thumbnail size that is at least as large as requested.
+fn size_index(requested: u32) -> usize {
+    THUMBNAIL_SIZES
+        .iter()
+        .position(|&(_, pixels)| pixels >= requested)
+        .unwrap_or(THUMBNAIL_SIZES.len() - 1)
+}
+
+/// Returns the thumbnail of an image file, creating it if no valid thumbnail exists yet.
+///
+/// Thumbnails are looked up at the requested size first and at the larger sizes after that,
+/// so thumbnails other applications already made are reused. A thumbnail is valid if its
+/// `Thumb::URI` refers to the file and its `Thumb::MTime` matches the file's modification time.
+/// Images that cannot be decoded are recorded as failed and are not attempted again until they change.
+///
+/// # Arguments
+/// * `cache_dir` - The thumbnail cache directory.
+/// * `path` - The absolute path to the image file.
+/// * `size` - The requested thumbnail size: 128, 256 or 512 pixels. Other sizes use the next larger one.
+/// * `tone_map` - The settings used to render high dynamic range images.
+/// * `limits` - The limits the image is decoded under.
+///
+/// # Returns
+/// `Result<Option<PathBuf>, String>` - The path of the thumbnail, `None` if no thumbnail can be made
+/// for the file, or an error string if the file cannot be accessed.
+pub fn get_or_create_thumbnail(
+    cache_dir: &Path,
+    path: &Path,
+    size: u32,
+    tone_map: &ToneMapSettings,
+    limits: &DecodeLimits,
+) -> Result<Option<PathBuf>, String> {
+    // The specification forbids thumbnailing the thumbnails themselves.
+    if path.starts_with(cache_dir) {
+        return Ok(None);
+    }
+
+    let mtime = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Failed to get file modification time: {}", e))?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let uri = file_uri(path);
+    let file_name = format!("{:x}.png", md5::compute(uri.as_bytes()));
+
+    let index = size_index(size);
+    for (directory, _) in &THUMBNAIL_SIZES[index..] {
+        let candidate = cache_dir.join(directory).join(&file_name);
+        if is_valid_thumbnail(&candidate, &uri, mtime) {
+            return Ok(Some(candidate));
+        }
+    }
+    let fail_path = cache_dir.join("fail").join(FAIL_DIRECTORY).join(&file_name);
+    if is_valid_thumbnail(&fail_path, &uri, mtime) {
+        return Ok(None);
+    }
+
+    let (directory, pixels) = THUMBNAIL_SIZES[index];
+    let thumbnail_path = cache_dir.join(directory).join(&file_name);
+    match render_thumbnail(path, pixels, tone_map, limits) {
+        Ok((thumbnail, text)) => {
+            let mut chunks = thumbnail_text(&uri, mtime, path);
+            chunks.extend(text);
+            write_thumbnail(&thumbnail_path, &thumbnail, &chunks)?;
+            Ok(Some(thumbnail_path))
+        }
+        Err(e) => {
+            eprintln!("Failed to create thumbnail for '{}': {}", path.display(), e);
+            let marker = image::RgbaImage::new(1, 1);
+            write_thumbnail(&fail_path, &marker, &thumbnail_text(&uri, mtime, path))?;
+            Ok(None)
+        }
+    }
+}
+
+/// Resolves the name of a cached thumbnail, as used in thumbnail URLs, to its file.
+///
+/// Only names of the form `<size directory>/<md5>.png` are accepted, so requests cannot reach
+/// files outside the thumbnail directories.
+///
+/// # Arguments
+/// * `cache_dir` - The thumbnail cache directory.
+/// * `name` - The size directory and file name of the thumbnail, separated by a slash.
+///
+/// # Returns
+/// `Option<PathBuf>` - The path of the thumbnail, or `None` if the name is not a thumbnail name.
+pub fn resolve_thumbnail_name(cache_dir: &Path, name: &str) -> Option<PathBuf> {
+    let (directory, file_name) = name.split_once('/')?;
+    let hash = file_name.strip_suffix(".png")?;
+    let is_thumbnail = THUMBNAIL_SIZES.iter().any(|(size, _)| *size == directory)
+        && hash.len() == 32
+        && hash.chars().all(|c| c.is_ascii_hexdigit());
+    is_thumbnail.then(|| cache_dir.join(directory).join(file_name))
+}
+
+/// Decodes an image and scales it down to fit into a square of the given size.
+///
+/// # Returns
+/// `Result<(RgbaImage, Vec<(String, String)>), String>` - The thumbnail and the text chunks
+/// describing the original image.
+fn render_thumbnail(
+    path: &Path,
+    pixels: u32,
+    tone_map: &ToneMapSettings,
+    limits: &DecodeLimits,
+) -> Result<(image::RgbaImage, Vec<(String, String)>), String> {
+    let bytes =
+        fs::read(path).map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+    let img = apply_exif_orientation(&bytes, decode_image(&bytes, path, limits)?);
+    let text = vec![
+        ("Thumb::Image::Width".to_string(), img.width().to_string()),
+        ("Thumb::Image::Height".to_string(), img.height().to_string()),
+    ];
+    let img = if img.width() > pixels || img.height() > pixels {
+        img.thumbnail(pixels, pixels)
+    } else {
+        img
+    };
+    Ok((to_display_image(img, tone_map).to_rgba8(), text))
+}
+
+/// Builds the text chunks every thumbnail carries to identify its original.
+fn thumbnail_text(uri: &str, mtime: u64, path: &Path) -> Vec<(String, String)> {
+    let mut text = vec![
+        ("Thumb::URI".to_string(), uri.to_string()),
+        ("Thumb::MTime".to_string(), mtime.to_string()),
+        ("Software".to_string(), "blickfang".to_string()),
+    ];
+    if let Ok(metadata) = fs::metadata(path) {
+        text.push(("Thumb::Size".to_string(), metadata.len().to_string()));
+    }
+    if let Some(header) = read_file_header(path) {
+        let (mime_type, _) = guess_image_format(path, &header);
+        text.push(("Thumb::Mimetype".to_string(), mime_type));
+    }
+    text
+}
+
+/// Reads the first bytes of a file, enough to identify its format.
+fn read_file_header(path: &Path) -> Option<Vec<u8>> {
+    use std::io::Read;
+    let mut header = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(64)
+        .read_to_end(&mut header)
+        .ok()?;
+    Some(header)
+}
+
+/// Returns `true` if the PNG file at the given path is a thumbnail of the given URI at the given modification time.
+fn is_valid_thumbnail(thumbnail_path: &Path, uri: &str, mtime: u64) -> bool {
+    let Ok(file) = File::open(thumbnail_path) else {
+        return false;
+    };
+    let Ok(reader) = png::Decoder::new(BufReader::new(file)).read_info() else {
+        return false;
+    };
+    let info = reader.info();
+    let text: Vec<(&str, String)> = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.as_str(), chunk.text.clone()))
+        .chain(
+            info.utf8_text
+                .iter()
+                .filter_map(|chunk| Some((chunk.keyword.as_str(), chunk.get_text().ok()?))),
+        )
+        .collect();
+    let value = |key: &str| {
+        text.iter()
+            .find(|(keyword, _)| *keyword == key)
+            .map(|(_, value)| value.as_str())
+    };
+
+    // Some applications store the modification time with a fractional part.
+    let stored_mtime = value("Thumb::MTime")
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .map(|value| value as u64);
+    value("Thumb::URI") == Some(uri) && stored_mtime == Some(mtime)
+}
+
+/// Writes a thumbnail as a PNG file with the given text chunks.
+///
+/// The file is written to a temporary name first and renamed into place, so other applications
+/// never see a partially written thumbnail. Directories and files are only accessible by the user.
+fn write_thumbnail(
+    thumbnail_path: &Path,
+    thumbnail: &image::RgbaImage,
+    text: &[(String, String)],
+) -> Result<(), String> {
+    let directory = thumbnail_path
+        .parent()
+        .ok_or_else(|| "Invalid thumbnail path".to_string())?;
+    create_private_dir(directory)?;
+
+    let temp_path = thumbnail_path.with_extension(format!("png.{}.tmp", std::process::id()));
+    let encode_error = |e: png::EncodingError| format!("Failed to write thumbnail: {}", e);
+    {
+        let file = File::create(&temp_path)
+            .map_err(|e| format!("Failed to create thumbnail file: {}", e))?;
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(file), thumbnail.width(), thumbnail.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        for (keyword, value) in text {
+            encoder
+                .add_text_chunk(keyword.clone(), value.clone())
+                .map_err(encode_error)?;
+        }
+        let mut writer = encoder.write_header().map_err(encode_error)?;
+        writer
+            .write_image_data(thumbnail.as_raw())
+            .map_err(encode_error)?;
+        writer.finish().map_err(encode_error)?;
+    }
+    set_private_permissions(&temp_path, 0o600);
+    fs::rename(&temp_path, thumbnail_path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        format!("Failed to store thumbnail: {}", e)
+    })
+}
+
+/// Creates a directory and its parents, accessible only by the user.
+fn create_private_dir(directory: &Path) -> Result<(), String> {
+    if directory.is_dir() {
+        return Ok(());
+    }
+    fs::create_dir_all(directory)
+        .map_err(|e| format!("Failed to create thumbnail directory: {}", e))?;
+    set_private_permissions(directory, 0o700);
+    Ok(())
+}
+
+/// Restricts the permissions of a file or directory, on platforms that support it.
+fn set_private_permissions(path: &Path, mode: u32) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(mode));
+    }
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+}
+
+/// Builds the `file://` URI of an absolute path, percent-encoding all bytes
+/// except unreserved characters and the separators allowed in paths.
+fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => uri.push(byte as char),
+            b'-' | b'.' | b'_' | b'~' | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+'
+            | b',' | b';' | b'=' | b':' | b'@' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
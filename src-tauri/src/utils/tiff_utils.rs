@@ -0,0 +1,405 @@
+use image::{DynamicImage, ImageBuffer};
+use std::collections::HashSet;
+use std::io::{Read, Seek, SeekFrom};
+use tiff::decoder::{Decoder, DecodingResult, Limits as TiffLimits};
+use tiff::ColorType;
+
+use crate::utils::decode_limits::DecodeLimits;
+
+/// The `NewSubfileType` tag, whose lowest bit marks reduced-resolution images such as thumbnails.
+pub const TAG_NEW_SUBFILE_TYPE: u16 = 254;
+
+/// The maximum number of directories followed in a chain, guarding against malformed files.
+const MAX_IFD_CHAIN_LEN: usize = 4096;
+
+/// A single entry of an image file directory (IFD).
+pub struct IfdEntry {
+    pub tag: u16,
+    pub field_type: u16,
+    pub count: u64,
+    /// The raw value field. It holds the value itself if it fits, and its offset otherwise.
+    value_field: [u8; 8],
+}
+
+/// An image file directory, describing one image (or page) within a TIFF file.
+pub struct Ifd {
+    pub entries: Vec<IfdEntry>,
+    /// The offset of the next directory in the chain, or 0 if this is the last one.
+    pub next_offset: u64,
+}
+
+impl Ifd {
+    /// Returns the entry for the given tag, if present.
+    pub fn entry(&self, tag: u16) -> Option<&IfdEntry> {
+        self.entries.iter().find(|entry| entry.tag == tag)
+    }
+}
+
+/// A reader for the directory structure of TIFF and BigTIFF files.
+///
+/// Only the directories and their tag values are read; image data is left to the `image` crate.
+pub struct TiffReader<R> {
+    reader: R,
+    little_endian: bool,
+    big_tiff: bool,
+    first_ifd_offset: u64,
+}
+
+impl<R: Read + Seek> TiffReader<R> {
+    /// Reads the TIFF header from the start of the given reader.
+    ///
+    /// # Arguments
+    /// * `reader` - A reader over the TIFF data.
+    ///
+    /// # Returns
+    /// `Result<Self, String>` - The TIFF reader, or an error string if the data is not a TIFF file.
+    pub fn new(mut reader: R) -> Result<Self, String> {
+        let mut header = [0u8; 8];
+        reader
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| reader.read_exact(&mut header))
+            .map_err(|e| format!("Failed to read TIFF header: {}", e))?;
+
+        let little_endian = match &header[0..2] {
+            b"II" => true,
+            b"MM" => false,
+            _ => return Err("Not a TIFF file".to_string()),
+        };
+        let mut tiff = Self {
+            reader,
+            little_endian,
+            big_tiff: false,
+            first_ifd_offset: 0,
+        };
+
+        match tiff.u16_from(&header[2..4]) {
+            42 => tiff.first_ifd_offset = tiff.u32_from(&header[4..8]) as u64,
+            43 => {
+                tiff.big_tiff = true;
+                tiff.first_ifd_offset = tiff.read_u64()?;
+            }
+            _ => return Err("Unsupported TIFF version".to_string()),
+        }
+        Ok(tiff)
+    }
+
+    /// Reads the image file directory at the given offset.
+    ///
+    /// # Arguments
+    /// * `offset` - The offset of the directory from the start of the file.
+    ///
+    /// # Returns
+    /// `Result<Ifd, String>` - The directory, or an error string if it cannot be read.
+    pub fn read_ifd(&mut self, offset: u64) -> Result<Ifd, String> {
+        self.reader
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| format!("Failed to seek to TIFF directory: {}", e))?;
+
+        let count = if self.big_tiff {
+            self.read_u64()?
+        } else {
+            self.read_u16()? as u64
+        };
+        let value_len = if self.big_tiff { 8 } else { 4 };
+
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let tag = self.read_u16()?;
+            let field_type = self.read_u16()?;
+            let count = if self.big_tiff {
+                self.read_u64()?
+            } else {
+                self.read_u32()? as u64
+            };
+            let mut value_field = [0u8; 8];
+            self.read_bytes(&mut value_field[..value_len])?;
+            entries.push(IfdEntry {
+                tag,
+                field_type,
+                count,
+                value_field,
+            });
+        }
+
+        let next_offset = if self.big_tiff {
+            self.read_u64()?
+        } else {
+            self.read_u32()? as u64
+        };
+        Ok(Ifd {
+            entries,
+            next_offset,
+        })
+    }
+
+    /// Reads the main chain of image file directories, starting at the first one.
+    ///
+    /// # Returns
+    /// `Result<Vec<Ifd>, String>` - The directories in file order, or an error string if the first one cannot be read.
+    pub fn read_ifd_chain(&mut self) -> Result<Vec<Ifd>, String> {
+        self.read_ifd_chain_from(self.first_ifd_offset)
+    }
+
+    /// Reads a chain of image file directories starting at the given offset.
+    /// Reading stops at the end of the chain, at a loop, or at the first unreadable directory.
+    ///
+    /// # Arguments
+    /// * `offset` - The offset of the first directory of the chain.
+    ///
+    /// # Returns
+    /// `Result<Vec<Ifd>, String>` - The directories in chain order, or an error string if the first one cannot be read.
+    pub fn read_ifd_chain_from(&mut self, mut offset: u64) -> Result<Vec<Ifd>, String> {
+        let mut ifds = Vec::new();
+        let mut visited = HashSet::new();
+
+        while offset != 0 && ifds.len() < MAX_IFD_CHAIN_LEN && visited.insert(offset) {
+            match self.read_ifd(offset) {
+                Ok(ifd) => {
+                    offset = ifd.next_offset;
+                    ifds.push(ifd);
+                }
+                Err(e) if ifds.is_empty() => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(ifds)
+    }
+
+    /// Reads the integer values of an entry. Only integer field types are supported.
+    ///
+    /// # Arguments
+    /// * `entry` - The directory entry to read.
+    ///
+    /// # Returns
+    /// `Result<Vec<u64>, String>` - The values, or an error string if they cannot be read.
+    pub fn read_integers(&mut self, entry: &IfdEntry) -> Result<Vec<u64>, String> {
+        let size = match entry.field_type {
+            1 | 6 | 7 => 1,  // BYTE, SBYTE, UNDEFINED
+            3 | 8 => 2,      // SHORT, SSHORT
+            4 | 9 | 13 => 4, // LONG, SLONG, IFD
+            16..=18 => 8,    // LONG8, SLONG8, IFD8
+            other => return Err(format!("Unsupported TIFF field type: {}", other)),
+        };
+        let total = entry
+            .count
+            .checked_mul(size)
+            .ok_or_else(|| "TIFF entry is too large".to_string())?;
+        if total > 1024 * 1024 {
+            return Err("TIFF entry is too large".to_string());
+        }
+
+        let inline_len = if self.big_tiff { 8 } else { 4 };
+        let data = if total <= inline_len {
+            entry.value_field[..total as usize].to_vec()
+        } else {
+            let offset = if self.big_tiff {
+                self.u64_from(&entry.value_field)
+            } else {
+                self.u32_from(&entry.value_field[..4]) as u64
+            };
+            self.reader
+                .seek(SeekFrom::Start(offset))
+                .map_err(|e| format!("Failed to seek to TIFF value: {}", e))?;
+            let mut data = vec![0u8; total as usize];
+            self.read_bytes(&mut data)?;
+            data
+        };
+
+        Ok(data
+            .chunks_exact(size)
+            .map(|chunk| match size {
+                1 => chunk[0] as u64,
+                2 => self.u16_from(chunk) as u64,
+                4 => self.u32_from(chunk) as u64,
+                _ => self.u64_from(chunk),
+            })
+            .collect())
+    }
+
+    /// Reads the first integer value of an entry.
+    ///
+    /// # Arguments
+    /// * `entry` - The directory entry to read.
+    ///
+    /// # Returns
+    /// `Option<u64>` - The value, or `None` if it cannot be read.
+    pub fn read_integer(&mut self, entry: &IfdEntry) -> Option<u64> {
+        self.read_integers(entry).ok()?.first().copied()
+    }
+
+    fn read_bytes(&mut self, buf: &mut [u8]) -> Result<(), String> {
+        self.reader
+            .read_exact(buf)
+            .map_err(|e| format!("Failed to read TIFF data: {}", e))
+    }
+
+    fn read_u16(&mut self) -> Result<u16, String> {
+        let mut buf = [0u8; 2];
+        self.read_bytes(&mut buf)?;
+        Ok(self.u16_from(&buf))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        let mut buf = [0u8; 4];
+        self.read_bytes(&mut buf)?;
+        Ok(self.u32_from(&buf))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, String> {
+        let mut buf = [0u8; 8];
+        self.read_bytes(&mut buf)?;
+        Ok(self.u64_from(&buf))
+    }
+
+    fn u16_from(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        }
+    }
+
+    fn u32_from(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    }
+
+    fn u64_from(&self, bytes: &[u8]) -> u64 {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&bytes[..8]);
+        if self.little_endian {
+            u64::from_le_bytes(buf)
+        } else {
+            u64::from_be_bytes(buf)
+        }
+    }
+}
+
+/// Counts the pages of a TIFF file.
+///
+/// Every directory in the main chain is a page, except reduced-resolution images such as thumbnails.
+///
+/// # Arguments
+/// * `reader` - A reader over the TIFF data.
+///
+/// # Returns
+/// `Result<u32, String>` - The number of pages, or an error string if the file cannot be read.
+pub fn count_tiff_pages<R: Read + Seek>(reader: R) -> Result<u32, String> {
+    Ok(tiff_page_directories(reader)?.len().max(1) as u32)
+}
+
+/// Decodes a single page of a TIFF file.
+///
+/// Pages with 8 or 16 bits per channel and 32-bit float RGB pages are supported.
+///
+/// # Arguments
+/// * `reader` - A reader over the TIFF data.
+/// * `page` - The index of the page, starting at 0.
+/// * `limits` - The limits the page is decoded under.
+///
+/// # Returns
+/// `Result<DynamicImage, String>` - The page, or an error string if it does not exist or cannot be decoded.
+pub fn decode_tiff_page<R: Read + Seek>(
+    mut reader: R,
+    page: u32,
+    limits: &DecodeLimits,
+) -> Result<DynamicImage, String> {
+    let directories = tiff_page_directories(&mut reader)?;
+    let directory = *directories
+        .get(page as usize)
+        .ok_or_else(|| format!("The file has no page {}", page + 1))?;
+    reader
+        .seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to seek in TIFF file: {}", e))?;
+
+    let mut tiff_limits = TiffLimits::default();
+    tiff_limits.decoding_buffer_size = usize::try_from(limits.max_alloc).unwrap_or(usize::MAX);
+    let mut decoder = Decoder::new(reader)
+        .map_err(|e| format!("Failed to read TIFF file: {}", e))?
+        .with_limits(tiff_limits);
+    decoder
+        .seek_to_image(directory)
+        .map_err(|e| format!("Failed to find TIFF page {}: {}", page + 1, e))?;
+    let (width, height) = decoder
+        .dimensions()
+        .map_err(|e| format!("Failed to read TIFF page size: {}", e))?;
+    let color_type = decoder
+        .colortype()
+        .map_err(|e| format!("Failed to read TIFF page color type: {}", e))?;
+    let channels = match color_type {
+        ColorType::Gray(_) => 1,
+        ColorType::GrayA(_) => 2,
+        ColorType::RGB(_) => 3,
+        _ => 4,
+    };
+    let bits = match color_type {
+        ColorType::Gray(bits)
+        | ColorType::GrayA(bits)
+        | ColorType::RGB(bits)
+        | ColorType::RGBA(bits) => bits,
+        _ => 8,
+    };
+    limits.check(width, height, channels * bits.div_ceil(8).max(1) as u64)?;
+
+    let data = decoder
+        .read_image()
+        .map_err(|e| format!("Failed to decode TIFF page {}: {}", page + 1, e))?;
+    let image = match (color_type, data) {
+        (ColorType::Gray(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA8)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::GrayA(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA16)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba16)
+        }
+        (ColorType::RGB(32), DecodingResult::F32(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb32F)
+        }
+        (ColorType::RGBA(32), DecodingResult::F32(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba32F)
+        }
+        _ => None,
+    };
+    image.ok_or_else(|| format!("Unsupported TIFF page color type: {:?}", color_type))
+}
+
+/// Lists the indices of the directories in the main chain of a TIFF file that are pages,
+/// skipping reduced-resolution images such as thumbnails.
+fn tiff_page_directories<R: Read + Seek>(reader: R) -> Result<Vec<usize>, String> {
+    let mut tiff = TiffReader::new(reader)?;
+    let ifds = tiff.read_ifd_chain()?;
+    Ok(ifds
+        .iter()
+        .enumerate()
+        .filter(|(_, ifd)| {
+            let subfile_type = ifd
+                .entry(TAG_NEW_SUBFILE_TYPE)
+                .and_then(|entry| tiff.read_integer(entry))
+                .unwrap_or(0);
+            subfile_type & 1 == 0
+        })
+        .map(|(index, _)| index)
+        .collect())
+}
@@ -0,0 +1,259 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use tauri::AppHandle;
+
+use crate::models::config::Config;
+use crate::utils::config_utils::read_config;
+
+/// The curve used to compress high dynamic range values into the displayable range.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ToneMapOperator {
+    Reinhard,
+    Aces,
+}
+
+impl ToneMapOperator {
+    /// Returns the operator with the given name, as stored in the configuration.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the operator ("aces" or "reinhard").
+    ///
+    /// # Returns
+    /// `Option<ToneMapOperator>` - The operator, or `None` if the name is unknown.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "aces" => Some(ToneMapOperator::Aces),
+            "reinhard" => Some(ToneMapOperator::Reinhard),
+            _ => None,
+        }
+    }
+}
+
+/// The settings used to render high dynamic range images for display.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ToneMapSettings {
+    pub operator: ToneMapOperator,
+    /// The exposure adjustment in stops (EV) applied before tone mapping.
+    pub exposure: f32,
+}
+
+impl Default for ToneMapSettings {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+impl ToneMapSettings {
+    /// Builds the tone mapping settings stored in the application configuration.
+    /// Unknown operators fall back to ACES.
+    ///
+    /// # Arguments
+    /// * `config` - The application configuration.
+    pub fn from_config(config: &Config) -> Self {
+        let operator =
+            ToneMapOperator::from_name(&config.tone_mapping).unwrap_or(ToneMapOperator::Aces);
+        Self {
+            operator,
+            exposure: config.exposure.clamp(-10.0, 10.0),
+        }
+    }
+
+    /// Returns a short string identifying these settings, used to tell renditions apart.
+    pub fn cache_key(&self) -> String {
+        let operator = match self.operator {
+            ToneMapOperator::Reinhard => "reinhard",
+            ToneMapOperator::Aces => "aces",
+        };
+        format!("{}{:+.2}", operator, self.exposure)
+    }
+}
+
+/// Reads the configured tone mapping settings, falling back to the defaults if the configuration cannot be read.
+///
+/// # Arguments
+/// * `app` - The Tauri application handle.
+///
+/// # Returns
+/// `ToneMapSettings` - The configured tone mapping settings.
+pub fn load_tone_map_settings(app: &AppHandle) -> ToneMapSettings {
+    read_config(app)
+        .ok()
+        .and_then(|config_str| serde_json::from_str::<Config>(&config_str).ok())
+        .map(|config| ToneMapSettings::from_config(&config))
+        .unwrap_or_default()
+}
+
+/// Converts an image to 8 bits per channel for display.
+///
+/// Floating point images hold linear, possibly unbounded values and are tone mapped.
+/// 16-bit images are already display-referred and are only reduced in precision.
+/// 8-bit images are returned unchanged.
+///
+/// # Arguments
+/// * `img` - The image to convert.
+/// * `settings` - The tone mapping settings applied to floating point images.
+///
+/// # Returns
+/// `DynamicImage` - The 8-bit display image.
+pub fn to_display_image(img: DynamicImage, settings: &ToneMapSettings) -> DynamicImage {
+    match img {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            DynamicImage::ImageRgba8(tone_map(&img, settings))
+        }
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_) => DynamicImage::ImageRgba8(img.to_rgba8()),
+        _ => img,
+    }
+}
+
+/// Tone maps a linear floating point image to 8-bit sRGB.
+fn tone_map(img: &DynamicImage, settings: &ToneMapSettings) -> RgbaImage {
+    let source = img.to_rgba32f();
+    let scale = 2f32.powf(settings.exposure);
+
+    RgbaImage::from_fn(source.width(), source.height(), |x, y| {
+        let [r, g, b, a] = source.get_pixel(x, y).0;
+        let [r, g, b] = tone_map_color_scaled([r, g, b], settings.operator, scale).map(to_u8);
+        Rgba([r, g, b, to_u8(sanitize(a))])
+    })
+}
+
+/// Tone maps a single linear color the way floating point images are displayed.
+///
+/// # Arguments
+/// * `rgb` - The linear color.
+/// * `settings` - The tone mapping settings.
+///
+/// # Returns
+/// `[f32; 3]` - The displayed sRGB color, with components from 0 to 1.
+pub fn tone_map_color(rgb: [f32; 3], settings: &ToneMapSettings) -> [f32; 3] {
+    tone_map_color_scaled(rgb, settings.operator, 2f32.powf(settings.exposure))
+}
+
+/// Tone maps a linear color after scaling it by the exposure factor.
+fn tone_map_color_scaled(rgb: [f32; 3], operator: ToneMapOperator, scale: f32) -> [f32; 3] {
+    let rgb = rgb.map(|c| sanitize(c) * scale);
+    let mapped = match operator {
+        ToneMapOperator::Reinhard => reinhard(rgb),
+        ToneMapOperator::Aces => rgb.map(aces),
+    };
+    mapped.map(linear_to_srgb)
+}
+
+/// Applies the Reinhard operator to the luminance of a color, preserving its hue.
+fn reinhard(rgb: [f32; 3]) -> [f32; 3] {
+    let luminance = relative_luminance(rgb);
+    if luminance <= 0.0 {
+        return [0.0; 3];
+    }
+    let factor = (luminance / (1.0 + luminance)) / luminance;
+    rgb.map(|c| c * factor)
+}
+
+/// Applies Krzysztof Narkowicz's fit of the ACES filmic curve to a single channel.
+fn aces(value: f32) -> f32 {
+    let x = value * 0.6;
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+/// Measures the dynamic range of an image with more than 8 bits per channel.
+///
+/// The range is the ratio between the brightest and the darkest non-black luminance,
+/// expressed in stops. The brightest and darkest 0.1% of pixels are ignored, so single
+/// outliers such as hot pixels do not dominate the result.
+///
+/// # Arguments
+/// * `img` - The decoded image.
+///
+/// # Returns
+/// `Option<f32>` - The dynamic range in stops, or `None` for 8-bit images and images without any lit pixels.
+pub fn measure_dynamic_range(img: &DynamicImage) -> Option<f32> {
+    let is_display_referred = match img {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => false,
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_) => true,
+        _ => return None,
+    };
+
+    // Histogram of log2 luminance in steps of 1/8 stop, covering 2^-32 to 2^32.
+    const BINS_PER_STOP: f32 = 8.0;
+    const MIN_STOP: f32 = -32.0;
+    const BIN_COUNT: usize = (64.0 * BINS_PER_STOP) as usize;
+    let mut histogram = vec![0u64; BIN_COUNT];
+
+    for pixel in img.to_rgb32f().pixels() {
+        let rgb = if is_display_referred {
+            pixel.0.map(srgb_to_linear)
+        } else {
+            pixel.0.map(sanitize)
+        };
+        let luminance = relative_luminance(rgb);
+        if luminance > 0.0 {
+            let bin = ((luminance.log2() - MIN_STOP) * BINS_PER_STOP) as usize;
+            histogram[bin.min(BIN_COUNT - 1)] += 1;
+        }
+    }
+
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let cutoff = total / 1000;
+    let low = percentile_bin(histogram.iter().copied().enumerate(), cutoff)?;
+    let high = percentile_bin(histogram.iter().copied().enumerate().rev(), cutoff)?;
+    Some(high.saturating_sub(low) as f32 / BINS_PER_STOP)
+}
+
+/// Returns the first bin at which the accumulated count exceeds the cutoff.
+fn percentile_bin(bins: impl Iterator<Item = (usize, u64)>, cutoff: u64) -> Option<usize> {
+    let mut accumulated = 0;
+    for (bin, count) in bins {
+        accumulated += count;
+        if accumulated > cutoff {
+            return Some(bin);
+        }
+    }
+    None
+}
+
+/// Returns the relative luminance of a linear Rec. 709 color.
+fn relative_luminance([r, g, b]: [f32; 3]) -> f32 {
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// Replaces negative and non-finite values, which some HDR files contain, with zero.
+fn sanitize(value: f32) -> f32 {
+    if value.is_finite() {
+        value.max(0.0)
+    } else {
+        0.0
+    }
+}
+
+/// Applies the sRGB transfer function to a linear value in the range 0 to 1.
+fn linear_to_srgb(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Inverts the sRGB transfer function for a value in the range 0 to 1.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a value in the range 0 to 1 to an 8-bit sample.
+fn to_u8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
@@ -20,6 +20,8 @@ class ImageViewport {
   private ctx: CanvasRenderingContext2D;
   private options: ViewportOptions;
   private image: HTMLImageElement | null;
+  private sharpImage: HTMLImageElement | null;
+  private sharpScale: number;
   private isDragging: boolean;
   private lastWheelTime: number;
   private isAnimating: boolean;
@@ -47,6 +49,8 @@ class ImageViewport {
     this.ctx = canvas.getContext("2d") as CanvasRenderingContext2D;
 
     this.image = null;
+    this.sharpImage = null;
+    this.sharpScale = 1;
     this.isDragging = false;
     this.lastWheelTime = 0;
     this.isAnimating = false;
@@ -102,6 +106,8 @@ class ImageViewport {
     const { zoomLevelStore, imageUrlStore } = this.options;
 
     const unSubImageUrl = imageUrlStore.subscribe((url) => {
+      this.sharpImage = null;
+      this.sharpScale = 1;
       if (url) {
         this.image = new Image();
         this.image.src = url;
@@ -223,13 +229,13 @@ class ImageViewport {
    */
   private renderPreview(image: HTMLImageElement, w: number, h: number) {
     if (this.displayScale > 0.5) {
-      this.ctx.drawImage(image, -w / 2, -h / 2);
+      this.ctx.drawImage(image, -w / 2, -h / 2, w, h);
       return;
     }
 
     const { downscaleCanvas1: c, downscaleCtx1: ctx } = this;
     if (!ctx) {
-      this.ctx.drawImage(image, -w / 2, -h / 2);
+      this.ctx.drawImage(image, -w / 2, -h / 2, w, h);
       return;
     }
 
@@ -261,7 +267,7 @@ class ImageViewport {
       downscaleCtx2: ctx2,
     } = this;
     if (!ctx1 || !ctx2) {
-      this.ctx.drawImage(image, -w / 2, -h / 2);
+      this.ctx.drawImage(image, -w / 2, -h / 2, w, h);
       return;
     }
 
@@ -312,9 +318,33 @@ class ImageViewport {
     const isInteracting = this.isInteracting();
     if (isInteracting) {
       this.debounceIndicatorVisibility();
+    } else {
+      this.updateSharpRendition();
     }
   }
 
+  /**
+   * Requests a sharper rendition of images that can be re-rendered, such as SVG images,
+   * once zooming has settled. Renditions are requested at powers of two of the intrinsic size,
+   * so only zooming past the current rendition triggers a new one.
+   */
+  private updateSharpRendition() {
+    if (!this.options.renditionUrl || !this.image || this.displayScale <= 1) return;
+    const scale = 2 ** Math.ceil(Math.log2(this.displayScale));
+    if (scale <= this.sharpScale) return;
+
+    const url = this.options.renditionUrl(scale);
+    if (!url) return;
+    this.sharpScale = scale;
+    const sharpImage = new Image();
+    sharpImage.onload = () => {
+      if (this.sharpScale !== scale) return;
+      this.sharpImage = sharpImage;
+      this.canvasCache = null;
+    };
+    sharpImage.src = url;
+  }
+
   /**
    * Determines if the viewport is currently in an interacting state.
    * @returns true if user is interacting with the viewport
@@ -385,20 +415,22 @@ class ImageViewport {
 
   /**
    * Renders the image using the appropriate rendering method based on zoom level and interaction state.
+   * A sharper rendition, if loaded, is drawn at the size of the original image.
    */
   private renderImage() {
     const w = this.image!.naturalWidth;
     const h = this.image!.naturalHeight;
+    const source = this.sharpImage ?? this.image!;
     const isZoomedOut = this.displayScale < 0.75;
     const isInteracting = this.isInteracting();
     
     if (isZoomedOut && !isInteracting) {
-      this.renderHighQuality(this.image!, w, h);
+      this.renderHighQuality(source, w, h);
     } else {
       if (isInteracting) {
         this.canvasCache = null;
       }
-      this.renderPreview(this.image!, w, h);
+      this.renderPreview(source, w, h);
     }
   }
 
@@ -1,20 +1,45 @@
+import { get } from "svelte/store";
 import { listen } from "@tauri-apps/api/event";
 import { invoke } from "@tauri-apps/api/core";
 import { setLocale } from "$lib/utils/i18n";
-import { imageUrl, imagePath, appConfig, aiDetectionResult } from "$lib/stores";
+import {
+  imageUrl,
+  imagePath,
+  appConfig,
+  aiDetectionResult,
+  zoomLevel,
+  rotation,
+  isConvertedToAscii,
+  directoryListing,
+} from "$lib/stores";
 import {
   isInitialDialogVisible,
   hasConfiguredInitialSettings,
   isLinuxDesktopInstallDialogVisible,
+  isZenModeActive,
+  isFullscreenActive,
 } from "$lib/stores/index";
-import type { AppConfig } from "$lib/types/app";
-import type { AiDetectionResult, ImageMetadata } from "$lib/types/image";
-import { updateImageStores } from "$lib/core/commands";
+import type { AppConfig, LaunchRequest } from "$lib/types/app";
+import type { AiDetectionResult, DirectoryChange, ImageMetadata } from "$lib/types/image";
+import {
+  notifyIfDecodeLimitsExceeded,
+  startSlideshow,
+  toggleFullscreen,
+  toggleZenMode,
+  updateImageStores,
+} from "$lib/core/commands";
+import { showNotification } from "$lib/utils/toastNotifications";
 
 export class AppManager {
   private unlistenImageSource: (() => void) | undefined;
+  private unlistenLaunchError: (() => void) | undefined;
   private unlistenConfig: (() => void) | undefined;
+  private unlistenCurrentImageChanged: (() => void) | undefined;
+  private unlistenDirectoryChanged: (() => void) | undefined;
+  private unlistenControlImageOpened: (() => void) | undefined;
+  private unlistenControlRotationChanged: (() => void) | undefined;
   private unsubscribeImagePath: (() => void) | undefined;
+  private unsubscribeRotation: (() => void) | undefined;
 
   /**
    * Runs AI detection on the given image path and updates the aiDetectionResult store.
@@ -39,30 +64,141 @@ export class AppManager {
 
   /**
    * Handles the "image-source" event.
-   * This event is triggered when the image source event listener is triggered,
-   * and the event payload should contain an array of strings representing the paths to the images.
-   * The function reads the first image from the paths and updates the image stores.
-   * @param {Object} event - The event object containing the paths to the images.
+   * This event is triggered when the app is launched with arguments, or a later launch hands its
+   * arguments over, and the event payload contains the image to open and the launch flags.
+   * The function reads the image, updates the image stores and applies the flags:
+   * fullscreen, zen mode and a slideshow.
+   * @param {Object} event - The event object containing the launch request.
    * @returns {Promise<void>}
    */
-  private async handleImageSourceEvent(event: { payload: string[] }): Promise<void> {
-    const paths = event.payload;
-    if (paths.length > 0) {
-      const path = paths[0];
-
-      try {
-        const [metadata, newPathStr, _directoryFiles] = await invoke<
-          [ImageMetadata, string, string[]]
-        >("read_image_from_path", { path });
-        imageUrl.set(metadata.image_data);
-        imagePath.set(newPathStr);
-        updateImageStores(metadata);
-      } catch (error) {
-        console.error("Failed to read image from path:", error);
-      }
+  private async handleImageSourceEvent(event: { payload: LaunchRequest }): Promise<void> {
+    const { path, fullscreen, zen, slideshow } = event.payload;
+
+    try {
+      const [metadata, newPathStr, _directoryFiles] = await invoke<
+        [ImageMetadata, string, string[]]
+      >("read_image_from_path", { path });
+      imageUrl.set(metadata.image_url);
+      imagePath.set(newPathStr);
+      updateImageStores(metadata);
+      zoomLevel.set(1);
+      rotation.set(0);
+      isConvertedToAscii.set(false);
+    } catch (error) {
+      console.error("Failed to read image from path:", error);
+      notifyIfDecodeLimitsExceeded(error);
+      return;
+    }
+
+    if (zen && !get(isZenModeActive)) {
+      await toggleZenMode();
+    } else if (fullscreen && !get(isFullscreenActive)) {
+      await toggleFullscreen();
+    }
+    if (slideshow) startSlideshow(slideshow);
+  }
+
+  /**
+   * Handles the "launch-error" event.
+   * This event is triggered when launch arguments are dropped, such as files that are no images
+   * or unknown options, and the event payload contains a message for each of them.
+   * @param {Object} event - The event object containing the messages.
+   */
+  private handleLaunchErrorEvent(event: { payload: string[] }) {
+    event.payload.forEach((message) => console.error("Launch argument dropped:", message));
+    showNotification(event.payload.join("\n"));
+  }
+
+  /**
+   * Handles the "current-image-changed" event.
+   * This event is triggered when the file of the displayed image is rewritten on disk,
+   * and the event payload contains the reloaded image metadata and the image path.
+   * Zoom and rotation are kept, so the refreshed image replaces the old one in place.
+   * @param {Object} event - The event object containing the image metadata and path.
+   */
+  private handleCurrentImageChangedEvent(event: { payload: [ImageMetadata, string] }) {
+    const [metadata, path] = event.payload;
+    if (path !== get(imagePath)) return;
+
+    imageUrl.set(metadata.image_url);
+    updateImageStores(metadata);
+  }
+
+  /**
+   * Handles the "directory-changed" event.
+   * This event is triggered when images are added to, removed from or renamed in the folder being viewed,
+   * or navigation continues into another folder, and the event payload contains the folder and its images.
+   * If the displayed image was removed from the folder, the image that took its place is shown instead.
+   * @param {Object} event - The event object containing the folder and its image listing.
+   * @returns {Promise<void>}
+   */
+  private async handleDirectoryChangedEvent(event: { payload: DirectoryChange }): Promise<void> {
+    const { directory, files } = event.payload;
+    const previous = get(directoryListing);
+    directoryListing.set(event.payload);
+
+    const path = get(imagePath);
+    if (!path || files.length === 0 || files.includes(path)) return;
+    const separator = path.charAt(directory.length);
+    if (!path.startsWith(directory) || (separator !== "/" && separator !== "\\")) return;
+
+    const previousIndex = previous?.directory === directory ? previous.files.indexOf(path) : -1;
+    const replacement = files[Math.min(Math.max(previousIndex, 0), files.length - 1)];
+    try {
+      const [metadata, newPath] = await invoke<[ImageMetadata, string, string[]]>(
+        "read_image_from_path",
+        { path: replacement }
+      );
+      if (get(imagePath) !== path) return;
+      imageUrl.set(metadata.image_url);
+      imagePath.set(newPath);
+      updateImageStores(metadata);
+      zoomLevel.set(1);
+      rotation.set(0);
+      isConvertedToAscii.set(false);
+    } catch (error) {
+      console.error("Failed to show the image replacing a removed one:", error);
     }
   }
 
+  /**
+   * Handles the "control-image-opened" event.
+   * This event is triggered when a script opens or navigates to an image through the control socket,
+   * and the event payload contains the image metadata and the image path.
+   * The image is shown like one opened from the window, at the default zoom and unrotated.
+   * @param {Object} event - The event object containing the image metadata and path.
+   */
+  private handleControlImageOpenedEvent(event: { payload: [ImageMetadata, string] }) {
+    const [metadata, path] = event.payload;
+    imageUrl.set(metadata.image_url);
+    imagePath.set(path);
+    updateImageStores(metadata);
+    zoomLevel.set(1);
+    rotation.set(0);
+    isConvertedToAscii.set(false);
+  }
+
+  /**
+   * Handles the "control-rotation-changed" event.
+   * This event is triggered when a script rotates the image through the control socket,
+   * and the event payload contains the new rotation angle in degrees.
+   * @param {Object} event - The event object containing the rotation angle.
+   */
+  private handleControlRotationChangedEvent(event: { payload: number }) {
+    rotation.set(event.payload);
+  }
+
+  /**
+   * Reports the rotation of the displayed image to the backend,
+   * so images exported through the control socket are rotated as shown.
+   * @param {number} angle - The rotation angle in degrees.
+   */
+  private reportRotation(angle: number) {
+    invoke("set_displayed_rotation", { rotation: angle }).catch((error) => {
+      console.error("Failed to report rotation:", error);
+    });
+  }
+
   /**
    * Handles an event from the config-updated event listener.
    * The event payload should contain an AppConfig object representing the updated app configuration.
@@ -77,8 +213,8 @@ export class AppManager {
 
   /**
    * Registers event listeners for the "image-source" and "config-updated" events.
-   * The "image-source" event is triggered when the image source event listener is triggered,
-   * and the event payload should contain an array of strings representing the paths to the images.
+   * The "image-source" event is triggered when the app is launched with arguments,
+   * and the event payload should contain a LaunchRequest with the image to open and the launch flags.
    * The "config-updated" event is triggered when the app configuration is updated,
    * and the event payload should contain an AppConfig object representing the updated app configuration.
    * The function returns an object containing two functions, unlistenImageSource and unlistenConfig,
@@ -89,11 +225,16 @@ export class AppManager {
     unlistenImageSource: () => void;
     unlistenConfig: () => void;
   }> {
-    this.unlistenImageSource = await listen<string[]>(
+    this.unlistenImageSource = await listen<LaunchRequest>(
       "image-source",
       this.handleImageSourceEvent.bind(this)
     );
 
+    this.unlistenLaunchError = await listen<string[]>(
+      "launch-error",
+      this.handleLaunchErrorEvent.bind(this)
+    );
+
     await invoke("frontend_is_ready");
 
     this.unlistenConfig = await listen<AppConfig>(
@@ -101,6 +242,26 @@ export class AppManager {
       this.handleConfigUpdatedEvent.bind(this)
     );
 
+    this.unlistenCurrentImageChanged = await listen<[ImageMetadata, string]>(
+      "current-image-changed",
+      this.handleCurrentImageChangedEvent.bind(this)
+    );
+
+    this.unlistenDirectoryChanged = await listen<DirectoryChange>(
+      "directory-changed",
+      this.handleDirectoryChangedEvent.bind(this)
+    );
+
+    this.unlistenControlImageOpened = await listen<[ImageMetadata, string]>(
+      "control-image-opened",
+      this.handleControlImageOpenedEvent.bind(this)
+    );
+
+    this.unlistenControlRotationChanged = await listen<number>(
+      "control-rotation-changed",
+      this.handleControlRotationChangedEvent.bind(this)
+    );
+
     return { unlistenImageSource: this.unlistenImageSource, unlistenConfig: this.unlistenConfig };
   }
 
@@ -126,6 +287,7 @@ export class AppManager {
     this.unlistenConfig = unlisteners.unlistenConfig;
 
     this.unsubscribeImagePath = imagePath.subscribe(this.runAiDetection.bind(this));
+    this.unsubscribeRotation = rotation.subscribe(this.reportRotation.bind(this));
 
     invoke("get_has_configured_initial_settings_command")
       .then((configured) => {
@@ -154,8 +316,14 @@ export class AppManager {
 
     return () => {
       this.unlistenImageSource?.();
+      this.unlistenLaunchError?.();
       this.unlistenConfig?.();
+      this.unlistenCurrentImageChanged?.();
+      this.unlistenDirectoryChanged?.();
+      this.unlistenControlImageOpened?.();
+      this.unlistenControlRotationChanged?.();
       this.unsubscribeImagePath?.();
+      this.unsubscribeRotation?.();
     };
   }
 }
@@ -25,6 +25,14 @@ import {
   isZoomModifierUpActive,
   isZoomModifierDownActive,
   imageFileSize,
+  imagePageCount,
+  imagePage,
+  imageDynamicRange,
+  imageSensorResolution,
+  imageAnimation,
+  imageViewBox,
+  imageColorProfile,
+  imageArchive,
   rotation,
   isConvertedToAscii,
   isGridOverlayVisible,
@@ -34,6 +42,23 @@ import {
 import { invoke } from "@tauri-apps/api/core";
 import { getCurrentWindow } from "@tauri-apps/api/window";
 import { showNotification } from "$lib/utils/toastNotifications";
+import { t } from "$lib/utils/i18n";
+
+/**
+ * The message the backend starts errors with when an image was rejected by the decoding limits.
+ */
+const DECODE_LIMITS_ERROR = "Image exceeds the decoding limits";
+
+/**
+ * Shows a notification if the given error reports an image that exceeds the decoding limits.
+ * Other errors are only logged by the caller.
+ * @param {unknown} error - the error returned by a backend command
+ */
+export const notifyIfDecodeLimitsExceeded = (error: unknown) => {
+  if (String(error).includes(DECODE_LIMITS_ERROR)) {
+    showNotification(get(t)["notification.decodeLimitsExceeded"]);
+  }
+};
 
 /**
  * Updates the image stores with the new metadata.
@@ -46,6 +71,31 @@ export const updateImageStores = (metadata: ImageMetadata) => {
   imageFormat.set(metadata.format);
   imageColorDepth.set(metadata.color_depth);
   imageFileSize.set(metadata.file_size);
+  imagePageCount.set(metadata.page_count);
+  imagePage.set(0);
+  imageDynamicRange.set(metadata.dynamic_range);
+  imageSensorResolution.set(
+    metadata.sensor_width && metadata.sensor_height
+      ? { width: metadata.sensor_width, height: metadata.sensor_height }
+      : null,
+  );
+  imageAnimation.set(
+    metadata.frame_count !== null
+      ? {
+          frameCount: metadata.frame_count,
+          frameDelays: metadata.frame_delays ?? [],
+          loopCount: metadata.loop_count ?? 0,
+          totalDuration: metadata.total_duration ?? 0,
+        }
+      : null,
+  );
+  imageViewBox.set(metadata.view_box);
+  imageColorProfile.set(metadata.color_profile);
+  imageArchive.set(
+    metadata.archive_path !== null && metadata.archive_entry !== null
+      ? { path: metadata.archive_path, entry: metadata.archive_entry }
+      : null,
+  );
 
   if (metadata.width > 0 && metadata.height > 0) {
     imageResolution.set({ width: metadata.width, height: metadata.height });
@@ -89,6 +139,26 @@ const singleShotFeedback = (actionName: string) => {
 
 // --- Actions ---
 
+/**
+ * Shows another page of the current multi-page image.
+ * Pages outside of the image's page count are ignored.
+ * @param {number} page - the index of the page to show, starting at 0
+ * @returns {Promise<void>}
+ */
+export const showImagePage = async (page: number): Promise<void> => {
+  const path = get(imagePath);
+  const pageCount = get(imagePageCount) ?? 1;
+  if (!path || page < 0 || page >= pageCount) return;
+
+  try {
+    const url = await invoke<string>("get_image_page_url", { path, page });
+    imageUrl.set(url);
+    imagePage.set(page);
+  } catch (error) {
+    console.error("Failed to show image page:", error);
+  }
+};
+
 /**
  * Opens a file dialog to select an image file, and then reads the selected file.
  * Updates the image stores with the new image data, and resets the zoom level to 1.
@@ -102,7 +172,7 @@ export const openFile = async (): Promise<void> => {
 
     if (result) {
       const [metadata, path, _] = result;
-      imageUrl.set(metadata.image_data);
+      imageUrl.set(metadata.image_url);
       imagePath.set(path);
       updateImageStores(metadata);
       zoomLevel.set(1);
@@ -111,6 +181,7 @@ export const openFile = async (): Promise<void> => {
     }
   } catch (error) {
     console.error("Failed to open and read file:", error);
+    notifyIfDecodeLimitsExceeded(error);
   } finally {
     stopFeedback("openFile");
   }
@@ -133,7 +204,7 @@ const changeImage = async (direction: "next" | "previous"): Promise<void> => {
       currentPath,
       direction,
     });
-    imageUrl.set(metadata.image_data);
+    imageUrl.set(metadata.image_url);
     imagePath.set(newPath);
     updateImageStores(metadata);
     zoomLevel.set(1);
@@ -141,25 +212,51 @@ const changeImage = async (direction: "next" | "previous"): Promise<void> => {
     isConvertedToAscii.set(false);
   } catch (error) {
     console.error("Failed to change image:", error);
+    notifyIfDecodeLimitsExceeded(error);
   }
 };
 
 /**
  * Handles all associated actions for changing the image to the previous one in the directory.
+ * Stops a running slideshow.
  */
 export const previousImage = () => {
   singleShotFeedback("previousImage");
+  stopSlideshow();
   changeImage("previous");
 };
 
 /**
  * Handles all associated actions for changing the image to the next one in the directory.
+ * Stops a running slideshow.
  */
 export const nextImage = () => {
   singleShotFeedback("nextImage");
+  stopSlideshow();
   changeImage("next");
 };
 
+let slideshowTimer: ReturnType<typeof setInterval> | undefined;
+
+/**
+ * Starts a slideshow that moves on to the next image in the directory at the given interval.
+ * The slideshow runs until the user navigates between images.
+ * @param {number} seconds - How long each image is shown, in seconds.
+ */
+export const startSlideshow = (seconds: number) => {
+  stopSlideshow();
+  slideshowTimer = setInterval(() => changeImage("next"), seconds * 1000);
+};
+
+/**
+ * Stops a running slideshow.
+ */
+export const stopSlideshow = () => {
+  if (slideshowTimer === undefined) return;
+  clearInterval(slideshowTimer);
+  slideshowTimer = undefined;
+};
+
 /**
  * Rotates the image 90 degrees to the right.
  */
@@ -178,30 +275,48 @@ export const rotateCounterclockwise = () => {
 
 /**
  * Saves the current image to a new file with a different format.
- * Uses a unified approach that works for both normal and ASCII-converted images.
+ * Images opened from disk are read by the backend directly, while ASCII-converted images
+ * are sent as base64 data.
+ * Animated images stay animated when saved as GIF, PNG or WebP, and SVG images are rasterized
+ * at the given scale. The color profile of the image is embedded, or converted to sRGB.
  * @param {string} format - The new format to save the image as (e.g., "png", "jpg").
  * @param {number | undefined} quality - The quality of the saved image (0-100).
+ * @param {{ maxFps?: number; maxDimension?: number; scale?: number; colorProfile?: "embed" | "srgb" }} options - Optional frame rate and size limits for animations, the rasterization scale of SVG images, and how the color profile is handled.
  * @returns {Promise<void>}
  */
-export const saveImageAs = async (format: string, quality: number | undefined): Promise<void> => {
+export const saveImageAs = async (
+  format: string,
+  quality: number | undefined,
+  options: {
+    maxFps?: number;
+    maxDimension?: number;
+    scale?: number;
+    colorProfile?: "embed" | "srgb";
+  } = {},
+): Promise<void> => {
   const currentPath = get(imagePath);
   if (!currentPath) return;
 
   startFeedback("saveImageAs");
   try {
+    if (!get(isConvertedToAscii)) {
+      await invoke<string | null>("save_image_as", {
+        path: currentPath,
+        format,
+        options: { ...options, quality, rotation: get(rotation) },
+      });
+      return;
+    }
+
     const imageData = get(imageUrl);
     if (imageData) {
       const base64Data = imageData.split(",")[1];
       if (base64Data) {
-        const isAscii = get(isConvertedToAscii);
-        const sourceName = isAscii ? "ascii_art" : currentPath;
-
         await invoke<string | null>("save_base64_image_as", {
           base64data: base64Data,
-          sourceName,
+          sourceName: "ascii_art",
           format,
-          quality,
-          rotation: get(rotation),
+          options: { quality, rotation: get(rotation) },
         });
       }
     }
@@ -1,4 +1,4 @@
-import type { AiDetectionResult } from "$lib/types/image";
+import type { AiDetectionResult, DirectoryChange } from "$lib/types/image";
 import { writable } from "svelte/store";
 
 export const imageUrl = writable<string | null>(null);
@@ -10,5 +10,19 @@ export const imageResolution = writable<{ width: number; height: number } | null
 export const imageAspectRatio = writable<string | null>(null);
 export const imageColorDepth = writable<number | null>(null);
 export const imageFileSize = writable<number | null>(null);
+export const imagePageCount = writable<number | null>(null);
+export const imagePage = writable<number>(0);
+export const imageDynamicRange = writable<number | null>(null);
+export const imageAnimation = writable<{
+  frameCount: number;
+  frameDelays: number[];
+  loopCount: number;
+  totalDuration: number;
+} | null>(null);
+export const imageViewBox = writable<[number, number, number, number] | null>(null);
+export const imageColorProfile = writable<string | null>(null);
+export const directoryListing = writable<DirectoryChange | null>(null);
+export const imageArchive = writable<{ path: string; entry: string } | null>(null);
+export const imageSensorResolution = writable<{ width: number; height: number } | null>(null);
 export const aiDetectionResult = writable<AiDetectionResult | null>(null);
 export const isConvertedToAscii = writable(false);
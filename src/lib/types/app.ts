@@ -47,4 +47,25 @@ export type AppConfig = {
   gridOverlayMode: "golden-ratio" | "rule-of-thirds" | "grid";
   gridColor?: string;
   gridLineStrength?: number;
+  imageCacheBudgetMb?: number;
+  prefetchCount?: number;
+  sortOrder?: "natural" | "modified" | "size" | "exif-date" | "dimensions";
+  sortDescending?: boolean;
+  toneMapping?: "aces" | "reinhard";
+  exposure?: number;
+  maxImageDimension?: number;
+  maxDecodeMemoryMb?: number;
+  convertToSrgb?: boolean;
+  recursiveBrowsing?: boolean;
+  recursiveMaxDepth?: number;
+  continueIntoNextFolder?: boolean;
+  singleInstance?: boolean;
+  controlSocket?: boolean;
+};
+
+export type LaunchRequest = {
+  path: string;
+  fullscreen: boolean;
+  zen: boolean;
+  slideshow: number | null;
 };
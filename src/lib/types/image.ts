@@ -9,7 +9,7 @@ export type AiDetectionResult = {
 };
 
 export type ImageMetadata = {
-  image_data: string;
+  image_url: string;
   exif_data: string;
   width: number;
   height: number;
@@ -17,4 +17,20 @@ export type ImageMetadata = {
   format: string;
   color_depth: number | null;
   file_size: number;
-};
\ No newline at end of file
+  page_count: number | null;
+  dynamic_range: number | null;
+  sensor_width: number | null;
+  sensor_height: number | null;
+  frame_count: number | null;
+  frame_delays: number[] | null;
+  loop_count: number | null;
+  total_duration: number | null;
+  view_box: [number, number, number, number] | null;
+  color_profile: string | null;
+  archive_path: string | null;
+  archive_entry: string | null;
+};
+export type DirectoryChange = {
+  directory: string;
+  files: string[];
+};
@@ -4,6 +4,8 @@ export type ViewportOptions = {
   zoomLevelStore: Writable<number>;
   imageUrlStore: Writable<string | null>;
   onImageDrawn?: () => void;
+  /** Returns the URL of the image rasterized at the given scale, for images that can be re-rendered sharply. */
+  renditionUrl?: (scale: number) => string | null;
 };
 
 export type ImageTransform = {